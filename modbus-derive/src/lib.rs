@@ -0,0 +1,279 @@
+//! modbus-rs的派生宏
+//!
+//! `#[derive(ModbusRegisters)]`把结构体映射到一段连续寄存器，生成`modbus_rs::ModbusRegisters`的实现。
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Ident, LitInt, LitStr, Type};
+
+const BYTE_ORDERS: [&str; 4] = ["ABCD", "DCBA", "BADC", "CDAB"];
+
+/// 为结构体生成`from_registers`/`to_registers`
+///
+/// 结构体属性：
+/// * `#[modbus(byte_order = "CDAB")]`：字段的默认字节序，缺省为ABCD
+///
+/// 字段属性`#[modbus(...)]`：
+/// * `offset = 4`：相对块起始的寄存器偏移，缺省紧接上一个字段
+/// * `ty = "i16"`：寄存器中的原始类型，缺省与字段类型相同
+/// * `byte_order = "CDAB"`：该字段的字节序
+/// * `scale = 0.1`：字段值 = 原始值 × scale，整数字段四舍五入；读写时都检查范围，超出返回`InvalidValue`
+/// * `bit = 3`：`bool`字段取寄存器中的一位，缺省为整个寄存器非0
+/// * `registers = 8`：`String`字段占用的寄存器数
+///
+/// 支持的字段类型为`u16`、`i16`、`u32`、`i32`、`f32`、`u64`、`i64`、`f64`、`bool`和`String`。
+#[proc_macro_derive(ModbusRegisters, attributes(modbus))]
+pub fn derive_modbus_registers(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+enum Kind {
+    /// 数值字段，`cast`表示需要经过f64换算
+    Number { raw: Ident, scale: Option<Expr>, cast: bool },
+    Bool { bit: Option<u8> },
+    String,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    kind: Kind,
+    offset: u16,
+    width: u16,
+    byte_order: Ident,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    offset: Option<u16>,
+    raw: Option<LitStr>,
+    byte_order: Option<Ident>,
+    scale: Option<Expr>,
+    bit: Option<u8>,
+    registers: Option<u16>,
+}
+
+fn parse_byte_order(lit: &LitStr) -> syn::Result<Ident> {
+    let value = lit.value();
+    if !BYTE_ORDERS.contains(&value.as_str()) {
+        return Err(syn::Error::new(lit.span(), "byte_order must be one of ABCD, DCBA, BADC, CDAB"));
+    }
+    Ok(Ident::new(&value, lit.span()))
+}
+
+fn number_width(name: &str) -> Option<u16> {
+    match name {
+        "u16" | "i16" => Some(1),
+        "u32" | "i32" | "f32" => Some(2),
+        "u64" | "i64" | "f64" => Some(4),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().map(Ident::to_string),
+        _ => None,
+    }
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("modbus")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("offset") {
+                attrs.offset = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("ty") {
+                attrs.raw = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("byte_order") {
+                attrs.byte_order = Some(parse_byte_order(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("scale") {
+                attrs.scale = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("bit") {
+                let lit: LitInt = meta.value()?.parse()?;
+                let bit: u8 = lit.base10_parse()?;
+                if bit >= 16 {
+                    return Err(syn::Error::new(lit.span(), "bit must be less than 16"));
+                }
+                attrs.bit = Some(bit);
+            } else if meta.path.is_ident("registers") {
+                attrs.registers = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else {
+                return Err(meta.error("unknown modbus attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let mut default_order = Ident::new("ABCD", Span::call_site());
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("modbus")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("byte_order") {
+                default_order = parse_byte_order(&meta.value()?.parse()?)?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown modbus attribute"))
+            }
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "ModbusRegisters requires named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "ModbusRegisters can only be derived for structs")),
+    };
+
+    let mut result: Vec<Field> = Vec::new();
+    let mut next_offset: u32 = 0;
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        let attrs = parse_field_attrs(field)?;
+        let field_type = type_name(&field.ty).unwrap_or_default();
+        let unsupported = || syn::Error::new_spanned(&field.ty, "unsupported field type, use a numeric type, bool or String");
+
+        let (kind, width) = match field_type.as_str() {
+            "bool" => (Kind::Bool { bit: attrs.bit }, 1),
+            "String" => {
+                let registers = attrs
+                    .registers
+                    .filter(|&registers| registers > 0)
+                    .ok_or_else(|| syn::Error::new_spanned(&field.ident, "String fields need registers = N"))?;
+                (Kind::String, registers)
+            },
+            _ => {
+                let raw = match &attrs.raw {
+                    Some(lit) => Ident::new(&lit.value(), lit.span()),
+                    None => Ident::new(&field_type, Span::call_site()),
+                };
+                let width = number_width(&raw.to_string()).ok_or_else(|| match &attrs.raw {
+                    Some(lit) => syn::Error::new(lit.span(), "ty must be one of u16, i16, u32, i32, f32, u64, i64, f64"),
+                    None => unsupported(),
+                })?;
+                if number_width(&field_type).is_none() {
+                    return Err(unsupported());
+                }
+                let cast = attrs.scale.is_some() || raw != field_type;
+                (Kind::Number { raw, scale: attrs.scale.clone(), cast }, width)
+            },
+        };
+        if attrs.bit.is_some() && !matches!(kind, Kind::Bool { .. }) {
+            return Err(syn::Error::new_spanned(&field.ident, "bit is only valid on bool fields"));
+        }
+
+        let offset = attrs.offset.map(u32::from).unwrap_or(next_offset);
+        if offset + width as u32 > u16::MAX as u32 {
+            return Err(syn::Error::new_spanned(&field.ident, "field exceeds the register address space"));
+        }
+        let field = Field {
+            ident,
+            ty: field.ty.clone(),
+            kind,
+            offset: offset as u16,
+            width,
+            byte_order: attrs.byte_order.unwrap_or_else(|| default_order.clone()),
+        };
+
+        // 只有取位的bool字段可以共享寄存器
+        let is_bit = |field: &Field| matches!(field.kind, Kind::Bool { bit: Some(_) });
+        if let Some(other) = result.iter().find(|other| {
+            other.offset < field.offset + field.width
+                && field.offset < other.offset + other.width
+                && !(is_bit(other) && is_bit(&field))
+        }) {
+            return Err(syn::Error::new_spanned(&field.ident, format!("field overlaps `{}`", other.ident)));
+        }
+
+        next_offset = offset + width as u32;
+        result.push(field);
+    }
+    Ok(result)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = parse_fields(&input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let register_count = fields.iter().map(|field| field.offset + field.width).max().unwrap_or(0);
+
+    let mut decoders = Vec::new();
+    let mut encoders = Vec::new();
+    for field in &fields {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let start = field.offset as usize;
+        let end = start + field.width as usize;
+        let byte_order = &field.byte_order;
+        let order = quote!(::modbus_rs::ByteOrder::#byte_order);
+
+        let (decode, encode) = match &field.kind {
+            Kind::Number { raw, cast: false, .. } => (
+                quote!(<#raw as ::modbus_rs::RegisterValue>::from_registers(&registers[#start..#end], #order)?),
+                quote!(registers[#start..#end].copy_from_slice(&::modbus_rs::RegisterValue::to_registers(self.#ident, #order));),
+            ),
+            Kind::Number { raw, scale, cast: true } => {
+                let scale = scale.as_ref().map_or_else(|| quote!(1.0), |scale| quote!((#scale) as f64));
+                let raw_value = format_ident!("raw_{}", ident);
+                (
+                    quote!({
+                        let #raw_value = <#raw as ::modbus_rs::RegisterValue>::from_registers(&registers[#start..#end], #order)?;
+                        <#ty as ::modbus_rs::RegisterNumber>::from_f64(::modbus_rs::RegisterNumber::to_f64(#raw_value) * #scale).ok_or_else(|| {
+                            ::modbus_rs::ModbusError::InvalidValue(format!("{} is out of range for field `{}`", #raw_value, stringify!(#ident)))
+                        })?
+                    }),
+                    quote!({
+                        let #raw_value = <#raw as ::modbus_rs::RegisterNumber>::from_f64(self.#ident as f64 / #scale).ok_or_else(|| {
+                            ::modbus_rs::ModbusError::InvalidValue(format!("{} is out of range for field `{}`", self.#ident, stringify!(#ident)))
+                        })?;
+                        registers[#start..#end].copy_from_slice(&::modbus_rs::RegisterValue::to_registers(#raw_value, #order));
+                    }),
+                )
+            },
+            Kind::Bool { bit: Some(bit) } => (
+                quote!((registers[#start] >> #bit) & 1 != 0),
+                quote!(registers[#start] |= (self.#ident as u16) << #bit;),
+            ),
+            Kind::Bool { bit: None } => (
+                quote!(registers[#start] != 0),
+                quote!(registers[#start] = self.#ident as u16;),
+            ),
+            Kind::String => {
+                let count = field.width as usize;
+                (
+                    quote!(::modbus_rs::registers_to_string(&registers[#start..#end], #order)),
+                    quote!(registers[#start..#end].copy_from_slice(&::modbus_rs::string_to_registers(&self.#ident, #count, #order)?);),
+                )
+            },
+        };
+        decoders.push(quote!(#ident: #decode));
+        encoders.push(encode);
+    }
+
+    let count = register_count as usize;
+    Ok(quote! {
+        impl #impl_generics ::modbus_rs::ModbusRegisters for #name #ty_generics #where_clause {
+            const REGISTER_COUNT: u16 = #register_count;
+
+            fn from_registers(registers: &[u16]) -> ::std::result::Result<Self, ::modbus_rs::ModbusError> {
+                if registers.len() < #count {
+                    return Err(::modbus_rs::ModbusError::InvalidDataLength);
+                }
+                Ok(Self {
+                    #(#decoders,)*
+                })
+            }
+
+            fn to_registers(&self) -> ::std::result::Result<::std::vec::Vec<u16>, ::modbus_rs::ModbusError> {
+                let mut registers = ::std::vec![0u16; #count];
+                #(#encoders)*
+                Ok(registers)
+            }
+        }
+    })
+}
@@ -0,0 +1,404 @@
+use crate::protocol::*;
+use super::master::ModbusMaster;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// 总线请求的优先级，高优先级的请求总是先于低优先级的请求执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// 控制命令等紧急请求
+    High,
+    /// 一般请求
+    #[default]
+    Normal,
+    /// 周期轮询
+    Low,
+}
+
+const PRIORITIES: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+/// 单个请求的排队选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestOptions {
+    pub priority: Priority,
+    /// 截止时间，到期时调用方立即收到`TimeoutError`：仍在排队的请求移出队列，
+    /// 已经开始的事务在总线上完成，回复被丢弃
+    pub deadline: Option<Instant>,
+}
+
+impl RequestOptions {
+    /// 默认选项：普通优先级，没有截止时间
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置优先级
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 设置截止时间
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// 设置从现在起的排队超时
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+}
+
+struct Job {
+    id: u64,
+    request: ModbusRequest,
+    deadline: Option<Instant>,
+    reply: oneshot::Sender<Result<ModbusResponse, ModbusError>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// 某一优先级的队列：调用方轮流出队，每个调用方的请求保持先后顺序
+#[derive(Default)]
+struct PriorityQueue {
+    callers: VecDeque<u64>,
+    jobs: HashMap<u64, VecDeque<Job>>,
+}
+
+impl PriorityQueue {
+    fn push(&mut self, caller: u64, job: Job) {
+        let jobs = self.jobs.entry(caller).or_default();
+        if jobs.is_empty() {
+            self.callers.push_back(caller);
+        }
+        jobs.push_back(job);
+    }
+
+    fn pop(&mut self) -> Option<Job> {
+        let caller = self.callers.pop_front()?;
+        let jobs = self.jobs.get_mut(&caller)?;
+        let job = jobs.pop_front();
+        if jobs.is_empty() {
+            self.jobs.remove(&caller);
+        } else {
+            self.callers.push_back(caller);
+        }
+        job
+    }
+
+    fn remove(&mut self, caller: u64, id: u64) -> Option<Job> {
+        let jobs = self.jobs.get_mut(&caller)?;
+        let index = jobs.iter().position(|job| job.id == id)?;
+        let job = jobs.remove(index);
+        if jobs.is_empty() {
+            self.jobs.remove(&caller);
+            self.callers.retain(|&c| c != caller);
+        }
+        job
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    queues: HashMap<Priority, PriorityQueue>,
+    len: usize,
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    notify: Notify,
+}
+
+impl Shared {
+    fn push(&self, priority: Priority, caller: u64, job: Job) {
+        let mut queues = self.queues.lock().unwrap();
+        queues.queues.entry(priority).or_default().push(caller, job);
+        queues.len += 1;
+        drop(queues);
+        self.notify.notify_one();
+    }
+
+    fn pop(&self) -> Option<Job> {
+        let mut queues = self.queues.lock().unwrap();
+        let job = PRIORITIES
+            .iter()
+            .find_map(|priority| queues.queues.get_mut(priority).and_then(PriorityQueue::pop))?;
+        queues.len -= 1;
+        Some(job)
+    }
+
+    /// 从队列中移除尚未出队的请求，请求已出队时什么也不做
+    fn remove(&self, priority: Priority, caller: u64, id: u64) {
+        let mut queues = self.queues.lock().unwrap();
+        if let Some(job) = queues.queues.get_mut(&priority).and_then(|queue| queue.remove(caller, id)) {
+            queues.len -= 1;
+            log::debug!("Dropping queued request to slave {}: deadline expired", job.request.slave_id);
+        }
+    }
+}
+
+struct Inner {
+    shared: Arc<Shared>,
+    slots: Arc<Semaphore>,
+    next_caller: AtomicU64,
+    next_job: AtomicU64,
+    stop: CancellationToken,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+/// 可共享的总线主站
+///
+/// 后台任务独占一个客户端，把多个任务的请求依次发送到同一条总线上。
+/// 高优先级的请求先执行；同一优先级内各调用方轮流执行，避免某个调用方长时间占用总线。
+/// 每个克隆都是一个独立的调用方。排队请求数达到上限时，`send`等待空位，`try_send`立即返回`QueueFull`。
+/// 所有句柄都被丢弃后后台任务退出。
+pub struct BusMaster {
+    inner: Arc<Inner>,
+    caller: u64,
+}
+
+impl Clone for BusMaster {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            caller: self.inner.next_caller.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+impl BusMaster {
+    /// 创建总线主站并启动后台任务，须在tokio运行时中调用
+    pub fn new<M: ModbusMaster>(master: M, queue_capacity: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queues: Mutex::new(Queues::default()),
+            notify: Notify::new(),
+        });
+        let stop = CancellationToken::new();
+        tokio::spawn(run_bus(master, Arc::clone(&shared), stop.clone()));
+
+        Self {
+            inner: Arc::new(Inner {
+                shared,
+                slots: Arc::new(Semaphore::new(queue_capacity)),
+                next_caller: AtomicU64::new(1),
+                next_job: AtomicU64::new(0),
+                stop,
+            }),
+            caller: 0,
+        }
+    }
+
+    /// 当前排队的请求数
+    pub fn queue_len(&self) -> usize {
+        self.inner.shared.queues.lock().unwrap().len
+    }
+
+    /// 发送请求，队列已满时等待空位（不超过截止时间）
+    pub async fn send(&self, request: ModbusRequest, options: RequestOptions) -> Result<ModbusResponse, ModbusError> {
+        let acquire = Arc::clone(&self.inner.slots).acquire_owned();
+        let permit = match options.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, acquire).await
+                .map_err(|_| ModbusError::TimeoutError)?,
+            None => acquire.await,
+        }
+        .map_err(|_| bus_stopped())?;
+        self.enqueue(request, options, permit).await
+    }
+
+    /// 发送请求，队列已满时立即返回`QueueFull`
+    pub async fn try_send(&self, request: ModbusRequest, options: RequestOptions) -> Result<ModbusResponse, ModbusError> {
+        let permit = Arc::clone(&self.inner.slots)
+            .try_acquire_owned()
+            .map_err(|_| ModbusError::QueueFull)?;
+        self.enqueue(request, options, permit).await
+    }
+
+    async fn enqueue(&self, request: ModbusRequest, options: RequestOptions, permit: OwnedSemaphorePermit) -> Result<ModbusResponse, ModbusError> {
+        let (reply, response) = oneshot::channel();
+        let id = self.inner.next_job.fetch_add(1, Ordering::Relaxed);
+        let job = Job {
+            id,
+            request,
+            deadline: options.deadline,
+            reply,
+            _permit: permit,
+        };
+        self.inner.shared.push(options.priority, self.caller, job);
+        let Some(deadline) = options.deadline else {
+            return response.await.map_err(|_| bus_stopped())?;
+        };
+        match tokio::time::timeout_at(deadline, response).await {
+            Ok(result) => result.map_err(|_| bus_stopped())?,
+            Err(_) => {
+                // 仍在排队的请求立即释放队列空位
+                self.inner.shared.remove(options.priority, self.caller, id);
+                Err(ModbusError::TimeoutError)
+            },
+        }
+    }
+}
+
+impl ModbusMaster for BusMaster {
+    fn send_request(&mut self, request: &ModbusRequest) -> impl Future<Output = Result<ModbusResponse, ModbusError>> + Send {
+        self.send(request.clone(), RequestOptions::new())
+    }
+}
+
+fn bus_stopped() -> ModbusError {
+    ModbusError::ProtocolError("Bus master stopped".to_string())
+}
+
+async fn run_bus<M: ModbusMaster>(mut master: M, shared: Arc<Shared>, stop: CancellationToken) {
+    loop {
+        let job = loop {
+            if let Some(job) = shared.pop() {
+                break job;
+            }
+            tokio::select! {
+                _ = shared.notify.notified() => {},
+                _ = stop.cancelled() => return,
+            }
+        };
+
+        // 调用方已放弃等待
+        if job.reply.is_closed() {
+            continue;
+        }
+        if job.deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            log::debug!("Dropping queued request to slave {}: deadline expired", job.request.slave_id);
+            let _ = job.reply.send(Err(ModbusError::TimeoutError));
+            continue;
+        }
+
+        let Job { request, reply, _permit: permit, .. } = job;
+        // 出队后释放队列空位
+        drop(permit);
+        let result = tokio::select! {
+            result = master.send_request(&request) => result,
+            _ = stop.cancelled() => return,
+        };
+        let _ = reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个请求占用总线一段时间，并按执行顺序记录请求地址
+    struct SlowMaster {
+        order: Arc<Mutex<Vec<u16>>>,
+    }
+
+    impl ModbusMaster for SlowMaster {
+        async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
+            self.order.lock().unwrap().push(request.address);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(ModbusResponse {
+                slave_id: request.slave_id,
+                function_code: request.function_code,
+                data: Vec::new(),
+                is_exception: false,
+                exception_code: None,
+            })
+        }
+    }
+
+    fn read(address: u16) -> ModbusRequest {
+        ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadHoldingRegisters,
+            address,
+            count: 1,
+            data: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bus_scheduling() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let bus = BusMaster::new(SlowMaster { order: Arc::clone(&order) }, 6);
+        let pause = || tokio::time::sleep(Duration::from_millis(2));
+
+        let mut tasks = Vec::new();
+        let spawn = |bus: &BusMaster, address, options| {
+            let bus = bus.clone();
+            tokio::spawn(async move { bus.send(read(address), options).await })
+        };
+        // 先占用总线
+        tasks.push(spawn(&bus, 0, RequestOptions::new()));
+        pause().await;
+
+        // 调用方A连续提交三个轮询请求，随后调用方B提交一个
+        let poller_a = Arc::new(bus.clone());
+        let low = RequestOptions::new().with_priority(Priority::Low);
+        for address in [10, 11, 12] {
+            let poller_a = Arc::clone(&poller_a);
+            tasks.push(tokio::spawn(async move { poller_a.send(read(address), low).await }));
+            pause().await;
+        }
+        tasks.push(spawn(&bus, 20, low));
+        pause().await;
+        tasks.push(spawn(&bus, 30, RequestOptions::new().with_priority(Priority::High)));
+        pause().await;
+
+        // 截止时间前没有轮到的请求被丢弃
+        let expired = bus.send(read(40), RequestOptions::new().with_timeout(Duration::from_millis(5))).await;
+        assert!(matches!(expired, Err(ModbusError::TimeoutError)));
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        // 高优先级先执行，同一优先级内调用方轮流执行
+        assert_eq!(*order.lock().unwrap(), vec![0, 30, 10, 20, 11, 12]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bus_backpressure() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let bus = BusMaster::new(SlowMaster { order }, 1);
+
+        let busy = bus.clone();
+        let first = tokio::spawn(async move { busy.send(read(0), RequestOptions::new()).await });
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        let queued = bus.clone();
+        let second = tokio::spawn(async move { queued.send(read(1), RequestOptions::new()).await });
+        tokio::time::sleep(Duration::from_millis(2)).await;
+
+        assert_eq!(bus.queue_len(), 1);
+        assert!(matches!(bus.try_send(read(2), RequestOptions::new()).await, Err(ModbusError::QueueFull)));
+        first.await.unwrap().unwrap();
+        second.await.unwrap().unwrap();
+        assert!(bus.try_send(read(3), RequestOptions::new()).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bus_deadline() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let bus = BusMaster::new(SlowMaster { order: Arc::clone(&order) }, 1);
+        let start = Instant::now();
+
+        // 执行中的请求到截止时间立即返回，不等事务结束
+        let in_flight = bus.send(read(0), RequestOptions::new().with_timeout(Duration::from_millis(5))).await;
+        assert!(matches!(in_flight, Err(ModbusError::TimeoutError)));
+        assert_eq!(start.elapsed(), Duration::from_millis(5));
+
+        // 排队中的请求超时后释放空位，后续请求可以立即入队
+        let queued = bus.send(read(1), RequestOptions::new().with_timeout(Duration::from_millis(5))).await;
+        assert!(matches!(queued, Err(ModbusError::TimeoutError)));
+        assert_eq!(bus.queue_len(), 0);
+        assert!(bus.try_send(read(2), RequestOptions::new()).await.is_ok());
+        assert_eq!(*order.lock().unwrap(), vec![0, 2]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::utils::Value;
+use super::poller::{PointValue, PollUpdate};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Instant;
+
+/// 变化订阅选项
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CovOptions {
+    /// 数值类型的死区，与上次发出的值相差不小于死区时才算变化；0表示任何变化都发出
+    pub deadband: f64,
+    /// 心跳周期，超过该时间没有变化时重发当前值
+    pub heartbeat: Option<Duration>,
+}
+
+impl CovOptions {
+    /// 默认选项：没有死区和心跳
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置死区
+    pub fn with_deadband(mut self, deadband: f64) -> Self {
+        self.deadband = deadband;
+        self
+    }
+
+    /// 设置心跳周期
+    pub fn with_heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// 判断新值相对上次发出的值是否算作变化，质量变化总是算作变化
+    pub fn is_change(&self, last: Option<&PointValue>, new: &PointValue) -> bool {
+        let Some(last) = last else {
+            return true;
+        };
+        if last.quality != new.quality {
+            return true;
+        }
+        let numeric = |value: &PointValue| value.value.as_ref().and_then(Value::as_f64);
+        match (numeric(last), numeric(new)) {
+            (Some(previous), Some(current)) if self.deadband > 0.0 => (current - previous).abs() >= self.deadband,
+            _ => last.value != new.value,
+        }
+    }
+}
+
+/// 点的变化流，由`PollerHandle::watch`创建
+///
+/// 第一个值总是发出，之后只在变化或心跳到期时发出。流被丢弃后后台过滤任务退出。
+pub struct CovStream {
+    values: mpsc::Receiver<PointValue>,
+}
+
+impl CovStream {
+    pub(crate) fn spawn(name: String, initial: Option<PointValue>, mut updates: broadcast::Receiver<PollUpdate>, options: CovOptions) -> Self {
+        let (sender, values) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut current = initial;
+            let mut last_sent: Option<PointValue> = None;
+            let mut sent_at = Instant::now();
+            if let Some(value) = &current {
+                if sender.send(value.clone()).await.is_err() {
+                    return;
+                }
+                last_sent = Some(value.clone());
+            }
+
+            loop {
+                let heartbeat = async {
+                    match (options.heartbeat, &last_sent) {
+                        (Some(heartbeat), Some(_)) => tokio::time::sleep_until(sent_at + heartbeat).await,
+                        _ => std::future::pending().await,
+                    }
+                };
+                let value = tokio::select! {
+                    _ = sender.closed() => return,
+                    _ = heartbeat => current.clone(),
+                    update = updates.recv() => match update {
+                        Ok(update) if update.name == name => {
+                            current = Some(update.value.clone());
+                            options.is_change(last_sent.as_ref(), &update.value).then_some(update.value)
+                        },
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => None,
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                };
+
+                if let Some(value) = value {
+                    if sender.send(value.clone()).await.is_err() {
+                        return;
+                    }
+                    last_sent = Some(value);
+                    sent_at = Instant::now();
+                }
+            }
+        });
+        Self { values }
+    }
+
+    /// 等待下一个值，轮询器停止后返回None
+    pub async fn next(&mut self) -> Option<PointValue> {
+        self.values.recv().await
+    }
+}
+
+impl Stream for CovStream {
+    type Item = PointValue;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.values.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::loopback::LoopbackMaster;
+    use crate::client::{PollPoint, Poller};
+    use crate::protocol::*;
+    use crate::server::MemoryService;
+    use crate::utils::DataType;
+    use std::sync::atomic::Ordering;
+
+    async fn next(stream: &mut CovStream) -> Option<PointValue> {
+        tokio::time::timeout(Duration::from_millis(100), stream.next()).await.ok().flatten()
+    }
+
+    // 时钟暂停时，运行时在所有任务空闲后才推进时间，轮询次数和发出时刻都是确定的
+    #[tokio::test(start_paused = true)]
+    async fn test_watch() {
+        let memory = MemoryService::new();
+        memory.set_f32(Table::HoldingRegisters, 0, 20.0, ByteOrder::ABCD).unwrap();
+        let master = LoopbackMaster::new(memory.clone());
+        let reads = master.requests();
+        let poller = Poller::new(master).spawn();
+        let start = Instant::now();
+
+        let interval = Duration::from_millis(10);
+        let point = PollPoint::new("power", 1, Table::HoldingRegisters, 0, interval).with_type(DataType::F32, ByteOrder::ABCD);
+        let options = CovOptions::new().with_deadband(1.0).with_heartbeat(Duration::from_millis(150));
+        let mut power = poller.watch(point, options);
+        let mut status = poller.watch(PollPoint::new("status", 1, Table::HoldingRegisters, 2, interval), CovOptions::new());
+
+        assert_eq!(next(&mut power).await.unwrap().value, Some(Value::F32(20.0)));
+        assert_eq!(next(&mut status).await.unwrap().value, Some(Value::U16(0)));
+
+        // 死区内的变化不发出
+        memory.set_f32(Table::HoldingRegisters, 0, 20.5, ByteOrder::ABCD).unwrap();
+        assert!(next(&mut power).await.is_none());
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        memory.set_f32(Table::HoldingRegisters, 0, 21.5, ByteOrder::ABCD).unwrap();
+        assert_eq!(next(&mut power).await.unwrap().value, Some(Value::F32(21.5)));
+        let sent_at = Instant::now();
+        memory.set_holding_register(2, 7);
+        assert_eq!(next(&mut status).await.unwrap().value, Some(Value::U16(7)));
+
+        // 没有变化时按心跳重发
+        let value = power.next().await.unwrap();
+        assert_eq!(value.value, Some(Value::F32(21.5)));
+        assert_eq!(sent_at.elapsed(), Duration::from_millis(150));
+
+        // 两个点合并为一次读取，每个周期读一次
+        let before = reads.load(Ordering::SeqCst);
+        tokio::time::sleep(interval * 5 + interval / 2).await;
+        assert_eq!(reads.load(Ordering::SeqCst) - before, 5);
+    }
+}
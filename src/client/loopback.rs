@@ -0,0 +1,47 @@
+//! 测试用的回环主站
+
+use crate::protocol::*;
+use crate::server::{dispatch, MemoryService, RequestContext, TransportKind};
+use super::master::ModbusMaster;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 以内存表模拟的从机：请求直接交给`dispatch`处理，响应经过TCP帧编码和解析
+pub(crate) struct LoopbackMaster {
+    memory: MemoryService,
+    requests: Arc<AtomicUsize>,
+    silent_unit: Option<u8>,
+}
+
+impl LoopbackMaster {
+    pub(crate) fn new(memory: MemoryService) -> Self {
+        Self {
+            memory,
+            requests: Arc::new(AtomicUsize::new(0)),
+            silent_unit: None,
+        }
+    }
+
+    /// 指定不回复的单元，发给它的请求返回`TimeoutError`
+    pub(crate) fn with_silent_unit(mut self, unit_id: u8) -> Self {
+        self.silent_unit = Some(unit_id);
+        self
+    }
+
+    /// 已发送请求数的计数器
+    pub(crate) fn requests(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.requests)
+    }
+}
+
+impl ModbusMaster for LoopbackMaster {
+    async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        if self.silent_unit == Some(request.slave_id) {
+            return Err(ModbusError::TimeoutError);
+        }
+        let ctx = RequestContext { unit_id: request.slave_id, peer_addr: None, transport: TransportKind::Tcp };
+        let response = dispatch(&self.memory, ctx, request).await;
+        ModbusTcp::parse_response(&ModbusTcp::build_response(&response, 1)?).map(|(_, response)| response)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::protocol::*;
+use crate::server::{MAX_READ_BITS, MAX_READ_REGISTERS, MAX_WRITE_REGISTERS};
+use crate::utils::DataConverter;
+use super::modbus_rtu_client::ModbusRtuClient;
+use super::modbus_rtu_over_tcp_client::ModbusRtuOverTcpClient;
+use super::modbus_tcp_client::ModbusTcpClient;
+use std::future::Future;
+
+/// 主站接口：发送原始请求并返回原始响应
+///
+/// 三种客户端都实现了该接口，网关等组件通过它与任意传输方式的下游设备通信。
+/// 从机回复的异常响应作为`Ok`返回，由调用方检查`is_exception`。
+pub trait ModbusMaster: Send + 'static {
+    /// 发送请求并等待响应，请求中的`slave_id`决定目标从机
+    fn send_request(&mut self, request: &ModbusRequest) -> impl Future<Output = Result<ModbusResponse, ModbusError>> + Send;
+
+    /// 放弃上一次未完成的事务，使迟到的回复不会被当作下一个请求的响应
+    ///
+    /// 调用方在`send_request`超时或被取消后调用。TCP类客户端重新建立连接，
+    /// RTU客户端清空串口接收缓冲区；默认实现什么也不做。
+    fn reset(&mut self) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async { Ok(()) }
+    }
+
+    /// 读取保持寄存器或输入寄存器，从机异常返回`ModbusError::Exception`
+    ///
+    /// `count`须在1～125之间，否则返回`InvalidDataLength`。
+    fn read_registers(&mut self, unit_id: u8, table: Table, address: u16, count: u16) -> impl Future<Output = Result<Vec<u16>, ModbusError>> + Send {
+        async move {
+            if count == 0 || count > MAX_READ_REGISTERS {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            let function_code = match table {
+                Table::HoldingRegisters => FunctionCode::ReadHoldingRegisters,
+                Table::InputRegisters => FunctionCode::ReadInputRegisters,
+                _ => return Err(ModbusError::ProtocolError(format!("{:?} is not a register table", table))),
+            };
+            let response = self.send_request(&read_request(unit_id, function_code, address, count)).await?;
+            let data = response_data(response)?;
+            if data.len() != count as usize * 2 {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            DataConverter::bytes_to_u16_array(&data, ByteOrder::ABCD)
+        }
+    }
+
+    /// 读取线圈或离散输入，从机异常返回`ModbusError::Exception`
+    ///
+    /// `count`须在1～2000之间，否则返回`InvalidDataLength`。
+    fn read_bits(&mut self, unit_id: u8, table: Table, address: u16, count: u16) -> impl Future<Output = Result<Vec<bool>, ModbusError>> + Send {
+        async move {
+            if count == 0 || count > MAX_READ_BITS {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            let function_code = match table {
+                Table::Coils => FunctionCode::ReadCoils,
+                Table::DiscreteInputs => FunctionCode::ReadDiscreteInputs,
+                _ => return Err(ModbusError::ProtocolError(format!("{:?} is not a bit table", table))),
+            };
+            let response = self.send_request(&read_request(unit_id, function_code, address, count)).await?;
+            let data = response_data(response)?;
+            if data.len() < (count as usize).div_ceil(8) {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            Ok(DataConverter::bytes_to_bool_array(&data, count as usize))
+        }
+    }
+
+    /// 按地址读取寄存器，地址可由`"40001"`、`"HR100"`等写法解析
+    fn read_registers_at(&mut self, unit_id: u8, address: ModbusAddress, count: u16) -> impl Future<Output = Result<Vec<u16>, ModbusError>> + Send {
+        self.read_registers(unit_id, address.table, address.offset, count)
+    }
+
+    /// 按地址读取线圈或离散输入
+    fn read_bits_at(&mut self, unit_id: u8, address: ModbusAddress, count: u16) -> impl Future<Output = Result<Vec<bool>, ModbusError>> + Send {
+        self.read_bits(unit_id, address.table, address.offset, count)
+    }
+
+    /// 写多个保持寄存器（功能码0x10），从机异常返回`ModbusError::Exception`
+    ///
+    /// 一次最多写123个寄存器，超出或为空时返回`InvalidDataLength`，不发送请求。
+    fn write_registers(&mut self, unit_id: u8, address: u16, values: &[u16]) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        let request = ModbusRequest {
+            slave_id: unit_id,
+            function_code: FunctionCode::WriteMultipleRegisters,
+            address,
+            count: values.len() as u16,
+            data: Some(DataConverter::u16_array_to_bytes(values, ByteOrder::ABCD)),
+        };
+        let valid = !values.is_empty() && values.len() <= MAX_WRITE_REGISTERS as usize;
+        async move {
+            if !valid {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            let response = self.send_request(&request).await?;
+            response_data(response).map(|_| ())
+        }
+    }
+
+    /// 按地址写多个保持寄存器，其他数据表返回错误
+    fn write_registers_at(&mut self, unit_id: u8, address: ModbusAddress, values: &[u16]) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move {
+            if address.table != Table::HoldingRegisters {
+                return Err(ModbusError::ProtocolError(format!("{:?} is not a writable register table", address.table)));
+            }
+            self.write_registers(unit_id, address.offset, values).await
+        }
+    }
+}
+
+fn read_request(slave_id: u8, function_code: FunctionCode, address: u16, count: u16) -> ModbusRequest {
+    ModbusRequest {
+        slave_id,
+        function_code,
+        address,
+        count,
+        data: None,
+    }
+}
+
+/// 取出响应数据，异常响应转换为错误
+fn response_data(response: ModbusResponse) -> Result<Vec<u8>, ModbusError> {
+    if response.is_exception {
+        return Err(ModbusError::Exception(response.exception_code.unwrap_or(ExceptionCode::SlaveDeviceFailure)));
+    }
+    Ok(response.data)
+}
+
+impl ModbusMaster for ModbusTcpClient {
+    fn send_request(&mut self, request: &ModbusRequest) -> impl Future<Output = Result<ModbusResponse, ModbusError>> + Send {
+        ModbusTcpClient::send_request(self, request)
+    }
+
+    fn reset(&mut self) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        ModbusTcpClient::reconnect(self)
+    }
+}
+
+impl ModbusMaster for ModbusRtuClient {
+    fn send_request(&mut self, request: &ModbusRequest) -> impl Future<Output = Result<ModbusResponse, ModbusError>> + Send {
+        ModbusRtuClient::send_request(self, request)
+    }
+
+    fn reset(&mut self) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        ModbusRtuClient::reset(self)
+    }
+}
+
+impl ModbusMaster for ModbusRtuOverTcpClient {
+    fn send_request(&mut self, request: &ModbusRequest) -> impl Future<Output = Result<ModbusResponse, ModbusError>> + Send {
+        ModbusRtuOverTcpClient::send_request(self, request)
+    }
+
+    fn reset(&mut self) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        ModbusRtuOverTcpClient::reconnect(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{ModbusRtuOverTcpServer, ModbusTcpServer};
+    use tokio::net::TcpListener;
+
+    /// 在随机端口接受一个连接后立即关闭，模拟下游设备断开
+    async fn closing_peer() -> (u16, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+        });
+        (port, task)
+    }
+
+    async fn check_recovery<M: ModbusMaster>(client: &mut M) {
+        // 对端已关闭，请求失败且连接被重置
+        assert!(client.read_registers(1, Table::HoldingRegisters, 0, 1).await.is_err());
+        client.reset().await.unwrap();
+        assert_eq!(client.read_registers(1, Table::HoldingRegisters, 0, 1).await.unwrap(), vec![42]);
+    }
+
+    #[tokio::test]
+    async fn test_reset_after_peer_closed() {
+        let (port, peer) = closing_peer().await;
+        let mut client = ModbusTcpClient::new("127.0.0.1", port, 1).await.unwrap();
+        peer.await.unwrap();
+        // 下游设备在同一端口重新启动
+        let server = ModbusTcpServer::new(&format!("127.0.0.1:{}", port), 1).await.unwrap();
+        server.set_holding_register(0, 42);
+        tokio::spawn(async move { server.run().await });
+        check_recovery(&mut client).await;
+
+        let (port, peer) = closing_peer().await;
+        let mut client = ModbusRtuOverTcpClient::new("127.0.0.1", port, 1).await.unwrap();
+        peer.await.unwrap();
+        let server = ModbusRtuOverTcpServer::new(&format!("127.0.0.1:{}", port), 1).await.unwrap();
+        server.set_holding_register(0, 42);
+        tokio::spawn(async move { server.run().await });
+        check_recovery(&mut client).await;
+    }
+}
@@ -1,7 +1,22 @@
 pub mod modbus_rtu_client;
 pub mod modbus_tcp_client;
 pub mod modbus_rtu_over_tcp_client;
+pub mod master;
+pub mod typed_access;
+pub mod scaling;
+pub mod bus;
+pub mod poller;
+pub mod cov;
+pub mod sunspec;
+#[cfg(test)]
+mod loopback;
 
 pub use modbus_rtu_client::*;
 pub use modbus_tcp_client::*;
 pub use modbus_rtu_over_tcp_client::*;
+pub use master::*;
+pub use typed_access::*;
+pub use scaling::*;
+pub use bus::*;
+pub use poller::*;
+pub use cov::*;
@@ -1,7 +1,7 @@
 use crate::protocol::*;
 use crate::utils::DataConverter;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio_serial::SerialStream;
+use tokio_serial::{ClearBuffer, SerialPort, SerialStream};
 use std::time::Duration;
 
 /// Modbus RTU客户端
@@ -244,31 +244,53 @@ impl ModbusRtuClient {
         Ok(())
     }
     
+    /// 超时后清空接收缓冲区
+    pub(crate) async fn reset(&mut self) -> Result<(), ModbusError> {
+        self.port.clear(ClearBuffer::Input)?;
+        Ok(())
+    }
+    
     /// 发送请求并接收响应
-    async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
+    ///
+    /// 读取到完整的响应帧或超时为止；从机的异常响应作为正常结果返回。
+    pub(crate) async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
         // 构建请求帧
         let frame = ModbusRtu::build_request(request)?;
         
+        // 丢弃上一次请求超时后才到达的残留数据
+        self.port.clear(ClearBuffer::Input)?;
+        
         // 发送请求
         self.port.write_all(&frame).await?;
         self.port.flush().await?;
         
-        // 等待响应
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        
-        // 读取响应
-        let mut buffer = vec![0u8; 256];
-        let bytes_read = tokio::time::timeout(
-            self.timeout,
-            self.port.read(&mut buffer)
-        ).await
+        // 读取响应，直到收到完整的帧
+        let mut buffer = Vec::with_capacity(256);
+        tokio::time::timeout(self.timeout, async {
+            let mut chunk = [0u8; 256];
+            loop {
+                let bytes_read = self.port.read(&mut chunk).await?;
+                if bytes_read == 0 {
+                    return Err(ModbusError::ProtocolError("No response received".to_string()));
+                }
+                buffer.extend_from_slice(&chunk[..bytes_read]);
+                if ModbusRtu::response_frame_len(&buffer).is_some_and(|len| buffer.len() >= len) {
+                    return Ok(());
+                }
+            }
+        }).await
         .map_err(|_| ModbusError::TimeoutError)??;
         
-        if bytes_read == 0 {
-            return Err(ModbusError::ProtocolError("No response received".to_string()));
+        // 解析响应
+        let len = ModbusRtu::response_frame_len(&buffer).unwrap_or(buffer.len());
+        let response = ModbusRtu::parse_response(&buffer[..len])?;
+        if request.slave_id != 0 && response.slave_id != request.slave_id {
+            return Err(ModbusError::ProtocolError(format!(
+                "Response from slave {} does not match request to slave {}",
+                response.slave_id, request.slave_id
+            )));
         }
         
-        // 解析响应
-        ModbusRtu::parse_response(&buffer[..bytes_read])
+        Ok(response)
     }
 }
@@ -2,12 +2,15 @@ use crate::protocol::*;
 use crate::utils::DataConverter;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use std::net::SocketAddr;
 use std::time::Duration;
 use std::sync::atomic::{AtomicU16, Ordering};
 
 /// Modbus TCP客户端
 pub struct ModbusTcpClient {
     stream: TcpStream,
+    /// 建立连接时解析出的地址，重连时使用
+    addr: SocketAddr,
     slave_id: u8,
     timeout: Duration,
     transaction_id: AtomicU16,
@@ -19,9 +22,11 @@ impl ModbusTcpClient {
         let addr = format!("{}:{}", host, port);
         let stream = TcpStream::connect(&addr).await
             .map_err(|e| ModbusError::NetworkError(e.to_string()))?;
+        let addr = stream.peer_addr()?;
         
         Ok(Self {
             stream,
+            addr,
             slave_id,
             timeout: Duration::from_millis(5000),
             transaction_id: AtomicU16::new(1),
@@ -33,6 +38,15 @@ impl ModbusTcpClient {
         self.timeout = timeout;
     }
     
+    /// 重新连接到同一地址，丢弃旧连接上尚未到达的回复
+    ///
+    /// 使用创建时解析的地址，对端已关闭或重置连接时同样可用。
+    pub async fn reconnect(&mut self) -> Result<(), ModbusError> {
+        self.stream = TcpStream::connect(self.addr).await
+            .map_err(|e| ModbusError::NetworkError(e.to_string()))?;
+        Ok(())
+    }
+    
     /// 读取线圈
     pub async fn read_coils(&mut self, address: u16, count: u16) -> Result<Vec<bool>, ModbusError> {
         self.read_coils_with_slave_id(self.slave_id, address, count).await
@@ -250,7 +264,7 @@ impl ModbusTcpClient {
     }
     
     /// 发送请求并接收响应
-    async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
+    pub(crate) async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
         // 获取事务ID
         let transaction_id = self.transaction_id.fetch_add(1, Ordering::SeqCst);
         
@@ -0,0 +1,534 @@
+use crate::protocol::*;
+use crate::server::{MAX_READ_BITS, MAX_READ_REGISTERS};
+use crate::utils::{DataType, Value};
+use super::cov::{CovOptions, CovStream};
+use super::master::ModbusMaster;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// 设备通信失败后的最大退避时间
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// 设备通信失败后第一次退避的时间，之后每次失败加倍
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 轮询点
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollPoint {
+    /// 点名称，在同一轮询器中唯一
+    pub name: String,
+    pub unit_id: u8,
+    pub table: Table,
+    pub address: u16,
+    pub data_type: DataType,
+    /// 字符串占用的寄存器数
+    pub length: Option<u16>,
+    pub byte_order: ByteOrder,
+    /// 轮询周期
+    pub interval: Duration,
+}
+
+impl PollPoint {
+    /// 创建轮询点，位表默认为`bool`，寄存器表默认为`u16`
+    pub fn new(name: impl Into<String>, unit_id: u8, table: Table, address: u16, interval: Duration) -> Self {
+        Self {
+            name: name.into(),
+            unit_id,
+            table,
+            address,
+            data_type: if table.is_bit() { DataType::Bool } else { DataType::U16 },
+            length: None,
+            byte_order: ByteOrder::ABCD,
+            interval,
+        }
+    }
+
+    /// 按地址创建轮询点，例如`"30001".parse()?`
+    pub fn at(name: impl Into<String>, unit_id: u8, address: ModbusAddress, interval: Duration) -> Self {
+        Self::new(name, unit_id, address.table, address.offset, interval)
+    }
+
+    /// 设置数据类型和字节序
+    pub fn with_type(mut self, data_type: DataType, byte_order: ByteOrder) -> Self {
+        self.data_type = data_type;
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// 设置字符串占用的寄存器数
+    pub fn with_length(mut self, length: u16) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// 点占用的地址数
+    pub fn width(&self) -> u16 {
+        self.data_type.width(self.length).max(1)
+    }
+}
+
+/// 点值的质量
+#[derive(Debug, Clone, PartialEq)]
+pub enum Quality {
+    Good,
+    /// 从机回复了异常码
+    Exception(ExceptionCode),
+    /// 超时、连接断开等通信故障
+    CommFailure,
+    /// 数据无法按类型解码
+    InvalidData,
+}
+
+/// 点的最新值
+///
+/// 读取失败时保留上一次成功读到的值，只更新质量和时间。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointValue {
+    pub value: Option<Value>,
+    pub quality: Quality,
+    pub timestamp: Instant,
+}
+
+/// 轮询结果通知
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollUpdate {
+    pub name: String,
+    pub unit_id: u8,
+    pub value: PointValue,
+}
+
+/// 合并后的一次读请求
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadBlock {
+    pub unit_id: u8,
+    pub table: Table,
+    pub address: u16,
+    pub count: u16,
+    pub interval: Duration,
+    points: Vec<usize>,
+}
+
+impl ReadBlock {
+    /// 该读请求覆盖的点在轮询器中的序号
+    pub fn points(&self) -> &[usize] {
+        &self.points
+    }
+}
+
+/// 轮询器
+///
+/// 同一单元、数据表和周期的点按地址排序后合并为尽量少的读请求，
+/// 单次读取不超过125个寄存器或2000个位，相邻点之间最多跳过`gap_tolerance`个地址。
+/// 某个设备通信失败时，它的点标记为`CommFailure`，该设备按指数退避重试，不影响其他设备的轮询。
+pub struct Poller<M: ModbusMaster> {
+    master: M,
+    points: Vec<PollPoint>,
+    gap_tolerance: u16,
+    max_backoff: Duration,
+}
+
+impl<M: ModbusMaster> Poller<M> {
+    /// 使用主站创建轮询器，多个设备共用时可以传入`BusMaster`
+    pub fn new(master: M) -> Self {
+        Self {
+            master,
+            points: Vec::new(),
+            gap_tolerance: 0,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// 添加轮询点
+    pub fn with_point(mut self, point: PollPoint) -> Self {
+        self.points.push(point);
+        self
+    }
+
+    /// 添加多个轮询点
+    pub fn with_points(mut self, points: impl IntoIterator<Item = PollPoint>) -> Self {
+        self.points.extend(points);
+        self
+    }
+
+    /// 设置合并读请求时允许跳过的最大地址数
+    pub fn with_gap_tolerance(mut self, gap_tolerance: u16) -> Self {
+        self.gap_tolerance = gap_tolerance;
+        self
+    }
+
+    /// 设置设备通信失败后的最大退避时间
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// 获取轮询点
+    pub fn points(&self) -> &[PollPoint] {
+        &self.points
+    }
+
+    /// 计算合并后的读请求
+    pub fn plan(&self) -> Vec<ReadBlock> {
+        plan_blocks(&self.points, self.gap_tolerance)
+    }
+
+    /// 在后台开始轮询，须在tokio运行时中调用
+    pub fn spawn(self) -> PollerHandle {
+        let (updates, _) = broadcast::channel(self.points.len().max(16) * 4);
+        let latest = Arc::new(Mutex::new(HashMap::new()));
+        let (points, added) = mpsc::unbounded_channel();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(run_poller(self, Arc::clone(&latest), updates.clone(), added, stop.clone()));
+
+        PollerHandle {
+            latest,
+            updates,
+            points,
+            stop,
+            task: Some(task),
+        }
+    }
+}
+
+/// 运行中的轮询器句柄，句柄被丢弃时轮询停止
+pub struct PollerHandle {
+    latest: Arc<Mutex<HashMap<String, PointValue>>>,
+    updates: broadcast::Sender<PollUpdate>,
+    points: mpsc::UnboundedSender<PollPoint>,
+    stop: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl PollerHandle {
+    /// 订阅每次轮询的结果
+    pub fn subscribe(&self) -> broadcast::Receiver<PollUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// 在运行中添加轮询点，已有同名点时忽略；新点与已有的点一起合并读取
+    pub fn add_point(&self, point: PollPoint) {
+        let _ = self.points.send(point);
+    }
+
+    /// 订阅点的变化，点不存在时先添加到轮询器
+    ///
+    /// 多个订阅共享轮询器的读取，同一设备上的点按规则合并为尽量少的读请求。
+    pub fn watch(&self, point: PollPoint, options: CovOptions) -> CovStream {
+        let name = point.name.clone();
+        // 先订阅再添加，避免错过第一次轮询的结果
+        let updates = self.updates.subscribe();
+        let initial = self.value(&name);
+        self.add_point(point);
+        CovStream::spawn(name, initial, updates, options)
+    }
+
+    /// 获取点的最新值
+    pub fn value(&self, name: &str) -> Option<PointValue> {
+        self.latest.lock().unwrap().get(name).cloned()
+    }
+
+    /// 获取所有点的最新值
+    pub fn values(&self) -> HashMap<String, PointValue> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// 停止轮询并等待后台任务结束
+    pub async fn stop(mut self) {
+        self.stop.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for PollerHandle {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+fn plan_blocks(points: &[PollPoint], gap_tolerance: u16) -> Vec<ReadBlock> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by_key(|&i| {
+        let point = &points[i];
+        (point.unit_id, point.table as u8, point.interval, point.address)
+    });
+
+    let mut blocks: Vec<ReadBlock> = Vec::new();
+    for i in order {
+        let point = &points[i];
+        let start = point.address as u32;
+        let end = start + point.width() as u32 - 1;
+        let max = if point.table.is_bit() { MAX_READ_BITS } else { MAX_READ_REGISTERS } as u32;
+
+        if let Some(block) = blocks.last_mut() {
+            let block_start = block.address as u32;
+            let block_end = block_start + block.count as u32 - 1;
+            let same_group = block.unit_id == point.unit_id && block.table == point.table && block.interval == point.interval;
+            if same_group && start <= block_end + 1 + gap_tolerance as u32 && end.max(block_end) - block_start < max {
+                block.count = (end.max(block_end) - block_start + 1) as u16;
+                block.points.push(i);
+                continue;
+            }
+        }
+        blocks.push(ReadBlock {
+            unit_id: point.unit_id,
+            table: point.table,
+            address: point.address,
+            count: (end - start + 1).min(0xFFFF) as u16,
+            interval: point.interval,
+            points: vec![i],
+        });
+    }
+    blocks
+}
+
+struct DeviceState {
+    failures: u32,
+    retry_at: Instant,
+}
+
+async fn run_poller<M: ModbusMaster>(
+    mut poller: Poller<M>,
+    latest: Arc<Mutex<HashMap<String, PointValue>>>,
+    updates: broadcast::Sender<PollUpdate>,
+    mut added: mpsc::UnboundedReceiver<PollPoint>,
+    stop: CancellationToken,
+) {
+    let mut blocks = poller.plan();
+    let mut next_due = vec![Instant::now(); blocks.len()];
+    let mut devices: HashMap<u8, DeviceState> = HashMap::new();
+
+    loop {
+        let next = next_due.iter().copied().enumerate().min_by_key(|(_, due)| *due);
+        let sleep = async {
+            match next {
+                Some((_, due)) => tokio::time::sleep_until(due).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = sleep => {},
+            Some(point) = added.recv() => {
+                if !poller.points.iter().any(|existing| existing.name == point.name) {
+                    poller.points.push(point);
+                    let replanned = poller.plan();
+                    next_due = reschedule(&blocks, &next_due, &replanned);
+                    blocks = replanned;
+                }
+                continue;
+            },
+            _ = stop.cancelled() => return,
+        }
+        let Some((index, due)) = next else {
+            continue;
+        };
+
+        let block = &blocks[index];
+        let now = Instant::now();
+        // 跳过错过的周期，避免总线恢复后集中补读
+        next_due[index] = (due + block.interval).max(now);
+
+        if devices.get(&block.unit_id).is_some_and(|device| now < device.retry_at) {
+            continue;
+        }
+
+        let result = tokio::select! {
+            result = read_block(&mut poller.master, block) => result,
+            _ = stop.cancelled() => return,
+        };
+        let timestamp = Instant::now();
+
+        let outcomes: Vec<(usize, Option<Value>, Quality)> = match result {
+            Ok(registers) => {
+                if let Some(device) = devices.remove(&block.unit_id) {
+                    log::info!("Unit {} is responding again after {} failed polls", block.unit_id, device.failures);
+                }
+                block.points.iter().map(|&i| {
+                    let point = &poller.points[i];
+                    let offset = (point.address - block.address) as usize;
+                    let words = &registers[offset..offset + point.width() as usize];
+                    match point.data_type.decode(words, point.byte_order) {
+                        Ok(value) => (i, Some(value), Quality::Good),
+                        Err(_) => (i, None, Quality::InvalidData),
+                    }
+                }).collect()
+            },
+            Err(ModbusError::Exception(code)) => {
+                log::debug!("Unit {} replied {:?} to poll of {:?} {}", block.unit_id, code, block.table, block.address);
+                block.points.iter().map(|&i| (i, None, Quality::Exception(code))).collect()
+            },
+            Err(e) => {
+                let device = devices.entry(block.unit_id).or_insert(DeviceState { failures: 0, retry_at: now });
+                device.failures += 1;
+                let backoff = INITIAL_BACKOFF.saturating_mul(1 << (device.failures - 1).min(16)).min(poller.max_backoff);
+                device.retry_at = timestamp + backoff;
+                if device.failures == 1 {
+                    log::warn!("Polling unit {} failed: {}", block.unit_id, e);
+                }
+                block.points.iter().map(|&i| (i, None, Quality::CommFailure)).collect()
+            },
+        };
+
+        let mut latest = latest.lock().unwrap();
+        for (i, value, quality) in outcomes {
+            let point = &poller.points[i];
+            // 读取失败时保留上一次的值
+            let value = value.or_else(|| latest.get(&point.name).and_then(|previous| previous.value.clone()));
+            let value = PointValue { value, quality, timestamp };
+            latest.insert(point.name.clone(), value.clone());
+            let _ = updates.send(PollUpdate {
+                name: point.name.clone(),
+                unit_id: point.unit_id,
+                value,
+            });
+        }
+    }
+}
+
+/// 重新规划后沿用原有的轮询时间，新加入的点所在的块立即读取
+fn reschedule(blocks: &[ReadBlock], next_due: &[Instant], replanned: &[ReadBlock]) -> Vec<Instant> {
+    let mut due_by_point = HashMap::new();
+    for (block, due) in blocks.iter().zip(next_due) {
+        for &point in &block.points {
+            due_by_point.insert(point, *due);
+        }
+    }
+    let now = Instant::now();
+    replanned
+        .iter()
+        .map(|block| {
+            block.points.iter().map(|point| due_by_point.get(point).copied().unwrap_or(now)).min().unwrap_or(now)
+        })
+        .collect()
+}
+
+/// 读取一个合并块，位以0/1寄存器表示
+async fn read_block<M: ModbusMaster>(master: &mut M, block: &ReadBlock) -> Result<Vec<u16>, ModbusError> {
+    if block.table.is_bit() {
+        let bits = master.read_bits(block.unit_id, block.table, block.address, block.count).await?;
+        Ok(bits.into_iter().map(u16::from).collect())
+    } else {
+        master.read_registers(block.unit_id, block.table, block.address, block.count).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::loopback::LoopbackMaster;
+    use crate::server::MemoryService;
+    use crate::utils::u48_to_registers;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_plan_coalescing() {
+        let second = Duration::from_secs(1);
+        let master = LoopbackMaster::new(MemoryService::new());
+        let poller = Poller::new(master)
+            .with_gap_tolerance(2)
+            .with_point(PollPoint::new("a", 1, Table::HoldingRegisters, 0, second))
+            .with_point(PollPoint::new("b", 1, Table::HoldingRegisters, 3, second).with_type(DataType::F32, ByteOrder::ABCD))
+            .with_point(PollPoint::new("c", 1, Table::HoldingRegisters, 8, second))
+            .with_point(PollPoint::new("d", 1, Table::HoldingRegisters, 120, second))
+            .with_point(PollPoint::new("e", 1, Table::HoldingRegisters, 130, second))
+            .with_point(PollPoint::new("f", 1, Table::HoldingRegisters, 1, second * 5))
+            .with_point(PollPoint::new("g", 1, Table::Coils, 0, second))
+            .with_point(PollPoint::new("h", 1, Table::Coils, 3, second));
+
+        let blocks: Vec<(Table, u16, u16)> = poller.plan().iter().map(|block| (block.table, block.address, block.count)).collect();
+        assert_eq!(blocks, vec![
+            (Table::Coils, 0, 4),
+            (Table::HoldingRegisters, 0, 5),
+            (Table::HoldingRegisters, 8, 1),
+            // 120～130共11个寄存器，间隔超过容差
+            (Table::HoldingRegisters, 120, 1),
+            (Table::HoldingRegisters, 130, 1),
+            (Table::HoldingRegisters, 1, 1),
+        ]);
+
+        // 合并后不超过单次读取的上限
+        let poller = Poller::new(LoopbackMaster::new(MemoryService::new()))
+            .with_gap_tolerance(2000)
+            .with_points([0, 124, 125].map(|address| PollPoint::new(format!("r{}", address), 1, Table::InputRegisters, address, second)))
+            .with_points([0, 1999, 2000].map(|address| PollPoint::new(format!("i{}", address), 1, Table::DiscreteInputs, address, second)));
+        let blocks: Vec<(u16, u16)> = poller.plan().iter().map(|block| (block.address, block.count)).collect();
+        assert_eq!(blocks, vec![(0, 2000), (2000, 1), (0, 125), (125, 1)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_coalesced_64_bit_points() {
+        let memory = MemoryService::new();
+        memory.set_holding_register(0, 7);
+        memory.set_u64(Table::HoldingRegisters, 1, 0x0123_4567_89AB_CDEF, ByteOrder::CDAB).unwrap();
+        memory.set_registers(Table::HoldingRegisters, 5, &u48_to_registers(0x0000_1234_5678_9ABC, ByteOrder::ABCD).unwrap()).unwrap();
+        memory.set_value(Table::HoldingRegisters, 8, -1.25f64, ByteOrder::ABCD).unwrap();
+
+        let interval = Duration::from_millis(20);
+        let master = LoopbackMaster::new(memory);
+        let requests = master.requests();
+        let poller = Poller::new(master)
+            .with_point(PollPoint::new("status", 1, Table::HoldingRegisters, 0, interval))
+            .with_point(PollPoint::new("energy", 1, Table::HoldingRegisters, 1, interval).with_type(DataType::U64, ByteOrder::CDAB))
+            .with_point(PollPoint::new("counter", 1, Table::HoldingRegisters, 5, interval).with_type(DataType::U48, ByteOrder::ABCD))
+            .with_point(PollPoint::new("total", 1, Table::HoldingRegisters, 8, interval).with_type(DataType::F64, ByteOrder::ABCD));
+
+        // 四个点合并为一次读取，64位点的宽度为4个寄存器
+        let blocks: Vec<(u16, u16)> = poller.plan().iter().map(|block| (block.address, block.count)).collect();
+        assert_eq!(blocks, vec![(0, 12)]);
+
+        let handle = poller.spawn();
+        tokio::time::sleep(interval / 2).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        let value = |name| handle.value(name).unwrap().value;
+        assert_eq!(value("status"), Some(Value::U16(7)));
+        assert_eq!(value("energy"), Some(Value::U64(0x0123_4567_89AB_CDEF)));
+        assert_eq!(value("counter"), Some(Value::U64(0x0000_1234_5678_9ABC)));
+        assert_eq!(value("total"), Some(Value::F64(-1.25)));
+        handle.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_polling() {
+        let memory = MemoryService::new();
+        memory.set_f32(Table::InputRegisters, 10, 21.5, ByteOrder::CDAB).unwrap();
+        memory.set_coil(3, true);
+
+        let interval = Duration::from_millis(20);
+        // 单元2不回复
+        let handle = Poller::new(LoopbackMaster::new(memory.clone()).with_silent_unit(2))
+            .with_point(PollPoint::new("temperature", 1, Table::InputRegisters, 10, interval).with_type(DataType::F32, ByteOrder::CDAB))
+            .with_point(PollPoint::new("running", 1, Table::Coils, 3, interval))
+            .with_point(PollPoint::new("missing", 1, Table::HoldingRegisters, 0xFFFF, interval).with_type(DataType::U32, ByteOrder::ABCD))
+            .with_point(PollPoint::new("offline", 2, Table::HoldingRegisters, 0, interval))
+            .spawn();
+        let start = Instant::now();
+
+        // 时钟暂停，启动时的第一轮轮询在时间推进前完成
+        tokio::time::sleep(interval / 2).await;
+        let value = |name| handle.value(name).unwrap();
+        assert_eq!(value("temperature").value, Some(Value::F32(21.5)));
+        assert_eq!(value("temperature").timestamp, start);
+        assert_eq!(value("running").value, Some(Value::Bool(true)));
+        assert_eq!(value("missing").quality, Quality::Exception(ExceptionCode::IllegalDataAddress));
+        assert_eq!(value("offline").quality, Quality::CommFailure);
+
+        // 新值在下一个周期发布
+        let mut updates = handle.subscribe();
+        memory.set_coil(3, false);
+        loop {
+            let update = updates.recv().await.unwrap();
+            if update.name == "running" {
+                assert_eq!(update.value.value, Some(Value::Bool(false)));
+                assert_eq!(update.value.quality, Quality::Good);
+                assert_eq!(update.value.timestamp, start + interval);
+                break;
+            }
+        }
+        handle.stop().await;
+    }
+}
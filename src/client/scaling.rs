@@ -0,0 +1,298 @@
+use crate::protocol::*;
+use crate::server::MAX_READ_REGISTERS;
+use crate::utils::{i48_to_registers, u48_to_registers, DataType, RegisterNumber, RegisterValue, I48_MAX, I48_MIN, U48_MAX};
+use super::master::ModbusMaster;
+use std::future::Future;
+
+/// SunSpec中表示"未实现"的比例因子
+const SCALE_FACTOR_NOT_IMPLEMENTED: i16 = i16::MIN;
+
+/// 原始值与工程量之间的换算方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scaling {
+    /// 不换算
+    #[default]
+    None,
+    /// 工程量 = 原始值 × scale + offset
+    Linear { scale: f64, offset: f64 },
+    /// 工程量 = 原始值 × 10^sf，sf为同一数据表中`address`处的i16寄存器（SunSpec比例因子）
+    ScaleFactor { address: u16 },
+}
+
+/// 工程量点定义
+///
+/// 组合地址、数据类型、字节序、换算方式和单位。读取时返回工程量，写入时换算回原始值，
+/// 超出`limits`或数据类型范围的值返回`ModbusError::InvalidValue`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointDefinition {
+    pub table: Table,
+    pub address: u16,
+    /// 原始值的数据类型，须为数值类型
+    pub data_type: DataType,
+    pub byte_order: ByteOrder,
+    pub scaling: Scaling,
+    /// 工程单位，例如"kW"
+    pub units: Option<String>,
+    /// 允许写入的工程量范围
+    pub limits: Option<(f64, f64)>,
+}
+
+impl PointDefinition {
+    /// 创建点定义，默认为`u16`、不换算
+    pub fn new(table: Table, address: u16) -> Self {
+        Self {
+            table,
+            address,
+            data_type: DataType::U16,
+            byte_order: ByteOrder::ABCD,
+            scaling: Scaling::None,
+            units: None,
+            limits: None,
+        }
+    }
+
+    /// 按地址创建点定义，例如`"40001".parse()?`
+    pub fn at(address: ModbusAddress) -> Self {
+        Self::new(address.table, address.offset)
+    }
+
+    /// 设置数据类型和字节序
+    pub fn with_type(mut self, data_type: DataType, byte_order: ByteOrder) -> Self {
+        self.data_type = data_type;
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// 设置线性换算
+    pub fn with_scale(mut self, scale: f64, offset: f64) -> Self {
+        self.scaling = Scaling::Linear { scale, offset };
+        self
+    }
+
+    /// 使用`address`处寄存器中的比例因子
+    pub fn with_scale_factor(mut self, address: u16) -> Self {
+        self.scaling = Scaling::ScaleFactor { address };
+        self
+    }
+
+    /// 设置工程单位
+    pub fn with_units(mut self, units: impl Into<String>) -> Self {
+        self.units = Some(units.into());
+        self
+    }
+
+    /// 设置允许写入的工程量范围
+    pub fn with_limits(mut self, min: f64, max: f64) -> Self {
+        self.limits = Some((min, max));
+        self
+    }
+
+    /// 原始值占用的寄存器数
+    pub fn width(&self) -> u16 {
+        self.data_type.width(None)
+    }
+
+    /// 将原始寄存器解码为工程量，使用比例因子时须提供`scale_factor`
+    pub fn decode(&self, registers: &[u16], scale_factor: Option<i16>) -> Result<f64, ModbusError> {
+        self.check_numeric()?;
+        let raw = self
+            .data_type
+            .decode(registers, self.byte_order)?
+            .as_f64()
+            .ok_or_else(|| self.not_numeric())?;
+        match self.scaling {
+            Scaling::None => Ok(raw),
+            Scaling::Linear { scale, offset } => Ok(raw * scale + offset),
+            Scaling::ScaleFactor { .. } => Ok(raw * scale_multiplier(scale_factor)?),
+        }
+    }
+
+    /// 将工程量编码为原始寄存器，整数类型四舍五入
+    pub fn encode(&self, value: f64, scale_factor: Option<i16>) -> Result<Vec<u16>, ModbusError> {
+        self.check_numeric()?;
+        if let Some((min, max)) = self.limits {
+            if !(min..=max).contains(&value) {
+                return Err(ModbusError::InvalidValue(format!("{} is outside {}..={}", value, min, max)));
+            }
+        }
+
+        let raw = match self.scaling {
+            Scaling::None => value,
+            Scaling::Linear { scale, offset } => {
+                if scale == 0.0 {
+                    return Err(ModbusError::InvalidValue("Scale must not be zero".to_string()));
+                }
+                (value - offset) / scale
+            },
+            Scaling::ScaleFactor { .. } => value / scale_multiplier(scale_factor)?,
+        };
+        let out_of_range = || ModbusError::InvalidValue(format!("{} is out of range for {:?}", value, self.data_type));
+        let integer = |min: f64, max: f64| {
+            let raw = raw.round();
+            if raw.is_finite() && (min..=max).contains(&raw) { Ok(raw) } else { Err(out_of_range()) }
+        };
+
+        let registers = match self.data_type {
+            DataType::U16 => (integer(0.0, u16::MAX as f64)? as u16).to_registers(self.byte_order),
+            DataType::I16 => (integer(i16::MIN as f64, i16::MAX as f64)? as i16).to_registers(self.byte_order),
+            DataType::U32 => (integer(0.0, u32::MAX as f64)? as u32).to_registers(self.byte_order),
+            DataType::I32 => (integer(i32::MIN as f64, i32::MAX as f64)? as i32).to_registers(self.byte_order),
+            DataType::F32 => {
+                if !raw.is_finite() || raw.abs() > f32::MAX as f64 {
+                    return Err(out_of_range());
+                }
+                (raw as f32).to_registers(self.byte_order)
+            },
+            DataType::U48 => u48_to_registers(integer(0.0, U48_MAX as f64)? as u64, self.byte_order)?,
+            DataType::I48 => i48_to_registers(integer(I48_MIN as f64, I48_MAX as f64)? as i64, self.byte_order)?,
+            // 2^64和2^63无法用integer的闭区间表示，交给from_f64严格检查
+            DataType::U64 => u64::from_f64(raw).ok_or_else(out_of_range)?.to_registers(self.byte_order),
+            DataType::I64 => i64::from_f64(raw).ok_or_else(out_of_range)?.to_registers(self.byte_order),
+            DataType::F64 => f64::from_f64(raw).ok_or_else(out_of_range)?.to_registers(self.byte_order),
+            DataType::Bool | DataType::String => return Err(self.not_numeric()),
+        };
+        Ok(registers)
+    }
+
+    fn check_numeric(&self) -> Result<(), ModbusError> {
+        match self.data_type {
+            DataType::Bool | DataType::String => Err(self.not_numeric()),
+            _ => Ok(()),
+        }
+    }
+
+    fn not_numeric(&self) -> ModbusError {
+        ModbusError::InvalidValue(format!("{:?} is not a numeric type", self.data_type))
+    }
+}
+
+/// 比例因子对应的倍数
+fn scale_multiplier(scale_factor: Option<i16>) -> Result<f64, ModbusError> {
+    match scale_factor {
+        Some(SCALE_FACTOR_NOT_IMPLEMENTED) => Err(ModbusError::InvalidValue("Scale factor is not implemented".to_string())),
+        Some(scale_factor) => Ok(10f64.powi(scale_factor as i32)),
+        None => Err(ModbusError::InvalidValue("Missing scale factor".to_string())),
+    }
+}
+
+/// 按点定义读写工程量，适用于所有实现了`ModbusMaster`的客户端
+pub trait ModbusPointExt: ModbusMaster {
+    /// 读取工程量；比例因子与值相距不远时在同一个请求中读取
+    fn read_point(&mut self, unit_id: u8, point: &PointDefinition) -> impl Future<Output = Result<f64, ModbusError>> + Send {
+        async move {
+            let width = point.width();
+            let Scaling::ScaleFactor { address: sf_address } = point.scaling else {
+                let registers = self.read_registers(unit_id, point.table, point.address, width).await?;
+                return point.decode(&registers, None);
+            };
+
+            let start = point.address.min(sf_address);
+            let end = (point.address as u32 + width as u32 - 1).max(sf_address as u32);
+            let span = end - start as u32 + 1;
+            let (registers, scale_factor) = if span <= MAX_READ_REGISTERS as u32 {
+                let block = self.read_registers(unit_id, point.table, start, span as u16).await?;
+                let value_start = (point.address - start) as usize;
+                let registers = block[value_start..value_start + width as usize].to_vec();
+                (registers, block[(sf_address - start) as usize] as i16)
+            } else {
+                let registers = self.read_registers(unit_id, point.table, point.address, width).await?;
+                (registers, self.read_scale_factor(unit_id, point.table, sf_address).await?)
+            };
+            point.decode(&registers, Some(scale_factor))
+        }
+    }
+
+    /// 写入工程量，只能写保持寄存器
+    fn write_point(&mut self, unit_id: u8, point: &PointDefinition, value: f64) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move {
+            if point.table != Table::HoldingRegisters {
+                return Err(ModbusError::InvalidValue(format!("{:?} is read-only", point.table)));
+            }
+            let scale_factor = match point.scaling {
+                Scaling::ScaleFactor { address } => Some(self.read_scale_factor(unit_id, point.table, address).await?),
+                _ => None,
+            };
+            let registers = point.encode(value, scale_factor)?;
+            self.write_registers(unit_id, point.address, &registers).await
+        }
+    }
+
+    /// 读取比例因子寄存器
+    fn read_scale_factor(&mut self, unit_id: u8, table: Table, address: u16) -> impl Future<Output = Result<i16, ModbusError>> + Send {
+        async move {
+            let registers = self.read_registers(unit_id, table, address, 1).await?;
+            i16::from_registers(&registers, ByteOrder::ABCD)
+        }
+    }
+}
+
+impl<M: ModbusMaster> ModbusPointExt for M {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::loopback::LoopbackMaster;
+    use crate::server::MemoryService;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_engineering_values() {
+        let memory = MemoryService::new();
+        let mut master = LoopbackMaster::new(memory.clone());
+
+        // 温度：原始值 × 0.1 - 40
+        let temperature = PointDefinition::new(Table::HoldingRegisters, 0)
+            .with_scale(0.1, -40.0)
+            .with_units("°C")
+            .with_limits(-40.0, 125.0);
+        memory.set_holding_register(0, 2345);
+        assert_close(master.read_point(1, &temperature).await.unwrap(), 194.5);
+        master.write_point(1, &temperature, 21.5).await.unwrap();
+        assert_eq!(memory.get_holding_register(0), 615);
+        assert!(matches!(master.write_point(1, &temperature, 130.0).await, Err(ModbusError::InvalidValue(_))));
+
+        // 换算后超出数据类型范围
+        let current = PointDefinition::new(Table::HoldingRegisters, 1).with_type(DataType::I16, ByteOrder::ABCD).with_scale(0.01, 0.0);
+        assert!(matches!(master.write_point(1, &current, 400.0).await, Err(ModbusError::InvalidValue(_))));
+        master.write_point(1, &current, -12.34).await.unwrap();
+        assert_eq!(memory.get_holding_register(1) as i16, -1234);
+
+        // SunSpec比例因子：功率 × 10^-1
+        let power = PointDefinition::new(Table::HoldingRegisters, 10)
+            .with_type(DataType::U32, ByteOrder::ABCD)
+            .with_scale_factor(14)
+            .with_units("W");
+        memory.set_registers(Table::HoldingRegisters, 10, &[0x0001, 0x0000]).unwrap();
+        memory.set_holding_register(14, (-1i16) as u16);
+        assert_close(master.read_point(1, &power).await.unwrap(), 6553.6);
+        master.write_point(1, &power, 1234.5).await.unwrap();
+        assert_eq!(master.read_registers(1, Table::HoldingRegisters, 10, 2).await.unwrap(), vec![0, 12345]);
+
+        // 比例因子未实现
+        memory.set_holding_register(14, 0x8000);
+        assert!(matches!(master.read_point(1, &power).await, Err(ModbusError::InvalidValue(_))));
+
+        // 64位电能累计量：原始值 × 0.001 kWh
+        let energy = PointDefinition::new(Table::HoldingRegisters, 20)
+            .with_type(DataType::U64, ByteOrder::CDAB)
+            .with_scale(0.001, 0.0)
+            .with_units("kWh");
+        master.write_point(1, &energy, 5_000_000_000.0).await.unwrap();
+        assert_eq!(memory.get_u64(Table::HoldingRegisters, 20, ByteOrder::CDAB).unwrap(), 5_000_000_000_000);
+        assert_close(master.read_point(1, &energy).await.unwrap(), 5_000_000_000.0);
+        assert!(matches!(master.write_point(1, &energy, -1.0).await, Err(ModbusError::InvalidValue(_))));
+
+        let counter = PointDefinition::new(Table::HoldingRegisters, 24).with_type(DataType::I48, ByteOrder::ABCD);
+        master.write_point(1, &counter, -3.0).await.unwrap();
+        assert_eq!(master.read_registers(1, Table::HoldingRegisters, 24, 3).await.unwrap(), vec![0xFFFF, 0xFFFF, 0xFFFD]);
+        assert_close(master.read_point(1, &counter).await.unwrap(), -3.0);
+        assert!(matches!(master.write_point(1, &counter, 2f64.powi(47)).await, Err(ModbusError::InvalidValue(_))));
+
+        // 输入寄存器只读
+        let input = PointDefinition::new(Table::InputRegisters, 0);
+        assert!(master.write_point(1, &input, 1.0).await.is_err());
+    }
+}
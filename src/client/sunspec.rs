@@ -0,0 +1,853 @@
+//! SunSpec设备的模型发现与解码
+//!
+//! 在标准基地址查找"SunS"标记，沿模型链读取模型列表，并把常用模型解码为带工程量的结构体：
+//! 公共模型1、整数逆变器101～103、浮点逆变器111～113、电表201～204、多路MPPT扩展160，
+//! 以及可写的立即控制模型123。
+
+use crate::protocol::*;
+use crate::server::MAX_READ_REGISTERS;
+use crate::utils::registers_to_string;
+use super::master::ModbusMaster;
+
+/// 依次尝试的基地址（从0开始）
+pub const BASE_ADDRESSES: [u16; 3] = [40000, 50000, 0];
+
+/// "SunS"标记
+pub const SUNS_MARKER: [u16; 2] = [0x5375, 0x6E53];
+
+/// 模型链结束标记
+const END_MODEL_ID: u16 = 0xFFFF;
+
+/// 模型链最多包含的模型数，防止异常设备造成死循环
+const MAX_MODELS: usize = 256;
+
+/// 公共模型
+pub const COMMON_MODEL: u16 = 1;
+/// 立即控制模型
+pub const IMMEDIATE_CONTROLS_MODEL: u16 = 123;
+/// 多路MPPT扩展模型
+pub const MPPT_MODEL: u16 = 160;
+
+/// 模型链中的一个模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// 模型ID
+    pub id: u16,
+    /// 模型数据的起始地址（跳过ID和长度两个寄存器）
+    pub address: u16,
+    /// 模型数据的寄存器数
+    pub length: u16,
+}
+
+/// 公共模型（1）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Common {
+    /// Mn
+    pub manufacturer: String,
+    /// Md
+    pub model: String,
+    /// Opt
+    pub options: String,
+    /// Vr
+    pub version: String,
+    /// SN
+    pub serial_number: String,
+    /// DA
+    pub device_address: Option<u16>,
+}
+
+/// 逆变器运行状态（St）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatingState {
+    Off,
+    Sleeping,
+    Starting,
+    Mppt,
+    Throttled,
+    ShuttingDown,
+    Fault,
+    Standby,
+    /// 标准之外的值
+    Other(u16),
+}
+
+impl From<u16> for OperatingState {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => OperatingState::Off,
+            2 => OperatingState::Sleeping,
+            3 => OperatingState::Starting,
+            4 => OperatingState::Mppt,
+            5 => OperatingState::Throttled,
+            6 => OperatingState::ShuttingDown,
+            7 => OperatingState::Fault,
+            8 => OperatingState::Standby,
+            other => OperatingState::Other(other),
+        }
+    }
+}
+
+/// 逆变器模型（101～103整数加比例因子，111～113浮点）
+///
+/// 所有测量值已换算为工程量，设备未实现的点为None。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inverter {
+    pub model_id: u16,
+    /// A，交流总电流
+    pub current: Option<f64>,
+    /// AphA/AphB/AphC
+    pub phase_currents: [Option<f64>; 3],
+    /// PPVphAB/PPVphBC/PPVphCA，线电压
+    pub line_voltages: [Option<f64>; 3],
+    /// PhVphA/PhVphB/PhVphC，相电压
+    pub phase_voltages: [Option<f64>; 3],
+    /// W，有功功率
+    pub power: Option<f64>,
+    /// Hz
+    pub frequency: Option<f64>,
+    /// VA
+    pub apparent_power: Option<f64>,
+    /// VAr
+    pub reactive_power: Option<f64>,
+    /// PF
+    pub power_factor: Option<f64>,
+    /// WH，累计发电量
+    pub energy: Option<f64>,
+    /// DCA
+    pub dc_current: Option<f64>,
+    /// DCV
+    pub dc_voltage: Option<f64>,
+    /// DCW
+    pub dc_power: Option<f64>,
+    /// TmpCab/TmpSnk/TmpTrns/TmpOt
+    pub temperatures: [Option<f64>; 4],
+    /// St
+    pub state: Option<OperatingState>,
+    /// StVnd
+    pub vendor_state: Option<u16>,
+    /// Evt1
+    pub events: Option<u32>,
+}
+
+/// 电表模型（201～204）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meter {
+    pub model_id: u16,
+    /// A
+    pub current: Option<f64>,
+    /// AphA/AphB/AphC
+    pub phase_currents: [Option<f64>; 3],
+    /// PhV，相电压平均值
+    pub voltage: Option<f64>,
+    /// PhVphA/PhVphB/PhVphC
+    pub phase_voltages: [Option<f64>; 3],
+    /// PPV，线电压平均值
+    pub line_voltage: Option<f64>,
+    /// PPVphAB/PPVphBC/PPVphCA
+    pub line_voltages: [Option<f64>; 3],
+    /// Hz
+    pub frequency: Option<f64>,
+    /// W
+    pub power: Option<f64>,
+    /// WphA/WphB/WphC
+    pub phase_powers: [Option<f64>; 3],
+    /// VA
+    pub apparent_power: Option<f64>,
+    /// VAR
+    pub reactive_power: Option<f64>,
+    /// PF
+    pub power_factor: Option<f64>,
+    /// TotWhExp，累计输出电能
+    pub energy_exported: Option<f64>,
+    /// TotWhImp，累计输入电能
+    pub energy_imported: Option<f64>,
+    /// Evt
+    pub events: Option<u32>,
+}
+
+/// MPPT扩展模型中的一路输入
+#[derive(Debug, Clone, PartialEq)]
+pub struct MpptModule {
+    /// ID
+    pub id: Option<u16>,
+    /// IDStr
+    pub label: String,
+    /// DCA
+    pub dc_current: Option<f64>,
+    /// DCV
+    pub dc_voltage: Option<f64>,
+    /// DCW
+    pub dc_power: Option<f64>,
+    /// DCWH
+    pub dc_energy: Option<f64>,
+    /// Tms，时间戳（秒）
+    pub timestamp: Option<u32>,
+    /// Tmp
+    pub temperature: Option<f64>,
+    /// DCSt
+    pub state: Option<OperatingState>,
+    /// DCEvt
+    pub events: Option<u32>,
+}
+
+/// 多路MPPT扩展模型（160）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mppt {
+    /// Evt
+    pub events: Option<u32>,
+    /// TmsPer，时间戳周期
+    pub timestamp_period: Option<u16>,
+    pub modules: Vec<MpptModule>,
+}
+
+/// 立即控制模型（123）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImmediateControls {
+    /// Conn，是否并网
+    pub connected: Option<bool>,
+    /// WMaxLimPct，有功功率限制（额定功率的百分比）
+    pub power_limit: Option<f64>,
+    /// WMaxLim_Ena
+    pub power_limit_enabled: Option<bool>,
+    /// OutPFSet，功率因数设定
+    pub power_factor: Option<f64>,
+    /// OutPFSet_Ena
+    pub power_factor_enabled: Option<bool>,
+}
+
+/// 解码后的模型
+#[derive(Debug, Clone, PartialEq)]
+pub enum Model {
+    Common(Common),
+    Inverter(Inverter),
+    Meter(Meter),
+    Mppt(Mppt),
+    ImmediateControls(ImmediateControls),
+    /// 不支持解码的模型，保留原始寄存器
+    Unknown { id: u16, registers: Vec<u16> },
+}
+
+/// 按比例因子换算，负的比例因子用除法以避免`0.1`这类系数带来的误差
+fn apply_scale(value: f64, scale_factor: i16) -> f64 {
+    if scale_factor < 0 {
+        value / 10f64.powi(-(scale_factor as i32))
+    } else {
+        value * 10f64.powi(scale_factor as i32)
+    }
+}
+
+/// 模型数据的读取器，把SunSpec定义的"未实现"值转换为None
+struct Fields<'a> {
+    registers: &'a [u16],
+}
+
+impl Fields<'_> {
+    fn raw(&self, offset: usize) -> Option<u16> {
+        self.registers.get(offset).copied()
+    }
+
+    fn raw32(&self, offset: usize) -> Option<u32> {
+        Some((self.raw(offset)? as u32) << 16 | self.raw(offset + 1)? as u32)
+    }
+
+    fn uint16(&self, offset: usize) -> Option<u16> {
+        self.raw(offset).filter(|&value| value != 0xFFFF)
+    }
+
+    fn int16(&self, offset: usize) -> Option<i16> {
+        self.raw(offset).map(|value| value as i16).filter(|&value| value != i16::MIN)
+    }
+
+    fn uint32(&self, offset: usize) -> Option<u32> {
+        self.raw32(offset).filter(|&value| value != 0xFFFF_FFFF)
+    }
+
+    /// 累计量，0表示未实现
+    fn acc32(&self, offset: usize) -> Option<u32> {
+        self.raw32(offset).filter(|&value| value != 0)
+    }
+
+    fn float32(&self, offset: usize) -> Option<f64> {
+        self.raw32(offset).map(f32::from_bits).filter(|value| !value.is_nan()).map(f64::from)
+    }
+
+    fn string(&self, offset: usize, length: usize) -> String {
+        self.registers
+            .get(offset..offset + length)
+            .map(|registers| registers_to_string(registers, ByteOrder::ABCD))
+            .unwrap_or_default()
+    }
+
+    fn scaled(value: Option<f64>, scale_factor: Option<i16>) -> Option<f64> {
+        Some(apply_scale(value?, scale_factor?))
+    }
+
+    fn scaled_u16(&self, offset: usize, scale_factor: Option<i16>) -> Option<f64> {
+        Self::scaled(self.uint16(offset).map(f64::from), scale_factor)
+    }
+
+    fn scaled_i16(&self, offset: usize, scale_factor: Option<i16>) -> Option<f64> {
+        Self::scaled(self.int16(offset).map(f64::from), scale_factor)
+    }
+
+    fn scaled_acc32(&self, offset: usize, scale_factor: Option<i16>) -> Option<f64> {
+        Self::scaled(self.acc32(offset).map(f64::from), scale_factor)
+    }
+}
+
+fn check_length(registers: &[u16], length: usize) -> Result<Fields<'_>, ModbusError> {
+    if registers.len() < length {
+        return Err(ModbusError::InvalidDataLength);
+    }
+    Ok(Fields { registers })
+}
+
+impl Common {
+    /// 从模型数据（不含ID和长度）解码
+    pub fn from_registers(registers: &[u16]) -> Result<Self, ModbusError> {
+        let fields = check_length(registers, 65)?;
+        Ok(Self {
+            manufacturer: fields.string(0, 16),
+            model: fields.string(16, 16),
+            options: fields.string(32, 8),
+            version: fields.string(40, 8),
+            serial_number: fields.string(48, 16),
+            device_address: fields.uint16(64),
+        })
+    }
+}
+
+impl Inverter {
+    /// 从模型数据解码，支持模型101～103和111～113
+    pub fn from_registers(model_id: u16, registers: &[u16]) -> Result<Self, ModbusError> {
+        match model_id {
+            101..=103 => Self::from_integer(model_id, registers),
+            111..=113 => Self::from_float(model_id, registers),
+            _ => Err(unsupported_model(model_id)),
+        }
+    }
+
+    fn from_integer(model_id: u16, registers: &[u16]) -> Result<Self, ModbusError> {
+        let f = check_length(registers, 50)?;
+        let (a_sf, v_sf, tmp_sf) = (f.int16(4), f.int16(11), f.int16(35));
+        Ok(Self {
+            model_id,
+            current: f.scaled_u16(0, a_sf),
+            phase_currents: [f.scaled_u16(1, a_sf), f.scaled_u16(2, a_sf), f.scaled_u16(3, a_sf)],
+            line_voltages: [f.scaled_u16(5, v_sf), f.scaled_u16(6, v_sf), f.scaled_u16(7, v_sf)],
+            phase_voltages: [f.scaled_u16(8, v_sf), f.scaled_u16(9, v_sf), f.scaled_u16(10, v_sf)],
+            power: f.scaled_i16(12, f.int16(13)),
+            frequency: f.scaled_u16(14, f.int16(15)),
+            apparent_power: f.scaled_i16(16, f.int16(17)),
+            reactive_power: f.scaled_i16(18, f.int16(19)),
+            power_factor: f.scaled_i16(20, f.int16(21)),
+            energy: f.scaled_acc32(22, f.int16(24)),
+            dc_current: f.scaled_u16(25, f.int16(26)),
+            dc_voltage: f.scaled_u16(27, f.int16(28)),
+            dc_power: f.scaled_i16(29, f.int16(30)),
+            temperatures: [f.scaled_i16(31, tmp_sf), f.scaled_i16(32, tmp_sf), f.scaled_i16(33, tmp_sf), f.scaled_i16(34, tmp_sf)],
+            state: f.uint16(36).map(OperatingState::from),
+            vendor_state: f.uint16(37),
+            events: f.uint32(38),
+        })
+    }
+
+    fn from_float(model_id: u16, registers: &[u16]) -> Result<Self, ModbusError> {
+        let f = check_length(registers, 60)?;
+        Ok(Self {
+            model_id,
+            current: f.float32(0),
+            phase_currents: [f.float32(2), f.float32(4), f.float32(6)],
+            line_voltages: [f.float32(8), f.float32(10), f.float32(12)],
+            phase_voltages: [f.float32(14), f.float32(16), f.float32(18)],
+            power: f.float32(20),
+            frequency: f.float32(22),
+            apparent_power: f.float32(24),
+            reactive_power: f.float32(26),
+            power_factor: f.float32(28),
+            energy: f.float32(30),
+            dc_current: f.float32(32),
+            dc_voltage: f.float32(34),
+            dc_power: f.float32(36),
+            temperatures: [f.float32(38), f.float32(40), f.float32(42), f.float32(44)],
+            state: f.uint16(46).map(OperatingState::from),
+            vendor_state: f.uint16(47),
+            events: f.uint32(48),
+        })
+    }
+}
+
+impl Meter {
+    /// 从模型数据解码，支持模型201～204
+    pub fn from_registers(model_id: u16, registers: &[u16]) -> Result<Self, ModbusError> {
+        if !(201..=204).contains(&model_id) {
+            return Err(unsupported_model(model_id));
+        }
+        let f = check_length(registers, 105)?;
+        let (a_sf, v_sf, w_sf, wh_sf) = (f.int16(4), f.int16(13), f.int16(20), f.int16(52));
+        Ok(Self {
+            model_id,
+            current: f.scaled_i16(0, a_sf),
+            phase_currents: [f.scaled_i16(1, a_sf), f.scaled_i16(2, a_sf), f.scaled_i16(3, a_sf)],
+            voltage: f.scaled_i16(5, v_sf),
+            phase_voltages: [f.scaled_i16(6, v_sf), f.scaled_i16(7, v_sf), f.scaled_i16(8, v_sf)],
+            line_voltage: f.scaled_i16(9, v_sf),
+            line_voltages: [f.scaled_i16(10, v_sf), f.scaled_i16(11, v_sf), f.scaled_i16(12, v_sf)],
+            frequency: f.scaled_i16(14, f.int16(15)),
+            power: f.scaled_i16(16, w_sf),
+            phase_powers: [f.scaled_i16(17, w_sf), f.scaled_i16(18, w_sf), f.scaled_i16(19, w_sf)],
+            apparent_power: f.scaled_i16(21, f.int16(25)),
+            reactive_power: f.scaled_i16(26, f.int16(30)),
+            power_factor: f.scaled_i16(31, f.int16(35)),
+            energy_exported: f.scaled_acc32(36, wh_sf),
+            energy_imported: f.scaled_acc32(44, wh_sf),
+            events: f.uint32(103),
+        })
+    }
+}
+
+impl Mppt {
+    /// 固定部分的寄存器数
+    const FIXED_LENGTH: usize = 8;
+    /// 每一路输入的寄存器数
+    const MODULE_LENGTH: usize = 20;
+
+    /// 从模型数据解码，输入路数取N与模型长度中较小者
+    pub fn from_registers(registers: &[u16]) -> Result<Self, ModbusError> {
+        let f = check_length(registers, Self::FIXED_LENGTH)?;
+        let (a_sf, v_sf, w_sf, wh_sf) = (f.int16(0), f.int16(1), f.int16(2), f.int16(3));
+        let available = (registers.len() - Self::FIXED_LENGTH) / Self::MODULE_LENGTH;
+        let count = f.uint16(6).map_or(available, |count| (count as usize).min(available));
+
+        let modules = (0..count)
+            .map(|index| {
+                let m = Fields { registers: &registers[Self::FIXED_LENGTH + index * Self::MODULE_LENGTH..] };
+                MpptModule {
+                    id: m.uint16(0),
+                    label: m.string(1, 8),
+                    dc_current: m.scaled_u16(9, a_sf),
+                    dc_voltage: m.scaled_u16(10, v_sf),
+                    dc_power: m.scaled_u16(11, w_sf),
+                    dc_energy: m.scaled_acc32(12, wh_sf),
+                    timestamp: m.uint32(14),
+                    temperature: m.int16(16).map(f64::from),
+                    state: m.uint16(17).map(OperatingState::from),
+                    events: m.uint32(18),
+                }
+            })
+            .collect();
+        Ok(Self {
+            events: f.uint32(4),
+            timestamp_period: f.uint16(7),
+            modules,
+        })
+    }
+}
+
+impl ImmediateControls {
+    /// Conn
+    const CONN: u16 = 2;
+    /// WMaxLimPct
+    const POWER_LIMIT: u16 = 3;
+    /// WMaxLim_Ena
+    const POWER_LIMIT_ENABLE: u16 = 7;
+    /// OutPFSet
+    const POWER_FACTOR: u16 = 8;
+    /// OutPFSet_Ena
+    const POWER_FACTOR_ENABLE: u16 = 12;
+    /// WMaxLimPct_SF
+    const POWER_LIMIT_SF: usize = 21;
+    /// OutPFSet_SF
+    const POWER_FACTOR_SF: usize = 22;
+
+    /// 从模型数据解码
+    pub fn from_registers(registers: &[u16]) -> Result<Self, ModbusError> {
+        let f = check_length(registers, 24)?;
+        let flag = |offset: u16| f.uint16(offset as usize).map(|value| value == 1);
+        Ok(Self {
+            connected: flag(Self::CONN),
+            power_limit: f.scaled_u16(Self::POWER_LIMIT as usize, f.int16(Self::POWER_LIMIT_SF)),
+            power_limit_enabled: flag(Self::POWER_LIMIT_ENABLE),
+            power_factor: f.scaled_i16(Self::POWER_FACTOR as usize, f.int16(Self::POWER_FACTOR_SF)),
+            power_factor_enabled: flag(Self::POWER_FACTOR_ENABLE),
+        })
+    }
+}
+
+impl Model {
+    /// 按模型ID解码模型数据
+    pub fn decode(id: u16, registers: &[u16]) -> Result<Self, ModbusError> {
+        Ok(match id {
+            COMMON_MODEL => Model::Common(Common::from_registers(registers)?),
+            101..=103 | 111..=113 => Model::Inverter(Inverter::from_registers(id, registers)?),
+            201..=204 => Model::Meter(Meter::from_registers(id, registers)?),
+            MPPT_MODEL => Model::Mppt(Mppt::from_registers(registers)?),
+            IMMEDIATE_CONTROLS_MODEL => Model::ImmediateControls(ImmediateControls::from_registers(registers)?),
+            _ => Model::Unknown { id, registers: registers.to_vec() },
+        })
+    }
+}
+
+fn unsupported_model(id: u16) -> ModbusError {
+    ModbusError::ProtocolError(format!("Unsupported SunSpec model {}", id))
+}
+
+fn missing_model(id: u16) -> ModbusError {
+    ModbusError::ProtocolError(format!("SunSpec model {} not found", id))
+}
+
+/// SunSpec设备
+///
+/// `discover`在标准基地址查找"SunS"标记并读取模型链，之后可以按模型ID读取和解码。
+/// 同一模型出现多次时（例如多个电表），`read_*`方法使用第一个，其余可以通过`models`和`read_model_at`访问。
+pub struct SunSpecDevice<M: ModbusMaster> {
+    master: M,
+    unit_id: u8,
+    base_address: u16,
+    models: Vec<ModelInfo>,
+}
+
+impl<M: ModbusMaster> SunSpecDevice<M> {
+    /// 查找SunSpec标记并读取模型链
+    ///
+    /// 某个基地址回复异常时尝试下一个；通信错误直接返回。
+    pub async fn discover(mut master: M, unit_id: u8) -> Result<Self, ModbusError> {
+        for base_address in BASE_ADDRESSES {
+            match master.read_registers(unit_id, Table::HoldingRegisters, base_address, 2).await {
+                Ok(marker) if marker == SUNS_MARKER => {
+                    log::debug!("Found SunSpec marker at {} on unit {}", base_address, unit_id);
+                    let models = read_model_chain(&mut master, unit_id, base_address).await?;
+                    return Ok(Self { master, unit_id, base_address, models });
+                },
+                Ok(_) | Err(ModbusError::Exception(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ModbusError::ProtocolError(format!("SunSpec marker not found on unit {}", unit_id)))
+    }
+
+    /// 标记所在的基地址
+    pub fn base_address(&self) -> u16 {
+        self.base_address
+    }
+
+    /// 单元ID
+    pub fn unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// 模型链中的所有模型
+    pub fn models(&self) -> &[ModelInfo] {
+        &self.models
+    }
+
+    /// 查找第一个指定ID的模型
+    pub fn find(&self, id: u16) -> Option<ModelInfo> {
+        self.models.iter().find(|model| model.id == id).copied()
+    }
+
+    /// 获取客户端
+    pub fn master(&mut self) -> &mut M {
+        &mut self.master
+    }
+
+    /// 取回客户端
+    pub fn into_inner(self) -> M {
+        self.master
+    }
+
+    /// 读取模型的全部数据寄存器，超过单次读取上限时分多次读取
+    pub async fn read_registers(&mut self, model: &ModelInfo) -> Result<Vec<u16>, ModbusError> {
+        let mut registers = Vec::with_capacity(model.length as usize);
+        while registers.len() < model.length as usize {
+            let offset = registers.len() as u16;
+            let count = (model.length - offset).min(MAX_READ_REGISTERS);
+            registers.extend(self.master.read_registers(self.unit_id, Table::HoldingRegisters, model.address + offset, count).await?);
+        }
+        Ok(registers)
+    }
+
+    /// 读取并解码指定模型
+    pub async fn read_model_at(&mut self, model: &ModelInfo) -> Result<Model, ModbusError> {
+        let registers = self.read_registers(model).await?;
+        Model::decode(model.id, &registers)
+    }
+
+    /// 读取并解码第一个指定ID的模型
+    pub async fn read_model(&mut self, id: u16) -> Result<Model, ModbusError> {
+        let model = self.find(id).ok_or_else(|| missing_model(id))?;
+        self.read_model_at(&model).await
+    }
+
+    /// 读取公共模型
+    pub async fn read_common(&mut self) -> Result<Common, ModbusError> {
+        let registers = self.read_first(|id| id == COMMON_MODEL).await?.1;
+        Common::from_registers(&registers)
+    }
+
+    /// 读取第一个逆变器模型（101～103或111～113）
+    pub async fn read_inverter(&mut self) -> Result<Inverter, ModbusError> {
+        let (id, registers) = self.read_first(|id| matches!(id, 101..=103 | 111..=113)).await?;
+        Inverter::from_registers(id, &registers)
+    }
+
+    /// 读取第一个电表模型（201～204）
+    pub async fn read_meter(&mut self) -> Result<Meter, ModbusError> {
+        let (id, registers) = self.read_first(|id| matches!(id, 201..=204)).await?;
+        Meter::from_registers(id, &registers)
+    }
+
+    /// 读取MPPT扩展模型
+    pub async fn read_mppt(&mut self) -> Result<Mppt, ModbusError> {
+        let registers = self.read_first(|id| id == MPPT_MODEL).await?.1;
+        Mppt::from_registers(&registers)
+    }
+
+    /// 读取立即控制模型
+    pub async fn read_controls(&mut self) -> Result<ImmediateControls, ModbusError> {
+        let registers = self.read_first(|id| id == IMMEDIATE_CONTROLS_MODEL).await?.1;
+        ImmediateControls::from_registers(&registers)
+    }
+
+    async fn read_first(&mut self, matches: impl Fn(u16) -> bool) -> Result<(u16, Vec<u16>), ModbusError> {
+        let model = self
+            .models
+            .iter()
+            .find(|model| matches(model.id))
+            .copied()
+            .ok_or_else(|| ModbusError::ProtocolError("Matching SunSpec model not found".to_string()))?;
+        Ok((model.id, self.read_registers(&model).await?))
+    }
+
+    /// 写入模型数据，`offset`相对模型数据起始，不能超出模型长度
+    pub async fn write_registers(&mut self, model: &ModelInfo, offset: u16, values: &[u16]) -> Result<(), ModbusError> {
+        if offset as usize + values.len() > model.length as usize {
+            return Err(ModbusError::InvalidValue(format!(
+                "Write of {} registers at offset {} exceeds model {} length {}",
+                values.len(), offset, model.id, model.length
+            )));
+        }
+        self.master.write_registers(self.unit_id, model.address + offset, values).await
+    }
+
+    /// 并网或断开（Conn）
+    pub async fn set_connected(&mut self, connected: bool) -> Result<(), ModbusError> {
+        let model = self.controls_model()?;
+        self.write_registers(&model, ImmediateControls::CONN, &[connected as u16]).await
+    }
+
+    /// 设置有功功率限制（额定功率的百分比）并启用；None取消限制
+    ///
+    /// 依次进行三个事务：读取比例因子WMaxLimPct_SF、写入WMaxLimPct、写入WMaxLim_Ena。
+    /// 每次调用都重新读取比例因子，不做缓存；三个事务不是原子的，中途失败时限制值可能已写入但未启用。
+    pub async fn set_power_limit(&mut self, percent: Option<f64>) -> Result<(), ModbusError> {
+        let model = self.controls_model()?;
+        let Some(percent) = percent else {
+            return self.write_registers(&model, ImmediateControls::POWER_LIMIT_ENABLE, &[0]).await;
+        };
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(ModbusError::InvalidValue(format!("Power limit {}% is outside 0..=100", percent)));
+        }
+        let raw = self.scaled_raw(&model, ImmediateControls::POWER_LIMIT_SF, percent, 0.0, 65534.0).await?;
+        self.write_registers(&model, ImmediateControls::POWER_LIMIT, &[raw as u16]).await?;
+        self.write_registers(&model, ImmediateControls::POWER_LIMIT_ENABLE, &[1]).await
+    }
+
+    /// 设置功率因数并启用；None取消设定
+    ///
+    /// 与`set_power_limit`相同，依次读取OutPFSet_SF、写入OutPFSet、写入OutPFSet_Ena三个事务，
+    /// 每次调用都重新读取比例因子。
+    pub async fn set_power_factor(&mut self, power_factor: Option<f64>) -> Result<(), ModbusError> {
+        let model = self.controls_model()?;
+        let Some(power_factor) = power_factor else {
+            return self.write_registers(&model, ImmediateControls::POWER_FACTOR_ENABLE, &[0]).await;
+        };
+        if !(-1.0..=1.0).contains(&power_factor) {
+            return Err(ModbusError::InvalidValue(format!("Power factor {} is outside -1..=1", power_factor)));
+        }
+        let raw = self.scaled_raw(&model, ImmediateControls::POWER_FACTOR_SF, power_factor, -32767.0, 32767.0).await?;
+        self.write_registers(&model, ImmediateControls::POWER_FACTOR, &[raw as i16 as u16]).await?;
+        self.write_registers(&model, ImmediateControls::POWER_FACTOR_ENABLE, &[1]).await
+    }
+
+    fn controls_model(&self) -> Result<ModelInfo, ModbusError> {
+        self.find(IMMEDIATE_CONTROLS_MODEL).ok_or_else(|| missing_model(IMMEDIATE_CONTROLS_MODEL))
+    }
+
+    /// 读取比例因子并把工程量换算为原始值
+    async fn scaled_raw(&mut self, model: &ModelInfo, sf_offset: usize, value: f64, min: f64, max: f64) -> Result<i64, ModbusError> {
+        let registers = self.master.read_registers(self.unit_id, Table::HoldingRegisters, model.address + sf_offset as u16, 1).await?;
+        let scale_factor = Fields { registers: &registers }
+            .int16(0)
+            .ok_or_else(|| ModbusError::InvalidValue("Scale factor is not implemented".to_string()))?;
+        let raw = apply_scale(value, -scale_factor).round();
+        if !(min..=max).contains(&raw) {
+            return Err(ModbusError::InvalidValue(format!("{} is out of range after scaling", value)));
+        }
+        Ok(raw as i64)
+    }
+}
+
+/// 从标记之后开始读取模型链，直到结束标记
+async fn read_model_chain<M: ModbusMaster>(master: &mut M, unit_id: u8, base_address: u16) -> Result<Vec<ModelInfo>, ModbusError> {
+    let mut models = Vec::new();
+    let mut address = base_address as u32 + 2;
+    while models.len() < MAX_MODELS {
+        if address + 2 > 0x10000 {
+            break;
+        }
+        let header = match master.read_registers(unit_id, Table::HoldingRegisters, address as u16, 2).await {
+            Ok(header) => header,
+            // 有的设备不提供结束标记，读到末尾之后回复异常
+            Err(ModbusError::Exception(_)) if !models.is_empty() => break,
+            Err(e) => return Err(e),
+        };
+        let (id, length) = (header[0], header[1]);
+        if id == END_MODEL_ID {
+            break;
+        }
+        let data_address = address + 2;
+        if data_address + length as u32 > 0x10000 {
+            return Err(ModbusError::ProtocolError(format!("SunSpec model {} at {} exceeds the address space", id, address)));
+        }
+        models.push(ModelInfo { id, address: data_address as u16, length });
+        address = data_address + length as u32;
+    }
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::loopback::LoopbackMaster;
+    use crate::client::ModbusTcpClient;
+    use crate::server::{MemoryService, ModbusServer, ServerConfig};
+
+    /// 三相逆变器加MPPT、立即控制和电表的寄存器表
+    ///
+    /// 按SunSpec模型定义手工编制，不是实际设备的抓包，也不是SunSpec联盟的参考样本。
+    fn example_device() -> MemoryService {
+        let config = ServerConfig::from_json(include_str!("testdata/sunspec_map.json")).unwrap();
+        config.single_slave().unwrap().build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sunspec_device() {
+        // 通过真实的TCP服务器和客户端发现设备
+        let memory = example_device();
+        let server = ModbusServer::tcp("127.0.0.1:0", 1, memory.clone()).await.unwrap();
+        let port = server.local_addr().unwrap().port();
+        let shutdown = server.shutdown_handle();
+        let task = tokio::spawn(async move { server.run().await });
+        let client = ModbusTcpClient::new("127.0.0.1", port, 1).await.unwrap();
+
+        let mut device = SunSpecDevice::discover(client, 1).await.unwrap();
+        assert_eq!(device.base_address(), 40000);
+        let ids: Vec<u16> = device.models().iter().map(|model| model.id).collect();
+        assert_eq!(ids, vec![1, 103, 160, 123, 203]);
+        assert_eq!(device.find(103), Some(ModelInfo { id: 103, address: 40072, length: 50 }));
+
+        let common = device.read_common().await.unwrap();
+        assert_eq!(common.manufacturer, "Example Solar");
+        assert_eq!(common.model, "XS-5000");
+        assert_eq!(common.serial_number, "XS5K-0042");
+        assert_eq!(common.device_address, Some(1));
+
+        let inverter = device.read_inverter().await.unwrap();
+        assert_eq!(inverter.current, Some(12.34));
+        assert_eq!(inverter.phase_voltages, [Some(230.0), Some(231.0), Some(229.5)]);
+        assert_eq!(inverter.power, Some(8520.0));
+        assert_eq!(inverter.frequency, Some(50.02));
+        assert_eq!(inverter.energy, Some(12_345_678.0));
+        assert_eq!(inverter.dc_voltage, Some(412.0));
+        // 未实现的点
+        assert_eq!(inverter.temperatures, [Some(45.2), None, None, None]);
+        assert_eq!(inverter.state, Some(OperatingState::Mppt));
+        assert_eq!(inverter.vendor_state, None);
+
+        let mppt = device.read_mppt().await.unwrap();
+        assert_eq!(mppt.modules.len(), 2);
+        assert_eq!(mppt.modules[0].label, "String 1");
+        assert_eq!(mppt.modules[0].dc_current, Some(10.5));
+        assert_eq!(mppt.modules[1].dc_voltage, Some(410.0));
+        assert_eq!(mppt.modules[1].dc_energy, Some(5_800_000.0));
+        assert_eq!(mppt.modules[1].temperature, None);
+
+        let meter = device.read_meter().await.unwrap();
+        assert_eq!(meter.model_id, 203);
+        assert_eq!(meter.current, Some(-31.0));
+        assert_eq!(meter.phase_voltages, [Some(230.1), Some(230.6), Some(230.8)]);
+        assert_eq!(meter.frequency, Some(50.01));
+        assert_eq!(meter.power, Some(-7100.0));
+        assert_eq!(meter.power_factor, Some(-98.5));
+        assert_eq!(meter.energy_exported, Some(3_456_789.0));
+        assert_eq!(meter.energy_imported, Some(234_567.0));
+
+        // 写入控制模型
+        let controls = device.read_controls().await.unwrap();
+        assert_eq!(controls.power_limit, Some(100.0));
+        assert_eq!(controls.power_limit_enabled, Some(false));
+        device.set_power_limit(Some(60.5)).await.unwrap();
+        device.set_power_factor(Some(-0.9)).await.unwrap();
+        device.set_connected(false).await.unwrap();
+        let controls = device.read_controls().await.unwrap();
+        assert_eq!(controls.connected, Some(false));
+        assert_eq!(controls.power_limit, Some(60.5));
+        assert_eq!(controls.power_limit_enabled, Some(true));
+        assert_eq!(controls.power_factor, Some(-0.9));
+        assert_eq!(memory.get_holding_register(device.find(123).unwrap().address + 3), 605);
+        assert!(matches!(device.set_power_limit(Some(120.0)).await, Err(ModbusError::InvalidValue(_))));
+
+        let model = device.find(1).unwrap();
+        assert!(device.write_registers(&model, 64, &[1, 2, 3]).await.is_err());
+        assert!(matches!(device.read_model(64).await, Err(ModbusError::ProtocolError(_))));
+
+        drop(device);
+        shutdown.shutdown();
+        task.await.unwrap().unwrap();
+
+        // 没有SunSpec标记
+        let error = SunSpecDevice::discover(LoopbackMaster::new(MemoryService::new()), 1).await.err().unwrap();
+        assert!(matches!(error, ModbusError::ProtocolError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_float_inverter() {
+        // 标记、只有设备地址的公共模型、浮点三相逆变器（113）、结束标记
+        let memory = MemoryService::new();
+        let holding = Table::HoldingRegisters;
+        memory.set_registers(holding, 40000, &SUNS_MARKER).unwrap();
+        memory.set_registers(holding, 40002, &[COMMON_MODEL, 66]).unwrap();
+        memory.set_holding_register(40004 + 64, 1);
+        memory.set_registers(holding, 40070, &[113, 60]).unwrap();
+        memory.set_registers(holding, 40132, &[END_MODEL_ID, 0]).unwrap();
+
+        let data = 40072;
+        // 未实现的浮点点为NaN（0x7FC00000）
+        memory.set_registers(holding, data, &[0x7FC0, 0].repeat(30)).unwrap();
+        let floats = [(0, 12.5f32), (14, 230.0), (16, 231.5), (20, -4200.0), (22, 49.98), (28, -0.95), (30, 1.5e7), (38, 41.0)];
+        for (offset, value) in floats {
+            memory.set_f32(holding, data + offset, value, ByteOrder::ABCD).unwrap();
+        }
+        memory.set_holding_register(data + 46, 4);
+        memory.set_registers(holding, data + 48, &[0, 0x0041]).unwrap();
+
+        let mut device = SunSpecDevice::discover(LoopbackMaster::new(memory), 1).await.unwrap();
+        assert_eq!(device.models().iter().map(|model| model.id).collect::<Vec<_>>(), vec![1, 113]);
+        let inverter = device.read_inverter().await.unwrap();
+        assert_eq!(inverter.model_id, 113);
+        assert_eq!(inverter.current, Some(12.5));
+        assert_eq!(inverter.phase_currents, [None, None, None]);
+        assert_eq!(inverter.phase_voltages, [Some(230.0), Some(231.5), None]);
+        assert_eq!(inverter.power, Some(-4200.0));
+        assert_eq!(inverter.frequency, Some(49.98f32 as f64));
+        assert_eq!(inverter.power_factor, Some(-0.95f32 as f64));
+        assert_eq!(inverter.energy, Some(1.5e7));
+        assert_eq!(inverter.temperatures, [Some(41.0), None, None, None]);
+        assert_eq!(inverter.state, Some(OperatingState::Mppt));
+        assert_eq!(inverter.events, Some(0x41));
+
+        // 浮点模型至少60个寄存器
+        assert!(matches!(Inverter::from_registers(113, &[0; 50]), Err(ModbusError::InvalidDataLength)));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::protocol::*;
+use crate::server::{MAX_READ_REGISTERS, MAX_WRITE_REGISTERS};
+use crate::utils::{registers_to_string, string_to_registers, ModbusRegisters, RegisterValue};
+use super::master::ModbusMaster;
+use std::future::Future;
+
+/// 按类型读写寄存器，适用于所有实现了`ModbusMaster`的客户端
+///
+/// 字节序同时决定寄存器内的字节顺序和多个寄存器之间的顺序，例如`ByteOrder::CDAB`表示低位字在前。
+/// 读取时`table`须为`Table::HoldingRegisters`或`Table::InputRegisters`，写入总是写保持寄存器。
+/// 多个值在一个请求中读写，超过单次读取（125个寄存器）或写入（123个寄存器）上限时返回
+/// `InvalidDataLength`；需要分多次读写的大块数据使用`read_struct`/`write_struct`。
+pub trait ModbusMasterExt: ModbusMaster {
+    /// 读取一个数值
+    fn read_value<V: RegisterValue + Send>(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<V, ModbusError>> + Send {
+        async move {
+            let registers = self.read_registers(unit_id, table, address, V::REGISTER_COUNT as u16).await?;
+            V::from_registers(&registers, byte_order)
+        }
+    }
+
+    /// 按地址读取一个数值
+    fn read_value_at<V: RegisterValue + Send>(&mut self, unit_id: u8, address: ModbusAddress, byte_order: ByteOrder) -> impl Future<Output = Result<V, ModbusError>> + Send {
+        self.read_value(unit_id, address.table, address.offset, byte_order)
+    }
+
+    /// 读取`count`个连续的数值
+    fn read_values<V: RegisterValue + Send>(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<V>, ModbusError>> + Send {
+        async move {
+            let register_count = count as usize * V::REGISTER_COUNT;
+            if register_count > MAX_READ_REGISTERS as usize {
+                return Err(ModbusError::InvalidDataLength);
+            }
+            let registers = self.read_registers(unit_id, table, address, register_count as u16).await?;
+            registers
+                .chunks(V::REGISTER_COUNT)
+                .map(|chunk| V::from_registers(chunk, byte_order))
+                .collect()
+        }
+    }
+
+    /// 写入连续的多个数值
+    fn write_values<V: RegisterValue>(&mut self, unit_id: u8, address: u16, values: &[V], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        let registers: Vec<u16> = values.iter().flat_map(|value| value.to_registers(byte_order)).collect();
+        async move { self.write_registers(unit_id, address, &registers).await }
+    }
+
+    /// 按地址写入连续的多个数值，只能写保持寄存器
+    fn write_values_at<V: RegisterValue>(&mut self, unit_id: u8, address: ModbusAddress, values: &[V], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        let registers: Vec<u16> = values.iter().flat_map(|value| value.to_registers(byte_order)).collect();
+        async move { self.write_registers_at(unit_id, address, &registers).await }
+    }
+
+    /// 读取`register_count`个寄存器中的字符串，去掉末尾的0
+    fn read_string(&mut self, unit_id: u8, table: Table, address: u16, register_count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<String, ModbusError>> + Send {
+        async move {
+            let registers = self.read_registers(unit_id, table, address, register_count).await?;
+            Ok(registers_to_string(&registers, byte_order))
+        }
+    }
+
+    /// 写入字符串，占用`register_count`个寄存器，不足部分补0
+    fn write_string(&mut self, unit_id: u8, address: u16, text: &str, register_count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        let registers = string_to_registers(text, register_count as usize, byte_order);
+        async move { self.write_registers(unit_id, address, &registers?).await }
+    }
+
+    /// 读取一个寄存器块并解码为结构体，超过单次读取上限时分多次读取
+    fn read_struct<T: ModbusRegisters + Send>(&mut self, unit_id: u8, table: Table, address: u16) -> impl Future<Output = Result<T, ModbusError>> + Send {
+        async move {
+            let mut registers = Vec::with_capacity(T::REGISTER_COUNT as usize);
+            while registers.len() < T::REGISTER_COUNT as usize {
+                let offset = registers.len() as u16;
+                let count = (T::REGISTER_COUNT - offset).min(MAX_READ_REGISTERS);
+                let start = address.checked_add(offset).ok_or(ModbusError::InvalidDataLength)?;
+                registers.extend(self.read_registers(unit_id, table, start, count).await?);
+            }
+            T::from_registers(&registers)
+        }
+    }
+
+    /// 将结构体编码后写入保持寄存器，超过单次写入上限时分多次写入
+    fn write_struct<T: ModbusRegisters>(&mut self, unit_id: u8, address: u16, value: &T) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        let registers = value.to_registers();
+        async move {
+            let registers = registers?;
+            for (index, chunk) in registers.chunks(MAX_WRITE_REGISTERS as usize).enumerate() {
+                let offset = (index * MAX_WRITE_REGISTERS as usize) as u16;
+                let start = address.checked_add(offset).ok_or(ModbusError::InvalidDataLength)?;
+                self.write_registers(unit_id, start, chunk).await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// 读取一个u32（2个寄存器）
+    fn read_u32(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<u32, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的u32
+    fn read_u32s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<u32>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个u32（2个寄存器）
+    fn write_u32(&mut self, unit_id: u8, address: u16, value: u32, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个u32
+    fn write_u32s(&mut self, unit_id: u8, address: u16, values: &[u32], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+
+    /// 读取一个i32（2个寄存器）
+    fn read_i32(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<i32, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的i32
+    fn read_i32s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<i32>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个i32（2个寄存器）
+    fn write_i32(&mut self, unit_id: u8, address: u16, value: i32, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个i32
+    fn write_i32s(&mut self, unit_id: u8, address: u16, values: &[i32], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+
+    /// 读取一个f32（2个寄存器）
+    fn read_f32(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<f32, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的f32
+    fn read_f32s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<f32>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个f32（2个寄存器）
+    fn write_f32(&mut self, unit_id: u8, address: u16, value: f32, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个f32
+    fn write_f32s(&mut self, unit_id: u8, address: u16, values: &[f32], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+
+    /// 读取一个u64（4个寄存器）
+    fn read_u64(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<u64, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的u64
+    fn read_u64s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<u64>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个u64（4个寄存器）
+    fn write_u64(&mut self, unit_id: u8, address: u16, value: u64, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个u64
+    fn write_u64s(&mut self, unit_id: u8, address: u16, values: &[u64], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+
+    /// 读取一个i64（4个寄存器）
+    fn read_i64(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<i64, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的i64
+    fn read_i64s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<i64>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个i64（4个寄存器）
+    fn write_i64(&mut self, unit_id: u8, address: u16, value: i64, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个i64
+    fn write_i64s(&mut self, unit_id: u8, address: u16, values: &[i64], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+
+    /// 读取一个f64（4个寄存器）
+    fn read_f64(&mut self, unit_id: u8, table: Table, address: u16, byte_order: ByteOrder) -> impl Future<Output = Result<f64, ModbusError>> + Send {
+        self.read_value(unit_id, table, address, byte_order)
+    }
+
+    /// 读取`count`个连续的f64
+    fn read_f64s(&mut self, unit_id: u8, table: Table, address: u16, count: u16, byte_order: ByteOrder) -> impl Future<Output = Result<Vec<f64>, ModbusError>> + Send {
+        self.read_values(unit_id, table, address, count, byte_order)
+    }
+
+    /// 写入一个f64（4个寄存器）
+    fn write_f64(&mut self, unit_id: u8, address: u16, value: f64, byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        async move { self.write_values(unit_id, address, &[value], byte_order).await }
+    }
+
+    /// 写入连续的多个f64
+    fn write_f64s(&mut self, unit_id: u8, address: u16, values: &[f64], byte_order: ByteOrder) -> impl Future<Output = Result<(), ModbusError>> + Send {
+        self.write_values(unit_id, address, values, byte_order)
+    }
+}
+
+impl<M: ModbusMaster> ModbusMasterExt for M {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::loopback::LoopbackMaster;
+    use crate::server::MemoryService;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_typed_helpers() {
+        let memory = MemoryService::new();
+        let mut master = LoopbackMaster::new(memory.clone());
+        let holding = Table::HoldingRegisters;
+
+        master.write_f32s(1, 0, &[1.5, -2.25], ByteOrder::CDAB).await.unwrap();
+        assert_eq!(memory.get_f32(holding, 2, ByteOrder::CDAB).unwrap(), -2.25);
+        assert_eq!(master.read_f32s(1, holding, 0, 2, ByteOrder::CDAB).await.unwrap(), vec![1.5, -2.25]);
+
+        memory.set_i32(Table::InputRegisters, 10, -123456, ByteOrder::BADC).unwrap();
+        assert_eq!(master.read_i32(1, Table::InputRegisters, 10, ByteOrder::BADC).await.unwrap(), -123456);
+
+        master.write_f64(1, 20, std::f64::consts::PI, ByteOrder::DCBA).await.unwrap();
+        assert_eq!(master.read_f64(1, holding, 20, ByteOrder::DCBA).await.unwrap(), std::f64::consts::PI);
+        master.write_u64(1, 30, u64::MAX - 1, ByteOrder::ABCD).await.unwrap();
+        assert_eq!(master.read_u64(1, holding, 30, ByteOrder::ABCD).await.unwrap(), u64::MAX - 1);
+
+        master.write_string(1, 40, "PV-1", 4, ByteOrder::ABCD).await.unwrap();
+        assert_eq!(master.read_string(1, holding, 40, 4, ByteOrder::ABCD).await.unwrap(), "PV-1");
+
+        // Modicon地址从1开始
+        let setpoint: ModbusAddress = "400051".parse().unwrap();
+        master.write_values_at(1, setpoint, &[-5i32], ByteOrder::ABCD).await.unwrap();
+        assert_eq!(memory.get_i32(holding, 50, ByteOrder::ABCD).unwrap(), -5);
+        assert_eq!(master.read_value_at::<i32>(1, "HR50".parse().unwrap(), ByteOrder::ABCD).await.unwrap(), -5);
+        assert_eq!(master.read_registers_at(1, "40052".parse().unwrap(), 1).await.unwrap(), vec![0xFFFB]);
+        assert!(master.write_values_at(1, "30001".parse().unwrap(), &[1u16], ByteOrder::ABCD).await.is_err());
+
+        // 超过单次读写上限的结构体分多次传输
+        #[derive(Debug, PartialEq, ModbusRegisters)]
+        struct Block {
+            #[modbus(offset = 0, ty = "i16", scale = 0.5)]
+            first: f32,
+            #[modbus(offset = 199)]
+            last: u32,
+        }
+        let block = Block { first: -3.5, last: 0xDEAD_BEEF };
+        master.write_struct(1, 100, &block).await.unwrap();
+        assert_eq!(memory.get_holding_register(100) as i16, -7);
+        assert_eq!(master.read_struct::<Block>(1, holding, 100).await.unwrap(), block);
+
+        let error = master.read_u32(1, holding, 0xFFFF, ByteOrder::ABCD).await.unwrap_err();
+        assert!(matches!(error, ModbusError::Exception(ExceptionCode::IllegalDataAddress)));
+
+        // 超过单次读写上限的数值不截断也不发送
+        let requests = master.requests();
+        let sent = requests.load(Ordering::SeqCst);
+        let error = master.write_values(1, 400, &[1u16; 124], ByteOrder::ABCD).await.unwrap_err();
+        assert!(matches!(error, ModbusError::InvalidDataLength));
+        let error = master.write_f64s(1, 400, &[1.0; 31], ByteOrder::ABCD).await.unwrap_err();
+        assert!(matches!(error, ModbusError::InvalidDataLength));
+        let error = master.read_f32s(1, holding, 0, 63, ByteOrder::ABCD).await.unwrap_err();
+        assert!(matches!(error, ModbusError::InvalidDataLength));
+        assert!(matches!(master.read_registers(1, holding, 0, 126).await, Err(ModbusError::InvalidDataLength)));
+        assert_eq!(requests.load(Ordering::SeqCst), sent);
+        assert_eq!(memory.get_holding_register(400), 0);
+
+        master.write_values(1, 400, &[1u16; 123], ByteOrder::ABCD).await.unwrap();
+        assert_eq!(master.read_registers(1, holding, 400, 125).await.unwrap()[122..], [1, 0, 0]);
+    }
+}
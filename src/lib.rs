@@ -1,3 +1,6 @@
+// 派生宏生成的代码通过`::modbus_rs`引用本crate
+extern crate self as modbus_rs;
+
 pub mod protocol;
 pub mod utils;
 pub mod client;
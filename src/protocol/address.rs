@@ -0,0 +1,210 @@
+use super::{ModbusError, Table};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 数据表中的地址，`offset`为协议中使用的从0开始的地址
+///
+/// 可以从设备手册中常见的写法解析：
+/// * 5位Modicon地址：`00001`～`09999`线圈，`10001`～`19999`离散输入，`30001`～`39999`输入寄存器，`40001`～`49999`保持寄存器
+/// * 6位Modicon地址：`000001`～`065536`等，范围扩展到65536
+/// * 显式写法：`CO`、`DI`、`IR`、`HR`加从0开始的地址，例如`HR100`
+///
+/// Modicon地址从1开始，因此`40001`对应保持寄存器0。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct ModbusAddress {
+    pub table: Table,
+    #[serde(rename = "address")]
+    pub offset: u16,
+}
+
+impl ModbusAddress {
+    /// 创建地址
+    pub fn new(table: Table, offset: u16) -> Self {
+        Self { table, offset }
+    }
+
+    /// 保持寄存器地址
+    pub fn holding_register(offset: u16) -> Self {
+        Self::new(Table::HoldingRegisters, offset)
+    }
+
+    /// 输入寄存器地址
+    pub fn input_register(offset: u16) -> Self {
+        Self::new(Table::InputRegisters, offset)
+    }
+
+    /// 线圈地址
+    pub fn coil(offset: u16) -> Self {
+        Self::new(Table::Coils, offset)
+    }
+
+    /// 离散输入地址
+    pub fn discrete_input(offset: u16) -> Self {
+        Self::new(Table::DiscreteInputs, offset)
+    }
+
+    /// 6位Modicon写法，例如保持寄存器0为`400001`
+    pub fn to_modicon(&self) -> String {
+        format!("{}{:05}", table_digit(self.table), self.offset as u32 + 1)
+    }
+}
+
+fn table_digit(table: Table) -> char {
+    match table {
+        Table::Coils => '0',
+        Table::DiscreteInputs => '1',
+        Table::InputRegisters => '3',
+        Table::HoldingRegisters => '4',
+    }
+}
+
+fn table_prefix(table: Table) -> &'static str {
+    match table {
+        Table::Coils => "CO",
+        Table::DiscreteInputs => "DI",
+        Table::InputRegisters => "IR",
+        Table::HoldingRegisters => "HR",
+    }
+}
+
+const TABLES: [Table; 4] = [Table::Coils, Table::DiscreteInputs, Table::InputRegisters, Table::HoldingRegisters];
+
+impl FromStr for ModbusAddress {
+    type Err = ModbusError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let invalid = || ModbusError::InvalidValue(format!("Invalid Modbus address: {:?}", text));
+        let is_number = |digits: &str| !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit());
+
+        // 显式写法，地址从0开始
+        for table in TABLES {
+            let prefix = table_prefix(table);
+            if text.len() > prefix.len() && text.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix)) {
+                let digits = &text[prefix.len()..];
+                if !is_number(digits) {
+                    return Err(invalid());
+                }
+                let offset = digits.parse().map_err(|_| invalid())?;
+                return Ok(Self::new(table, offset));
+            }
+        }
+
+        // Modicon写法，地址从1开始
+        if !is_number(text) {
+            return Err(invalid());
+        }
+        let max = match text.len() {
+            5 => 9999,
+            6 => 65536,
+            _ => return Err(invalid()),
+        };
+        let table = TABLES
+            .into_iter()
+            .find(|&table| text.starts_with(table_digit(table)))
+            .ok_or_else(invalid)?;
+        let number: u32 = text[1..].parse().map_err(|_| invalid())?;
+        if !(1..=max).contains(&number) {
+            return Err(invalid());
+        }
+        Ok(Self::new(table, (number - 1) as u16))
+    }
+}
+
+impl TryFrom<&str> for ModbusAddress {
+    type Error = ModbusError;
+
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for ModbusAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", table_prefix(self.table), self.offset)
+    }
+}
+
+/// 配置文件中的地址：`"40001"`、`"HR100"`，或`table`加数字地址
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AddressRepr {
+    Text(String),
+    Fields {
+        #[serde(default)]
+        table: Option<Table>,
+        address: AddressField,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AddressField {
+    Offset(u16),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for ModbusAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match AddressRepr::deserialize(deserializer)? {
+            AddressRepr::Text(text) => text.parse().map_err(D::Error::custom),
+            AddressRepr::Fields { table: Some(table), address: AddressField::Offset(offset) } => Ok(Self::new(table, offset)),
+            AddressRepr::Fields { table: None, address: AddressField::Offset(offset) } => {
+                Err(D::Error::custom(format!("Missing table for numeric address {}", offset)))
+            },
+            AddressRepr::Fields { table, address: AddressField::Text(text) } => {
+                let address: Self = text.parse().map_err(D::Error::custom)?;
+                match table {
+                    Some(table) if table != address.table => {
+                        Err(D::Error::custom(format!("Address {} is not in table {:?}", text, table)))
+                    },
+                    _ => Ok(address),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!("40001".parse::<ModbusAddress>().unwrap(), ModbusAddress::holding_register(0));
+        assert_eq!("30101".parse::<ModbusAddress>().unwrap(), ModbusAddress::input_register(100));
+        assert_eq!("00001".parse::<ModbusAddress>().unwrap(), ModbusAddress::coil(0));
+        assert_eq!("10010".parse::<ModbusAddress>().unwrap(), ModbusAddress::discrete_input(9));
+        assert_eq!("400001".parse::<ModbusAddress>().unwrap(), ModbusAddress::holding_register(0));
+        assert_eq!("465536".parse::<ModbusAddress>().unwrap(), ModbusAddress::holding_register(65535));
+        assert_eq!("HR100".parse::<ModbusAddress>().unwrap(), ModbusAddress::holding_register(100));
+        assert_eq!("ir0".parse::<ModbusAddress>().unwrap(), ModbusAddress::input_register(0));
+        assert_eq!("CO7".parse::<ModbusAddress>().unwrap(), ModbusAddress::coil(7));
+        assert_eq!("DI65535".parse::<ModbusAddress>().unwrap(), ModbusAddress::discrete_input(65535));
+
+        for text in ["40000", "400000", "465537", "20001", "4001", "4000001", "HR", "HR-1", "HR65536", "X100", "4000a", "温度"] {
+            assert!(text.parse::<ModbusAddress>().is_err(), "{}", text);
+        }
+
+        let address = ModbusAddress::input_register(100);
+        assert_eq!(address.to_modicon(), "300101");
+        assert_eq!(address.to_string(), "IR100");
+        assert_eq!(address.to_modicon().parse::<ModbusAddress>().unwrap(), address);
+    }
+
+    #[test]
+    fn test_deserialize_address() {
+        let parse = |json: &str| serde_json::from_str::<ModbusAddress>(json);
+        assert_eq!(parse(r#""40010""#).unwrap(), ModbusAddress::holding_register(9));
+        assert_eq!(parse(r#"{"address": "HR5"}"#).unwrap(), ModbusAddress::holding_register(5));
+        assert_eq!(parse(r#"{"table": "coils", "address": 3}"#).unwrap(), ModbusAddress::coil(3));
+        assert_eq!(parse(r#"{"table": "input_registers", "address": "30001"}"#).unwrap(), ModbusAddress::input_register(0));
+        assert!(parse(r#"{"table": "coils", "address": "40001"}"#).is_err());
+        assert!(parse(r#"{"address": 3}"#).is_err());
+
+        let json = serde_json::to_string(&ModbusAddress::coil(3)).unwrap();
+        assert_eq!(json, r#"{"table":"coils","address":3}"#);
+    }
+}
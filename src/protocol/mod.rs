@@ -1,11 +1,14 @@
 pub mod modbus_rtu;
 pub mod modbus_tcp;
 pub mod modbus_rtu_over_tcp;
+pub mod address;
 
 pub use modbus_rtu::*;
 pub use modbus_tcp::*;
 pub use modbus_rtu_over_tcp::*;
+pub use address::*;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Modbus功能码
@@ -37,6 +40,27 @@ impl FunctionCode {
     }
 }
 
+/// Modbus数据表
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Table {
+    /// 线圈（可读写位）
+    Coils,
+    /// 离散输入（只读位）
+    DiscreteInputs,
+    /// 保持寄存器（可读写寄存器）
+    HoldingRegisters,
+    /// 输入寄存器（只读寄存器）
+    InputRegisters,
+}
+
+impl Table {
+    /// 是否为位类型的数据表
+    pub fn is_bit(&self) -> bool {
+        matches!(self, Table::Coils | Table::DiscreteInputs)
+    }
+}
+
 /// Modbus异常码
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExceptionCode {
@@ -80,6 +104,21 @@ pub enum ModbusError {
     
     #[error("Timeout error")]
     TimeoutError,
+    
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+    
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+    
+    #[error("Request queue is full")]
+    QueueFull,
+    
+    #[error("Modbus exception: {0:?}")]
+    Exception(ExceptionCode),
+    
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
 }
 
 /// Modbus请求结构
@@ -92,6 +131,54 @@ pub struct ModbusRequest {
     pub data: Option<Vec<u8>>,
 }
 
+impl ModbusRequest {
+    /// 从PDU（功能码及其后的数据）解析请求
+    ///
+    /// PDU长度与功能码不符，或写多个线圈/寄存器时字节数与实际数据长度不符，返回`InvalidDataLength`。
+    pub(crate) fn from_pdu(slave_id: u8, pdu: &[u8]) -> Result<Self, ModbusError> {
+        let (&code, body) = pdu.split_first().ok_or(ModbusError::InvalidDataLength)?;
+        let function_code = FunctionCode::from_u8(code)?;
+        let expected_len = match function_code {
+            // 地址、数量、字节数 + 数据
+            FunctionCode::WriteMultipleCoils | FunctionCode::WriteMultipleRegisters => {
+                body.get(4).map(|&byte_count| 5 + byte_count as usize)
+            },
+            // 地址 + 数量或值
+            _ => Some(4),
+        };
+        if expected_len != Some(body.len()) {
+            return Err(ModbusError::InvalidDataLength);
+        }
+
+        let word = |offset: usize| u16::from_be_bytes([body[offset], body[offset + 1]]);
+        let mut request = ModbusRequest {
+            slave_id,
+            function_code,
+            address: word(0),
+            count: 0,
+            data: None,
+        };
+        match function_code {
+            FunctionCode::ReadCoils |
+            FunctionCode::ReadDiscreteInputs |
+            FunctionCode::ReadHoldingRegisters |
+            FunctionCode::ReadInputRegisters |
+            FunctionCode::WriteSingleCoil => {
+                request.count = word(2);
+            },
+            FunctionCode::WriteSingleRegister => {
+                request.data = Some(body[2..4].to_vec());
+            },
+            FunctionCode::WriteMultipleCoils |
+            FunctionCode::WriteMultipleRegisters => {
+                request.count = word(2);
+                request.data = Some(body[5..].to_vec());
+            },
+        }
+        Ok(request)
+    }
+}
+
 /// Modbus响应结构
 #[derive(Debug, Clone)]
 pub struct ModbusResponse {
@@ -102,78 +189,157 @@ pub struct ModbusResponse {
     pub exception_code: Option<ExceptionCode>,
 }
 
+impl ModbusResponse {
+    /// 构建异常响应
+    pub fn exception(slave_id: u8, function_code: FunctionCode, exception_code: ExceptionCode) -> Self {
+        Self {
+            slave_id,
+            function_code,
+            data: Vec::new(),
+            is_exception: true,
+            exception_code: Some(exception_code),
+        }
+    }
+}
+
+/// 高低位顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    /// 高位在前
+    #[default]
+    Big,
+    /// 低位在前
+    Little,
+}
+
 /// 字节序类型
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// 由两个独立的设置组成：寄存器内的字节顺序和多个寄存器之间的字序，适用于任意寄存器数。
+/// 名称中的字母按数值从高到低的字节排列，例如32位值0xAABBCCDD以CDAB排列时线路上为CC DD AA BB。
+/// 单个寄存器没有字序之分，因此u16下CDAB与ABCD相同、BADC与DCBA相同。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ByteOrder {
-    /// ABCD：大端序-高字节在前
+    /// ABCD：字节大端，字序大端（高字在前）
     ABCD,
-    /// DCBA：小端序-低字节在前
+    /// DCBA：字节小端，字序小端，即整体小端序
     DCBA,
-    /// BADC：大端序-字节交换
+    /// BADC：字节小端（寄存器内字节交换），字序大端
     BADC,
-    /// CDAB：小端序-字节交换
+    /// CDAB：字节大端，字序小端（低字在前）
     CDAB,
 }
 
 impl ByteOrder {
-    /// 将字节数组转换为u16值
-    pub fn bytes_to_u16(&self, bytes: &[u8]) -> Result<u16, ModbusError> {
-        if bytes.len() < 2 {
-            return Err(ModbusError::InvalidDataLength);
+    /// 由寄存器内的字节顺序和寄存器之间的字序组合
+    pub fn from_parts(bytes_in_word: Endianness, word_order: Endianness) -> Self {
+        match (bytes_in_word, word_order) {
+            (Endianness::Big, Endianness::Big) => ByteOrder::ABCD,
+            (Endianness::Little, Endianness::Little) => ByteOrder::DCBA,
+            (Endianness::Little, Endianness::Big) => ByteOrder::BADC,
+            (Endianness::Big, Endianness::Little) => ByteOrder::CDAB,
         }
-        
+    }
+    
+    /// 寄存器内的字节顺序
+    pub fn bytes_in_word(&self) -> Endianness {
         match self {
-            ByteOrder::ABCD => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
-            ByteOrder::DCBA => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
-            ByteOrder::BADC => Ok(u16::from_be_bytes([bytes[1], bytes[0]])),
-            ByteOrder::CDAB => Ok(u16::from_le_bytes([bytes[1], bytes[0]])),
+            ByteOrder::ABCD | ByteOrder::CDAB => Endianness::Big,
+            ByteOrder::DCBA | ByteOrder::BADC => Endianness::Little,
         }
     }
     
-    /// 将u16值转换为字节数组
-    pub fn u16_to_bytes(&self, value: u16) -> [u8; 2] {
+    /// 寄存器之间的字序
+    pub fn word_order(&self) -> Endianness {
         match self {
-            ByteOrder::ABCD => value.to_be_bytes(),
-            ByteOrder::DCBA => value.to_le_bytes(),
-            ByteOrder::BADC => {
-                let bytes = value.to_be_bytes();
-                [bytes[1], bytes[0]]
-            },
-            ByteOrder::CDAB => {
-                let bytes = value.to_le_bytes();
-                [bytes[1], bytes[0]]
-            },
+            ByteOrder::ABCD | ByteOrder::BADC => Endianness::Big,
+            ByteOrder::DCBA | ByteOrder::CDAB => Endianness::Little,
         }
     }
     
-    /// 将字节数组转换为u32值
-    pub fn bytes_to_u32(&self, bytes: &[u8]) -> Result<u32, ModbusError> {
-        if bytes.len() < 4 {
-            return Err(ModbusError::InvalidDataLength);
+    /// 将线路上的寄存器字节转换为大端序字节，长度应为偶数
+    pub fn to_big_endian(&self, bytes: &[u8]) -> Vec<u8> {
+        self.reorder(bytes)
+    }
+    
+    /// 将大端序字节按本字节序排列为寄存器字节，长度应为偶数
+    pub fn from_big_endian(&self, bytes: &[u8]) -> Vec<u8> {
+        self.reorder(bytes)
+    }
+    
+    /// 字序反转和字节交换都是对称操作，两个方向通用
+    fn reorder(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut words: Vec<&[u8]> = bytes.chunks(2).collect();
+        if self.word_order() == Endianness::Little {
+            words.reverse();
         }
-        
-        match self {
-            ByteOrder::ABCD => Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
-            ByteOrder::DCBA => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
-            ByteOrder::BADC => Ok(u32::from_be_bytes([bytes[1], bytes[0], bytes[3], bytes[2]])),
-            ByteOrder::CDAB => Ok(u32::from_le_bytes([bytes[1], bytes[0], bytes[3], bytes[2]])),
+        let swap = self.bytes_in_word() == Endianness::Little;
+        words
+            .into_iter()
+            .flat_map(|word| {
+                let mut word = word.to_vec();
+                if swap {
+                    word.reverse();
+                }
+                word
+            })
+            .collect()
+    }
+    
+    /// 取前`N`个字节并转换为大端序
+    fn read<const N: usize>(&self, bytes: &[u8]) -> Result<[u8; N], ModbusError> {
+        if bytes.len() < N {
+            return Err(ModbusError::InvalidDataLength);
         }
+        self.to_big_endian(&bytes[..N]).try_into().map_err(|_| ModbusError::InvalidDataLength)
+    }
+    
+    /// 将字节数组转换为u16值
+    pub fn bytes_to_u16(&self, bytes: &[u8]) -> Result<u16, ModbusError> {
+        self.read(bytes).map(u16::from_be_bytes)
+    }
+    
+    /// 将u16值转换为字节数组
+    pub fn u16_to_bytes(&self, value: u16) -> [u8; 2] {
+        let bytes = self.from_big_endian(&value.to_be_bytes());
+        [bytes[0], bytes[1]]
+    }
+    
+    /// 将字节数组转换为u32值
+    pub fn bytes_to_u32(&self, bytes: &[u8]) -> Result<u32, ModbusError> {
+        self.read(bytes).map(u32::from_be_bytes)
     }
     
     /// 将u32值转换为字节数组
     pub fn u32_to_bytes(&self, value: u32) -> [u8; 4] {
-        match self {
-            ByteOrder::ABCD => value.to_be_bytes(),
-            ByteOrder::DCBA => value.to_le_bytes(),
-            ByteOrder::BADC => {
-                let bytes = value.to_be_bytes();
-                [bytes[1], bytes[0], bytes[3], bytes[2]]
-            },
-            ByteOrder::CDAB => {
-                let bytes = value.to_le_bytes();
-                [bytes[1], bytes[0], bytes[3], bytes[2]]
-            },
-        }
+        let mut result = [0; 4];
+        result.copy_from_slice(&self.from_big_endian(&value.to_be_bytes()));
+        result
+    }
+    
+    /// 将6个字节（3个寄存器）转换为48位无符号值
+    pub fn bytes_to_u48(&self, bytes: &[u8]) -> Result<u64, ModbusError> {
+        let bytes: [u8; 6] = self.read(bytes)?;
+        Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u64))
+    }
+    
+    /// 将48位无符号值的低48位转换为字节数组
+    pub fn u48_to_bytes(&self, value: u64) -> [u8; 6] {
+        let mut result = [0; 6];
+        result.copy_from_slice(&self.from_big_endian(&value.to_be_bytes()[2..]));
+        result
+    }
+    
+    /// 将字节数组转换为u64值
+    pub fn bytes_to_u64(&self, bytes: &[u8]) -> Result<u64, ModbusError> {
+        self.read(bytes).map(u64::from_be_bytes)
+    }
+    
+    /// 将u64值转换为字节数组
+    pub fn u64_to_bytes(&self, value: u64) -> [u8; 8] {
+        let mut result = [0; 8];
+        result.copy_from_slice(&self.from_big_endian(&value.to_be_bytes()));
+        result
     }
 }
 
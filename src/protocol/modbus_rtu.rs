@@ -47,7 +47,7 @@ impl ModbusRtu {
                 // 线圈数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = ((request.count + 7) / 8) as u8;
+                let byte_count = u8::try_from(request.count.div_ceil(8)).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 线圈数据
                 if let Some(data) = &request.data {
@@ -60,7 +60,7 @@ impl ModbusRtu {
                 // 寄存器数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = (request.count * 2) as u8;
+                let byte_count = u8::try_from(request.count as u32 * 2).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 寄存器数据
                 if let Some(data) = &request.data {
@@ -84,6 +84,14 @@ impl ModbusRtu {
             return Err(ModbusError::InvalidDataLength);
         }
         
+        // 验证CRC（异常响应同样校验）
+        let frame_data = &data[..data.len() - 2];
+        let received_crc = u16::from_le_bytes([data[data.len() - 2], data[data.len() - 1]]);
+        
+        if !verify_crc16(frame_data, received_crc) {
+            return Err(ModbusError::CrcCheckFailed);
+        }
+        
         let mut buf = Bytes::copy_from_slice(data);
         let slave_id = buf.get_u8();
         let function_code_byte = buf.get_u8();
@@ -117,18 +125,6 @@ impl ModbusRtu {
         
         let function_code = FunctionCode::from_u8(function_code_byte)?;
         
-        // 验证CRC
-        if data.len() < 4 {
-            return Err(ModbusError::InvalidDataLength);
-        }
-        
-        let frame_data = &data[..data.len() - 2];
-        let received_crc = u16::from_le_bytes([data[data.len() - 2], data[data.len() - 1]]);
-        
-        if !verify_crc16(frame_data, received_crc) {
-            return Err(ModbusError::CrcCheckFailed);
-        }
-        
         // 解析数据部分
         let mut response_data = Vec::new();
         
@@ -174,6 +170,21 @@ impl ModbusRtu {
         })
     }
     
+    /// 根据已收到的字节计算完整响应帧的长度，字节数不足以判断时返回None
+    pub fn response_frame_len(data: &[u8]) -> Option<usize> {
+        let function_code = *data.get(1)?;
+        if function_code & 0x80 != 0 {
+            // 从机地址 + 功能码 + 异常码 + CRC
+            return Some(5);
+        }
+        match function_code {
+            // 从机地址 + 功能码 + 字节数 + 数据 + CRC
+            0x01..=0x04 => data.get(2).map(|&byte_count| 5 + byte_count as usize),
+            // 写操作回显地址和值/数量
+            _ => Some(8),
+        }
+    }
+
     /// 构建RTU响应帧
     pub fn build_response(response: &ModbusResponse) -> Result<Bytes, ModbusError> {
         let mut frame = BytesMut::new();
@@ -198,59 +209,48 @@ impl ModbusRtu {
         Ok(frame.freeze())
     }
     
-    /// 解析RTU请求帧
+    /// 解析RTU请求帧（含CRC）
     pub fn parse_request(data: &[u8]) -> Result<ModbusRequest, ModbusError> {
         if data.len() < 4 {
             return Err(ModbusError::InvalidDataLength);
         }
         
-        let mut buf = Bytes::copy_from_slice(data);
-        let slave_id = buf.get_u8();
-        let function_code = FunctionCode::from_u8(buf.get_u8())?;
-        let address = buf.get_u16();
-        
-        let mut request = ModbusRequest {
-            slave_id,
-            function_code,
-            address,
-            count: 0,
-            data: None,
-        };
-        
-        match function_code {
-            FunctionCode::ReadCoils | 
-            FunctionCode::ReadDiscreteInputs | 
-            FunctionCode::ReadHoldingRegisters | 
-            FunctionCode::ReadInputRegisters => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleCoil => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleRegister => {
-                let value = buf.get_u16();
-                request.data = Some(value.to_be_bytes().to_vec());
-            },
-            FunctionCode::WriteMultipleCoils => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u8();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
-            FunctionCode::WriteMultipleRegisters => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u8();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
+        // 验证CRC
+        let frame_data = &data[..data.len() - 2];
+        let received_crc = u16::from_le_bytes([data[data.len() - 2], data[data.len() - 1]]);
+        if !verify_crc16(frame_data, received_crc) {
+            return Err(ModbusError::CrcCheckFailed);
         }
         
-        Ok(request)
+        ModbusRequest::from_pdu(frame_data[0], &frame_data[1..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_crc(data: &[u8]) -> Vec<u8> {
+        let mut frame = data.to_vec();
+        frame.extend_from_slice(&calculate_crc16(data).to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_parse_malformed_request() {
+        let valid = with_crc(&[0x01, 0x10, 0x00, 0x00, 0x00, 0x01, 0x02, 0x12, 0x34]);
+        let request = ModbusRtu::parse_request(&valid).unwrap();
+        assert_eq!(request.data, Some(vec![0x12, 0x34]));
+
+        for data in [
+            &[0x01, 0x03, 0x00][..],
+            &[0x01, 0x03, 0x00, 0x00, 0x00],
+            &[0x01, 0x10, 0x00, 0x00, 0x00, 0x02, 0x04, 0x12, 0x34],
+            &[0x01, 0x10, 0x00, 0x00, 0x00, 0x01],
+        ] {
+            assert!(matches!(ModbusRtu::parse_request(&with_crc(data)), Err(ModbusError::InvalidDataLength)), "{:02X?}", data);
+        }
+        assert!(matches!(ModbusRtu::parse_request(&[0x01, 0x03, 0x00]), Err(ModbusError::InvalidDataLength)));
+        assert!(matches!(ModbusRtu::parse_request(&valid[..valid.len() - 1]), Err(ModbusError::CrcCheckFailed)));
     }
 }
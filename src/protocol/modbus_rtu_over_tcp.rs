@@ -56,7 +56,7 @@ impl ModbusRtuOverTcp {
                 // 线圈数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = ((request.count + 7) / 8) as u8;
+                let byte_count = u8::try_from(request.count.div_ceil(8)).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 线圈数据
                 if let Some(data) = &request.data {
@@ -69,7 +69,7 @@ impl ModbusRtuOverTcp {
                 // 寄存器数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = (request.count * 2) as u8;
+                let byte_count = u8::try_from(request.count as u32 * 2).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 寄存器数据
                 if let Some(data) = &request.data {
@@ -189,57 +189,30 @@ impl ModbusRtuOverTcp {
     
     /// 解析RTU over TCP请求帧
     pub fn parse_request(data: &[u8]) -> Result<ModbusRequest, ModbusError> {
-        if data.len() < 4 {
+        if data.len() < 2 {
             return Err(ModbusError::InvalidDataLength);
         }
         
-        let mut buf = Bytes::copy_from_slice(data);
-        let slave_id = buf.get_u8();
-        let function_code = FunctionCode::from_u8(buf.get_u8())?;
-        let address = buf.get_u16();
-        
-        let mut request = ModbusRequest {
-            slave_id,
-            function_code,
-            address,
-            count: 0,
-            data: None,
+        ModbusRequest::from_pdu(data[0], &data[1..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_write_multiple_registers_request() {
+        let request = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::WriteMultipleRegisters,
+            address: 10,
+            count: 2,
+            data: Some(vec![0x12, 0x34, 0x56, 0x78]),
         };
-        
-        match function_code {
-            FunctionCode::ReadCoils | 
-            FunctionCode::ReadDiscreteInputs | 
-            FunctionCode::ReadHoldingRegisters | 
-            FunctionCode::ReadInputRegisters => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleCoil => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleRegister => {
-                let value = buf.get_u16();
-                request.data = Some(value.to_be_bytes().to_vec());
-            },
-            FunctionCode::WriteMultipleCoils => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u8();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
-            FunctionCode::WriteMultipleRegisters => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u16();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
-        }
-        
-        Ok(request)
+        // 字节数只占一个字节，按两个字节读取会吞掉第一个数据字节
+        let parsed = ModbusRtuOverTcp::parse_request(&ModbusRtuOverTcp::build_request(&request).unwrap()).unwrap();
+        assert_eq!(parsed.count, 2);
+        assert_eq!(parsed.data, request.data);
     }
 }
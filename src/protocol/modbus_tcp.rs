@@ -52,7 +52,7 @@ impl ModbusTcp {
                 // 线圈数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = ((request.count + 7) / 8) as u8;
+                let byte_count = u8::try_from(request.count.div_ceil(8)).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 线圈数据
                 if let Some(data) = &request.data {
@@ -65,7 +65,7 @@ impl ModbusTcp {
                 // 寄存器数量
                 frame.put_u16(request.count);
                 // 字节数
-                let byte_count = (request.count * 2) as u8;
+                let byte_count = u8::try_from(request.count as u32 * 2).map_err(|_| ModbusError::InvalidDataLength)?;
                 frame.put_u8(byte_count);
                 // 寄存器数据
                 if let Some(data) = &request.data {
@@ -214,71 +214,68 @@ impl ModbusTcp {
     
     /// 解析TCP请求帧
     pub fn parse_request(data: &[u8]) -> Result<(u16, ModbusRequest), ModbusError> {
-        if data.len() < 9 {
+        if data.len() < 8 {
             return Err(ModbusError::InvalidDataLength);
         }
         
-        let mut buf = Bytes::copy_from_slice(data);
-        
         // 解析MBAP头部
-        let transaction_id = buf.get_u16();
-        let protocol_id = buf.get_u16();
-        let length = buf.get_u16();
-        let unit_id = buf.get_u8();
+        let transaction_id = u16::from_be_bytes([data[0], data[1]]);
+        let protocol_id = u16::from_be_bytes([data[2], data[3]]);
+        let length = u16::from_be_bytes([data[4], data[5]]) as usize;
+        let unit_id = data[6];
         
         if protocol_id != 0x0000 {
             return Err(ModbusError::ProtocolError("Invalid protocol identifier".to_string()));
         }
         
-        if data.len() < (6 + length) as usize {
+        // 长度包含单元标识符
+        if length < 2 || data.len() < 6 + length {
             return Err(ModbusError::InvalidDataLength);
         }
         
-        let function_code = FunctionCode::from_u8(buf.get_u8())?;
-        let address = buf.get_u16();
-        
-        let mut request = ModbusRequest {
-            slave_id: unit_id,
-            function_code,
-            address,
-            count: 0,
-            data: None,
+        let request = ModbusRequest::from_pdu(unit_id, &data[7..6 + length])?;
+        Ok((transaction_id, request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_write_multiple_registers_request() {
+        let request = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::WriteMultipleRegisters,
+            address: 10,
+            count: 2,
+            data: Some(vec![0x12, 0x34, 0x56, 0x78]),
         };
-        
-        match function_code {
-            FunctionCode::ReadCoils | 
-            FunctionCode::ReadDiscreteInputs | 
-            FunctionCode::ReadHoldingRegisters | 
-            FunctionCode::ReadInputRegisters => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleCoil => {
-                request.count = buf.get_u16();
-            },
-            FunctionCode::WriteSingleRegister => {
-                let value = buf.get_u16();
-                request.data = Some(value.to_be_bytes().to_vec());
-            },
-            FunctionCode::WriteMultipleCoils => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u8();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
-            FunctionCode::WriteMultipleRegisters => {
-                request.count = buf.get_u16();
-                let byte_count = buf.get_u16();
-                let mut data = Vec::new();
-                for _ in 0..byte_count {
-                    data.push(buf.get_u8());
-                }
-                request.data = Some(data);
-            },
+        // 字节数只占一个字节，按两个字节读取会吞掉第一个数据字节
+        let (transaction_id, parsed) = ModbusTcp::parse_request(&ModbusTcp::build_request(&request, 9).unwrap()).unwrap();
+        assert_eq!(transaction_id, 9);
+        assert_eq!(parsed.count, 2);
+        assert_eq!(parsed.data, request.data);
+    }
+
+    #[test]
+    fn test_parse_malformed_request() {
+        let frames: [&[u8]; 6] = [
+            // 长度为3，PDU只有功能码和一个地址字节
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x03, 0x00],
+            // 长度为1，没有功能码
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x03],
+            // 读请求缺少数量
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x01, 0x03, 0x00, 0x00],
+            // 字节数为4，实际只有2个数据字节
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x09, 0x01, 0x10, 0x00, 0x00, 0x00, 0x02, 0x04, 0x12, 0x34],
+            // 缺少字节数
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x10, 0x00, 0x00, 0x00, 0x02],
+            // 长度超出实际数据
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x08, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01],
+        ];
+        for frame in frames {
+            assert!(matches!(ModbusTcp::parse_request(frame), Err(ModbusError::InvalidDataLength)), "{:02X?}", frame);
         }
-        
-        Ok((transaction_id, request))
     }
 }
@@ -0,0 +1,235 @@
+use crate::protocol::*;
+use super::hooks::{DataChange, TableValues};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// 地址范围的访问权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    /// 可读写
+    ReadWrite,
+    /// 只读，客户端写入被拒绝
+    ReadOnly,
+    /// 客户端只能写入一次，之后变为只读
+    WriteOnce,
+}
+
+/// 寄存器值约束
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueConstraint {
+    /// 按无符号数校验的取值范围（含两端）
+    Range { min: u16, max: u16 },
+    /// 按有符号数校验的取值范围（含两端）
+    SignedRange { min: i16, max: i16 },
+    /// 允许的枚举值
+    OneOf(Vec<u16>),
+}
+
+impl ValueConstraint {
+    /// 值是否满足约束
+    pub fn allows(&self, value: u16) -> bool {
+        match self {
+            ValueConstraint::Range { min, max } => (*min..=*max).contains(&value),
+            ValueConstraint::SignedRange { min, max } => (*min..=*max).contains(&(value as i16)),
+            ValueConstraint::OneOf(values) => values.contains(&value),
+        }
+    }
+}
+
+/// 一段地址范围的访问规则
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessRule {
+    /// 数据表
+    pub table: Table,
+    /// 地址范围（含两端）
+    pub addresses: RangeInclusive<u16>,
+    /// 访问权限
+    pub access: Access,
+    /// 值约束，仅对寄存器生效
+    pub constraint: Option<ValueConstraint>,
+    /// 权限不允许写入时回复的异常码
+    pub denied_exception: ExceptionCode,
+}
+
+impl AccessRule {
+    /// 创建访问规则，权限拒绝时默认回复IllegalDataAddress
+    pub fn new(table: Table, addresses: RangeInclusive<u16>, access: Access) -> Self {
+        Self {
+            table,
+            addresses,
+            access,
+            constraint: None,
+            denied_exception: ExceptionCode::IllegalDataAddress,
+        }
+    }
+
+    /// 只读规则
+    pub fn read_only(table: Table, addresses: RangeInclusive<u16>) -> Self {
+        Self::new(table, addresses, Access::ReadOnly)
+    }
+
+    /// 只能写入一次的规则
+    pub fn write_once(table: Table, addresses: RangeInclusive<u16>) -> Self {
+        Self::new(table, addresses, Access::WriteOnce)
+    }
+
+    /// 可读写规则，通常与值约束一起使用
+    pub fn read_write(table: Table, addresses: RangeInclusive<u16>) -> Self {
+        Self::new(table, addresses, Access::ReadWrite)
+    }
+
+    /// 限制取值范围（无符号）
+    pub fn with_range(mut self, min: u16, max: u16) -> Self {
+        self.constraint = Some(ValueConstraint::Range { min, max });
+        self
+    }
+
+    /// 限制取值范围（有符号）
+    pub fn with_signed_range(mut self, min: i16, max: i16) -> Self {
+        self.constraint = Some(ValueConstraint::SignedRange { min, max });
+        self
+    }
+
+    /// 限制为枚举值
+    pub fn with_allowed_values(mut self, values: Vec<u16>) -> Self {
+        self.constraint = Some(ValueConstraint::OneOf(values));
+        self
+    }
+
+    /// 设置权限拒绝时回复的异常码
+    pub fn with_denied_exception(mut self, exception_code: ExceptionCode) -> Self {
+        self.denied_exception = exception_code;
+        self
+    }
+
+    fn covers(&self, table: Table, address: u16) -> bool {
+        self.table == table && self.addresses.contains(&address)
+    }
+}
+
+/// 数据表的访问规则集合
+///
+/// 同一地址被多条规则覆盖时，后添加的规则生效；未被覆盖的地址可自由读写。
+#[derive(Debug, Clone, Default)]
+pub struct AccessMap {
+    rules: Vec<AccessRule>,
+    written_once: HashSet<(Table, u16)>,
+}
+
+impl AccessMap {
+    /// 创建空的访问规则集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加规则
+    pub fn add_rule(&mut self, rule: AccessRule) {
+        self.rules.push(rule);
+    }
+
+    /// 清除所有规则和一次写入记录
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.written_once.clear();
+    }
+
+    /// 所有规则
+    pub fn rules(&self) -> &[AccessRule] {
+        &self.rules
+    }
+
+    /// 查找覆盖该地址的规则
+    pub fn rule_for(&self, table: Table, address: u16) -> Option<&AccessRule> {
+        self.rules.iter().rev().find(|rule| rule.covers(table, address))
+    }
+
+    /// 检查客户端写入是否被允许
+    pub fn check_write(&self, change: &DataChange) -> Result<(), ExceptionCode> {
+        for i in 0..change.values.len() {
+            let address = change.address.wrapping_add(i as u16);
+            let rule = match self.rule_for(change.table, address) {
+                Some(rule) => rule,
+                None => continue,
+            };
+
+            match rule.access {
+                Access::ReadWrite => {},
+                Access::ReadOnly => return Err(rule.denied_exception),
+                Access::WriteOnce => {
+                    if self.written_once.contains(&(change.table, address)) {
+                        return Err(rule.denied_exception);
+                    }
+                },
+            }
+
+            if let (Some(constraint), TableValues::Registers(values)) = (&rule.constraint, &change.values) {
+                if !constraint.allows(values[i]) {
+                    return Err(ExceptionCode::IllegalDataValue);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 已经写入过的一次写入地址
+    pub fn written_once(&self) -> impl Iterator<Item = ModbusAddress> + '_ {
+        self.written_once.iter().map(|&(table, address)| ModbusAddress::new(table, address))
+    }
+
+    /// 替换一次写入记录，用于从快照恢复
+    pub fn set_written_once(&mut self, addresses: impl IntoIterator<Item = ModbusAddress>) {
+        self.written_once = addresses.into_iter().map(|address| (address.table, address.offset)).collect();
+    }
+
+    /// 记录一次成功的客户端写入
+    pub fn record_write(&mut self, change: &DataChange) {
+        for i in 0..change.values.len() {
+            let address = change.address.wrapping_add(i as u16);
+            if let Some(Access::WriteOnce) = self.rule_for(change.table, address).map(|rule| rule.access) {
+                self.written_once.insert((change.table, address));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(table: Table, address: u16, values: TableValues) -> DataChange {
+        DataChange {
+            unit_id: 1,
+            table,
+            address,
+            values,
+        }
+    }
+
+    #[test]
+    fn test_access_rules() {
+        let mut map = AccessMap::new();
+        map.add_rule(AccessRule::read_only(Table::HoldingRegisters, 0..=9));
+        map.add_rule(AccessRule::write_once(Table::Coils, 0..=0).with_denied_exception(ExceptionCode::IllegalDataValue));
+        map.add_rule(AccessRule::read_write(Table::HoldingRegisters, 10..=10).with_range(0, 100));
+        map.add_rule(AccessRule::read_write(Table::HoldingRegisters, 11..=11).with_allowed_values(vec![1, 2, 4]));
+        map.add_rule(AccessRule::read_write(Table::HoldingRegisters, 12..=12).with_signed_range(-10, 10));
+
+        let registers = |address, values: Vec<u16>| change(Table::HoldingRegisters, address, TableValues::Registers(values));
+
+        assert_eq!(map.check_write(&registers(5, vec![1])), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(map.check_write(&registers(9, vec![1, 50])), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(map.check_write(&registers(10, vec![50, 4])), Ok(()));
+        assert_eq!(map.check_write(&registers(10, vec![101])), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(map.check_write(&registers(11, vec![3])), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(map.check_write(&registers(12, vec![(-5i16) as u16])), Ok(()));
+        assert_eq!(map.check_write(&registers(12, vec![(-11i16) as u16])), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(map.check_write(&registers(100, vec![0xFFFF])), Ok(()));
+
+        let coil = change(Table::Coils, 0, TableValues::Bits(vec![true]));
+        assert_eq!(map.check_write(&coil), Ok(()));
+        map.record_write(&coil);
+        assert_eq!(map.check_write(&coil), Err(ExceptionCode::IllegalDataValue));
+    }
+}
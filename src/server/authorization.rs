@@ -0,0 +1,275 @@
+use crate::protocol::*;
+use super::limits::IpNetwork;
+use super::service::{Request, RequestContext};
+use std::ops::RangeInclusive;
+
+/// 审计日志使用的日志目标，可在日志配置中单独输出
+pub const AUDIT_LOG_TARGET: &str = "modbus_rs::audit";
+
+/// 授权结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthDecision {
+    /// 允许请求
+    Allow,
+    /// 拒绝请求，回复指定的异常码
+    Deny(ExceptionCode),
+}
+
+/// 一条授权规则
+///
+/// 未设置的条件匹配任意值。允许规则要求请求的地址范围完全落在规则范围内，
+/// 拒绝规则只要地址范围有重叠即生效。
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationRule {
+    /// 客户端所在网段，为空时匹配任意客户端（包括串口）
+    pub peers: Vec<IpNetwork>,
+    /// 单元ID，为空时匹配任意单元
+    pub unit_ids: Vec<u8>,
+    /// 功能码，为空时匹配任意功能码
+    pub function_codes: Vec<FunctionCode>,
+    /// 数据表
+    pub table: Option<Table>,
+    /// 地址范围（含两端）
+    pub addresses: Option<RangeInclusive<u16>>,
+    /// 匹配时的授权结果
+    pub decision: AuthDecision,
+}
+
+impl AuthorizationRule {
+    /// 创建授权规则
+    pub fn new(decision: AuthDecision) -> Self {
+        Self {
+            peers: Vec::new(),
+            unit_ids: Vec::new(),
+            function_codes: Vec::new(),
+            table: None,
+            addresses: None,
+            decision,
+        }
+    }
+
+    /// 允许规则
+    pub fn allow() -> Self {
+        Self::new(AuthDecision::Allow)
+    }
+
+    /// 拒绝规则，默认回复IllegalFunction
+    pub fn deny() -> Self {
+        Self::new(AuthDecision::Deny(ExceptionCode::IllegalFunction))
+    }
+
+    /// 限定客户端网段，可多次调用
+    pub fn from_peer(mut self, network: IpNetwork) -> Self {
+        self.peers.push(network);
+        self
+    }
+
+    /// 限定单元ID，可多次调用
+    pub fn for_unit(mut self, unit_id: u8) -> Self {
+        self.unit_ids.push(unit_id);
+        self
+    }
+
+    /// 限定功能码
+    pub fn for_functions(mut self, function_codes: &[FunctionCode]) -> Self {
+        self.function_codes.extend_from_slice(function_codes);
+        self
+    }
+
+    /// 限定为读功能码
+    pub fn reads(self) -> Self {
+        self.for_functions(&[
+            FunctionCode::ReadCoils,
+            FunctionCode::ReadDiscreteInputs,
+            FunctionCode::ReadHoldingRegisters,
+            FunctionCode::ReadInputRegisters,
+        ])
+    }
+
+    /// 限定为写功能码
+    pub fn writes(self) -> Self {
+        self.for_functions(&[
+            FunctionCode::WriteSingleCoil,
+            FunctionCode::WriteSingleRegister,
+            FunctionCode::WriteMultipleCoils,
+            FunctionCode::WriteMultipleRegisters,
+        ])
+    }
+
+    /// 限定数据表和地址范围
+    pub fn on(mut self, table: Table, addresses: RangeInclusive<u16>) -> Self {
+        self.table = Some(table);
+        self.addresses = Some(addresses);
+        self
+    }
+
+    /// 设置拒绝时回复的异常码，允许规则调用后变为拒绝规则
+    pub fn with_exception(mut self, exception_code: ExceptionCode) -> Self {
+        self.decision = AuthDecision::Deny(exception_code);
+        self
+    }
+
+    /// 规则是否匹配该请求
+    pub fn matches(&self, ctx: &RequestContext, request: &Request) -> bool {
+        if !self.peers.is_empty() {
+            match ctx.peer_addr {
+                Some(addr) if self.peers.iter().any(|network| network.contains(addr.ip())) => {},
+                _ => return false,
+            }
+        }
+        if !self.unit_ids.is_empty() && !self.unit_ids.contains(&ctx.unit_id) {
+            return false;
+        }
+        if !self.function_codes.is_empty() && !self.function_codes.contains(&request.function_code()) {
+            return false;
+        }
+        if self.table.is_some_and(|table| table != request.table()) {
+            return false;
+        }
+        if let Some(addresses) = &self.addresses {
+            let start = request.address();
+            let end = start.saturating_add(request.quantity().saturating_sub(1));
+            let matched = match self.decision {
+                AuthDecision::Allow => addresses.contains(&start) && addresses.contains(&end),
+                AuthDecision::Deny(_) => start <= *addresses.end() && end >= *addresses.start(),
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 按客户端、单元ID、功能码和地址范围授权请求
+///
+/// 规则按添加顺序匹配，第一条匹配的规则决定结果；没有规则匹配时使用默认结果。
+/// 被拒绝的请求写入审计日志（日志目标为`AUDIT_LOG_TARGET`）。
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationPolicy {
+    rules: Vec<AuthorizationRule>,
+    default: AuthDecision,
+}
+
+impl Default for AuthorizationPolicy {
+    fn default() -> Self {
+        Self::allow_by_default()
+    }
+}
+
+impl AuthorizationPolicy {
+    /// 没有规则匹配时允许请求
+    pub fn allow_by_default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: AuthDecision::Allow,
+        }
+    }
+
+    /// 没有规则匹配时拒绝请求，回复IllegalFunction
+    pub fn deny_by_default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: AuthDecision::Deny(ExceptionCode::IllegalFunction),
+        }
+    }
+
+    /// 设置没有规则匹配时的结果
+    pub fn with_default(mut self, decision: AuthDecision) -> Self {
+        self.default = decision;
+        self
+    }
+
+    /// 追加规则
+    pub fn with_rule(mut self, rule: AuthorizationRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// 所有规则
+    pub fn rules(&self) -> &[AuthorizationRule] {
+        &self.rules
+    }
+
+    /// 对请求进行授权
+    pub fn authorize(&self, ctx: &RequestContext, request: &Request) -> AuthDecision {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(ctx, request))
+            .map_or(self.default, |rule| rule.decision)
+    }
+
+    /// 对原始请求授权，拒绝时写入审计日志；无法解析的请求交给后续处理回复异常
+    pub(crate) fn check(&self, ctx: &RequestContext, request: &ModbusRequest) -> Result<(), ExceptionCode> {
+        let typed_request = match Request::from_modbus_request(request) {
+            Ok(typed_request) => typed_request,
+            Err(_) => return Ok(()),
+        };
+
+        match self.authorize(ctx, &typed_request) {
+            AuthDecision::Allow => Ok(()),
+            AuthDecision::Deny(code) => {
+                let peer = ctx.peer_addr.map_or_else(|| ctx.transport.to_string(), |addr| addr.to_string());
+                log::warn!(
+                    target: AUDIT_LOG_TARGET,
+                    "Denied {:?} from {} to unit {}: {:?} address {} quantity {}, replied {:?}",
+                    typed_request.function_code(),
+                    peer,
+                    ctx.unit_id,
+                    typed_request.table(),
+                    typed_request.address(),
+                    typed_request.quantity(),
+                    code
+                );
+                Err(code)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::service::TransportKind;
+
+    fn ctx(peer: &str, unit_id: u8) -> RequestContext {
+        RequestContext {
+            unit_id,
+            peer_addr: Some(peer.parse().unwrap()),
+            transport: TransportKind::Tcp,
+        }
+    }
+
+    #[test]
+    fn test_authorization_policy() {
+        let policy = AuthorizationPolicy::deny_by_default()
+            .with_rule(AuthorizationRule::allow().from_peer("10.1.0.0/16".parse().unwrap()).reads())
+            .with_rule(AuthorizationRule::allow()
+                .from_peer("10.2.0.10".parse().unwrap())
+                .writes()
+                .on(Table::HoldingRegisters, 1000..=1999))
+            .with_rule(AuthorizationRule::allow().from_peer("10.2.0.10".parse().unwrap()).reads());
+
+        let hmi = ctx("10.1.2.3:40000", 1);
+        let engineering = ctx("10.2.0.10:40000", 1);
+        let read = Request::ReadHoldingRegisters { address: 1000, count: 10 };
+        let write = |address| Request::WriteMultipleRegisters { address, values: vec![1, 2] };
+
+        assert_eq!(policy.authorize(&hmi, &read), AuthDecision::Allow);
+        assert_eq!(policy.authorize(&hmi, &write(1000)), AuthDecision::Deny(ExceptionCode::IllegalFunction));
+        assert_eq!(policy.authorize(&engineering, &write(1000)), AuthDecision::Allow);
+        // 跨出允许范围的写入被拒绝
+        assert_eq!(policy.authorize(&engineering, &write(1999)), AuthDecision::Deny(ExceptionCode::IllegalFunction));
+        assert_eq!(policy.authorize(&engineering, &read), AuthDecision::Allow);
+        assert_eq!(policy.authorize(&ctx("192.168.0.1:502", 1), &read), AuthDecision::Deny(ExceptionCode::IllegalFunction));
+
+        let policy = AuthorizationPolicy::allow_by_default()
+            .with_rule(AuthorizationRule::deny()
+                .for_unit(2)
+                .on(Table::Coils, 0..=9)
+                .with_exception(ExceptionCode::IllegalDataAddress));
+        let write_coils = Request::WriteMultipleCoils { address: 8, values: vec![true; 4] };
+        assert_eq!(policy.authorize(&ctx("10.0.0.1:1", 2), &write_coils), AuthDecision::Deny(ExceptionCode::IllegalDataAddress));
+        assert_eq!(policy.authorize(&ctx("10.0.0.1:1", 1), &write_coils), AuthDecision::Allow);
+    }
+}
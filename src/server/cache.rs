@@ -0,0 +1,243 @@
+use crate::protocol::*;
+use super::router::GatewayTarget;
+use super::service::*;
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 缓存规则：对某数据表的一段地址缓存读取结果，超过`max_age`后重新读取
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheRule {
+    units: Option<RangeInclusive<u8>>,
+    table: Table,
+    addresses: RangeInclusive<u16>,
+    max_age: Duration,
+}
+
+impl CacheRule {
+    /// 创建缓存规则，默认对所有单元ID生效
+    pub fn new(table: Table, addresses: RangeInclusive<u16>, max_age: Duration) -> Self {
+        Self {
+            units: None,
+            table,
+            addresses,
+            max_age,
+        }
+    }
+
+    /// 只对这些单元ID生效
+    pub fn for_units(mut self, units: RangeInclusive<u8>) -> Self {
+        self.units = Some(units);
+        self
+    }
+
+    /// 获取最大缓存时间
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    fn matches(&self, unit_id: u8, table: Table, start: u16, end: u16) -> bool {
+        self.units.as_ref().is_none_or(|units| units.contains(&unit_id))
+            && self.table == table
+            && self.addresses.contains(&start)
+            && self.addresses.contains(&end)
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// (单元ID, 数据表) -> 地址 -> (值, 读取时间)，线圈和离散输入以0/1保存
+    entries: HashMap<(u8, Table), HashMap<u16, (u16, Instant)>>,
+    /// 每次写入或清空后递增，读取期间发生过写入时不缓存该次结果
+    generation: u64,
+}
+
+/// 网关读缓存，包装一个下游目标
+///
+/// 读请求完全落在某条缓存规则内，且所有地址的缓存都未过期时直接用缓存回复，
+/// 因此与之前请求重叠的范围也能命中。写请求照常转发，并使它涉及的地址失效。
+/// 没有匹配规则的请求不经过缓存。
+pub struct ReadCache<T: GatewayTarget> {
+    target: T,
+    rules: Vec<CacheRule>,
+    state: Mutex<CacheState>,
+}
+
+impl<T: GatewayTarget> ReadCache<T> {
+    /// 包装下游目标，添加规则后才会缓存
+    pub fn new(target: T) -> Self {
+        Self {
+            target,
+            rules: Vec::new(),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// 添加缓存规则，多条规则匹配时使用第一条
+    pub fn with_rule(mut self, rule: CacheRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// 获取被包装的下游目标
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+
+    /// 清空所有缓存
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.generation += 1;
+    }
+
+    /// 经过缓存向下游转发请求
+    pub async fn forward(&self, unit_id: u8, request: &Request) -> Result<Response, ExceptionCode> {
+        let table = request.table();
+        let start = request.address();
+        let end = start.saturating_add(request.quantity().saturating_sub(1));
+
+        if request.is_write() {
+            let result = self.target.forward(unit_id, request).await;
+            // 写入失败时从机的数据也可能已经部分改变
+            self.invalidate(unit_id, table, start, end);
+            return result;
+        }
+
+        let max_age = match self.rules.iter().find(|rule| rule.matches(unit_id, table, start, end)) {
+            Some(rule) => rule.max_age,
+            None => return self.target.forward(unit_id, request).await,
+        };
+
+        let generation = {
+            let state = self.state.lock().unwrap();
+            if let Some(values) = lookup(&state, unit_id, table, start, end, max_age) {
+                log::trace!("Cache hit for unit {} {:?} {}..={}", unit_id, table, start, end);
+                return Ok(to_response(request, values));
+            }
+            state.generation
+        };
+
+        let response = self.target.forward(unit_id, request).await?;
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            let now = Instant::now();
+            let entries = state.entries.entry((unit_id, table)).or_default();
+            let values: Vec<u16> = match &response {
+                Response::ReadCoils(values) | Response::ReadDiscreteInputs(values) => {
+                    values.iter().map(|&value| value as u16).collect()
+                },
+                Response::ReadHoldingRegisters(values) | Response::ReadInputRegisters(values) => values.clone(),
+                _ => Vec::new(),
+            };
+            for (address, value) in (start..=end).zip(values) {
+                entries.insert(address, (value, now));
+            }
+        }
+        Ok(response)
+    }
+
+    fn invalidate(&self, unit_id: u8, table: Table, start: u16, end: u16) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        if let Some(entries) = state.entries.get_mut(&(unit_id, table)) {
+            entries.retain(|address, _| *address < start || *address > end);
+        }
+    }
+}
+
+fn lookup(state: &CacheState, unit_id: u8, table: Table, start: u16, end: u16, max_age: Duration) -> Option<Vec<u16>> {
+    let entries = state.entries.get(&(unit_id, table))?;
+    (start..=end)
+        .map(|address| {
+            let (value, read_at) = entries.get(&address)?;
+            (read_at.elapsed() <= max_age).then_some(*value)
+        })
+        .collect()
+}
+
+fn to_response(request: &Request, values: Vec<u16>) -> Response {
+    let bits = || values.iter().map(|&value| value != 0).collect();
+    match request {
+        Request::ReadCoils { .. } => Response::ReadCoils(bits()),
+        Request::ReadDiscreteInputs { .. } => Response::ReadDiscreteInputs(bits()),
+        Request::ReadInputRegisters { .. } => Response::ReadInputRegisters(values),
+        _ => Response::ReadHoldingRegisters(values),
+    }
+}
+
+impl<T: GatewayTarget> GatewayTarget for ReadCache<T> {
+    fn forward<'a>(&'a self, unit_id: u8, request: &'a Request) -> Pin<Box<dyn Future<Output = Result<Response, ExceptionCode>> + Send + 'a>> {
+        Box::pin(ReadCache::forward(self, unit_id, request))
+    }
+}
+
+impl<T: GatewayTarget> Service for ReadCache<T> {
+    async fn call(&self, ctx: RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        ReadCache::forward(self, ctx.unit_id, &request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 以内存表作为下游，并统计转发次数
+    struct CountingTarget {
+        memory: MemoryService,
+        calls: AtomicUsize,
+    }
+
+    impl GatewayTarget for CountingTarget {
+        fn forward<'a>(&'a self, unit_id: u8, request: &'a Request) -> Pin<Box<dyn Future<Output = Result<Response, ExceptionCode>> + Send + 'a>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let ctx = RequestContext { unit_id, peer_addr: None, transport: TransportKind::Rtu };
+            Box::pin(self.memory.call(ctx, request.clone()))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_cache() {
+        let memory = MemoryService::new();
+        memory.set_registers(Table::HoldingRegisters, 0, &[1, 2, 3, 4, 5]).unwrap();
+        let cache = ReadCache::new(CountingTarget { memory: memory.clone(), calls: AtomicUsize::new(0) })
+            .with_rule(CacheRule::new(Table::HoldingRegisters, 0..=99, Duration::from_millis(50)));
+        let calls = || cache.target().calls.load(Ordering::SeqCst);
+        let read = |address, count| Request::ReadHoldingRegisters { address, count };
+
+        assert_eq!(cache.forward(1, &read(0, 5)).await, Ok(Response::ReadHoldingRegisters(vec![1, 2, 3, 4, 5])));
+        // 重叠范围命中缓存，其他单元ID不共享缓存
+        assert_eq!(cache.forward(1, &read(2, 2)).await, Ok(Response::ReadHoldingRegisters(vec![3, 4])));
+        assert_eq!(calls(), 1);
+        cache.forward(2, &read(2, 2)).await.unwrap();
+        assert_eq!(calls(), 2);
+
+        // 写入使涉及的地址失效
+        cache.forward(1, &Request::WriteSingleRegister { address: 3, value: 40 }).await.unwrap();
+        assert_eq!(cache.forward(1, &read(0, 2)).await, Ok(Response::ReadHoldingRegisters(vec![1, 2])));
+        assert_eq!(calls(), 3);
+        assert_eq!(cache.forward(1, &read(2, 2)).await, Ok(Response::ReadHoldingRegisters(vec![3, 40])));
+        assert_eq!(calls(), 4);
+
+        // 时钟暂停，只有手动推进才会过期；未到期时仍返回缓存的旧值
+        memory.set_holding_register(0, 10);
+        tokio::time::advance(Duration::from_millis(49)).await;
+        assert_eq!(cache.forward(1, &read(0, 1)).await, Ok(Response::ReadHoldingRegisters(vec![1])));
+        assert_eq!(calls(), 4);
+
+        // 过期后重新读取
+        tokio::time::advance(Duration::from_millis(2)).await;
+        assert_eq!(cache.forward(1, &read(0, 1)).await, Ok(Response::ReadHoldingRegisters(vec![10])));
+        assert_eq!(calls(), 5);
+
+        // 超出规则范围的请求不缓存
+        cache.forward(1, &read(99, 2)).await.unwrap();
+        cache.forward(1, &read(99, 2)).await.unwrap();
+        assert_eq!(calls(), 7);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::protocol::*;
+use crate::utils::{i48_to_registers, string_to_registers, u48_to_registers, DataType, RegisterValue};
+use super::access::{Access, AccessRule};
+use super::service::{MemoryService, MultiSlaveMemoryService};
+use super::simulator::SimulatedPoint;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 初始值，可以是单个值（用于所有`count`个点）或列表
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InitialValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<InitialValue>),
+}
+
+/// 一组连续的点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointConfig {
+    /// 点名称，仅用于说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 起始地址：`table`加数字地址，或不带`table`的Modicon/显式写法，例如`"40001"`、`"HR100"`
+    #[serde(flatten)]
+    pub address: ModbusAddress,
+    /// 数据类型，省略时位表为`bool`，寄存器表为`u16`
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<DataType>,
+    /// 连续点的个数
+    #[serde(default = "default_count")]
+    pub count: u16,
+    /// 字符串占用的寄存器数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<u16>,
+    /// 字节序，省略时为ABCD；字符串只区分是否交换寄存器内的两个字节（BADC/DCBA）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_order: Option<ByteOrder>,
+    /// 初始值，省略时为0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<InitialValue>,
+    /// 访问权限，省略时可读写且不添加规则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<Access>,
+}
+
+fn default_count() -> u16 {
+    1
+}
+
+/// 一个从机的配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlaveConfig {
+    /// 单元ID
+    pub unit_id: u8,
+    /// 点配置
+    #[serde(default)]
+    pub points: Vec<PointConfig>,
+    /// 仿真点，由`Simulator::from_config`使用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub simulation: Vec<SimulatedPoint>,
+}
+
+/// 服务器数据配置文件
+///
+/// ```json
+/// {
+///   "slaves": [
+///     {
+///       "unit_id": 1,
+///       "points": [
+///         { "name": "temperature", "table": "input_registers", "address": 0, "type": "f32", "byte_order": "CDAB", "value": 21.5 },
+///         { "name": "setpoints", "address": "400101", "count": 3, "value": [10, 20, 30] },
+///         { "name": "serial", "table": "holding_registers", "address": 200, "type": "string", "length": 4, "value": "SN-0001", "access": "read_only" },
+///         { "table": "coils", "address": 0, "count": 8, "value": false }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// 从机配置
+    #[serde(default)]
+    pub slaves: Vec<SlaveConfig>,
+    /// 仿真更新周期（毫秒），省略时为1秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulation_interval_ms: Option<u64>,
+}
+
+impl ServerConfig {
+    /// 从JSON字符串解析配置
+    pub fn from_json(json: &str) -> Result<Self, ModbusError> {
+        serde_json::from_str(json).map_err(|e| ModbusError::ConfigError(e.to_string()))
+    }
+
+    /// 从JSON文件读取配置
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ModbusError> {
+        let json = std::fs::read_to_string(path.as_ref())?;
+        Self::from_json(&json)
+    }
+
+    /// 获取唯一的从机配置，用于单从机服务器
+    pub fn single_slave(&self) -> Result<&SlaveConfig, ModbusError> {
+        match self.slaves.as_slice() {
+            [slave] => Ok(slave),
+            slaves => Err(ModbusError::ConfigError(format!(
+                "Expected exactly one slave in config, found {}",
+                slaves.len()
+            ))),
+        }
+    }
+
+    /// 将所有从机的配置写入多从机服务，不存在的从机会被添加
+    pub fn apply(&self, service: &MultiSlaveMemoryService) -> Result<(), ModbusError> {
+        for slave in &self.slaves {
+            if service.slave(slave.unit_id).is_none() {
+                service.add_slave(slave.unit_id);
+            }
+            if let Some(memory) = service.slave(slave.unit_id) {
+                slave.apply(&memory)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按配置创建多从机服务
+    pub fn build_multi_slave(&self) -> Result<MultiSlaveMemoryService, ModbusError> {
+        let service = MultiSlaveMemoryService::new();
+        self.apply(&service)?;
+        Ok(service)
+    }
+}
+
+impl SlaveConfig {
+    /// 将点配置写入内存服务：设置初始值并添加访问规则
+    pub fn apply(&self, service: &MemoryService) -> Result<(), ModbusError> {
+        for point in &self.points {
+            point.apply(service).map_err(|e| match e {
+                ModbusError::ConfigError(message) => ModbusError::ConfigError(format!(
+                    "Unit {} {:?} address {}: {}",
+                    self.unit_id, point.address.table, point.address.offset, message
+                )),
+                other => other,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 按配置创建内存服务
+    pub fn build(&self) -> Result<MemoryService, ModbusError> {
+        let service = MemoryService::new();
+        self.apply(&service)?;
+        Ok(service)
+    }
+}
+
+impl PointConfig {
+    /// 点的数据类型（考虑省略时的默认值）
+    pub fn data_type(&self) -> DataType {
+        self.data_type.unwrap_or(if self.address.table.is_bit() { DataType::Bool } else { DataType::U16 })
+    }
+
+    /// 所有点占用的地址数
+    pub fn span(&self) -> u32 {
+        self.data_type().width(self.length) as u32 * self.count as u32
+    }
+
+    fn apply(&self, service: &MemoryService) -> Result<(), ModbusError> {
+        let data_type = self.data_type();
+        if self.address.table.is_bit() != (data_type == DataType::Bool) {
+            return Err(config_error(format!("type {:?} is not valid for this table", data_type)));
+        }
+        if data_type == DataType::String && self.length.unwrap_or(0) == 0 {
+            return Err(config_error("string points require a non-zero length"));
+        }
+        if self.count == 0 {
+            return Ok(());
+        }
+        let span = self.span();
+        if self.address.offset as u32 + span > 0x10000 {
+            return Err(config_error("address range exceeds 65535"));
+        }
+
+        let values = self.values()?;
+        if self.address.table.is_bit() {
+            for (i, value) in values.iter().enumerate() {
+                let value = match value {
+                    InitialValue::Bool(value) => *value,
+                    InitialValue::Integer(value) => *value != 0,
+                    other => return Err(config_error(format!("invalid bool value {:?}", other))),
+                };
+                let address = self.address.offset + i as u16;
+                match self.address.table {
+                    Table::Coils => service.set_coil(address, value),
+                    _ => service.set_discrete_input(address, value),
+                }
+            }
+        } else {
+            let mut registers = Vec::with_capacity(span as usize);
+            for value in &values {
+                registers.extend(self.encode(data_type, value)?);
+            }
+            service.set_registers(self.address.table, self.address.offset, &registers)?;
+        }
+
+        if let Some(access) = self.access {
+            let end = (self.address.offset as u32 + span - 1) as u16;
+            service.add_access_rule(AccessRule::new(self.address.table, self.address.offset..=end, access));
+        }
+        Ok(())
+    }
+
+    /// 展开为每个点一个初始值
+    fn values(&self) -> Result<Vec<InitialValue>, ModbusError> {
+        let count = self.count as usize;
+        match &self.value {
+            None => Ok(vec![InitialValue::Integer(0); count]),
+            Some(InitialValue::List(values)) if values.len() == count => Ok(values.clone()),
+            Some(InitialValue::List(values)) => Err(config_error(format!(
+                "expected {} values, found {}",
+                count,
+                values.len()
+            ))),
+            Some(value) => Ok(vec![value.clone(); count]),
+        }
+    }
+
+    /// 将一个初始值编码为寄存器
+    fn encode(&self, data_type: DataType, value: &InitialValue) -> Result<Vec<u16>, ModbusError> {
+        let byte_order = self.byte_order.unwrap_or(ByteOrder::ABCD);
+        let invalid = || config_error(format!("invalid {:?} value {:?}", data_type, value));
+
+        match (data_type, value) {
+            (DataType::U16, InitialValue::Integer(v)) => {
+                u16::try_from(*v).map(|v| vec![v]).map_err(|_| invalid())
+            },
+            (DataType::I16, InitialValue::Integer(v)) => {
+                i16::try_from(*v).map(|v| v.to_registers(byte_order)).map_err(|_| invalid())
+            },
+            (DataType::U32, InitialValue::Integer(v)) => {
+                u32::try_from(*v).map(|v| v.to_registers(byte_order)).map_err(|_| invalid())
+            },
+            (DataType::I32, InitialValue::Integer(v)) => {
+                i32::try_from(*v).map(|v| v.to_registers(byte_order)).map_err(|_| invalid())
+            },
+            (DataType::F32, InitialValue::Integer(v)) => Ok((*v as f32).to_registers(byte_order)),
+            (DataType::F32, InitialValue::Float(v)) => Ok((*v as f32).to_registers(byte_order)),
+            (DataType::U48, InitialValue::Integer(v)) => {
+                u64::try_from(*v).map_err(|_| invalid()).and_then(|v| u48_to_registers(v, byte_order).map_err(|_| invalid()))
+            },
+            (DataType::I48, InitialValue::Integer(v)) => i48_to_registers(*v, byte_order).map_err(|_| invalid()),
+            (DataType::U64, InitialValue::Integer(v)) => {
+                u64::try_from(*v).map(|v| v.to_registers(byte_order)).map_err(|_| invalid())
+            },
+            (DataType::I64, InitialValue::Integer(v)) => Ok(v.to_registers(byte_order)),
+            (DataType::F64, InitialValue::Integer(v)) => Ok((*v as f64).to_registers(byte_order)),
+            (DataType::F64, InitialValue::Float(v)) => Ok(v.to_registers(byte_order)),
+            (DataType::String, InitialValue::Text(text)) => {
+                let length = self.length.unwrap_or(0) as usize;
+                string_to_registers(text, length, byte_order)
+                    .map_err(|_| config_error(format!("string {:?} longer than {} bytes", text, length * 2)))
+            },
+            (DataType::String, InitialValue::Integer(0)) => Ok(vec![0; self.length.unwrap_or(0) as usize]),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn config_error(message: impl Into<String>) -> ModbusError {
+    ModbusError::ConfigError(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{Request, RequestContext, TransportKind};
+    use crate::protocol::ExceptionCode;
+
+    #[test]
+    fn test_load_config() {
+        let config = ServerConfig::from_json(r#"{
+            "slaves": [
+                {
+                    "unit_id": 1,
+                    "points": [
+                        { "table": "input_registers", "address": 0, "type": "f32", "byte_order": "CDAB", "value": 1.5 },
+                        { "table": "holding_registers", "address": 10, "count": 3, "value": [1, 2, 3] },
+                        { "table": "holding_registers", "address": 20, "type": "i32", "value": -2 },
+                        { "table": "holding_registers", "address": 30, "type": "string", "length": 2, "value": "ABC", "access": "read_only" },
+                        { "table": "coils", "address": 5, "count": 2, "value": true }
+                    ]
+                },
+                { "unit_id": 2 }
+            ]
+        }"#).unwrap();
+
+        let service = config.build_multi_slave().unwrap();
+        let mut ids = service.get_slave_ids();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+
+        let slave = service.slave(1).unwrap();
+        // 1.5f32 = 0x3FC00000，CDAB为低位字在前
+        assert_eq!(slave.get_input_register(0), 0x0000);
+        assert_eq!(slave.get_input_register(1), 0x3FC0);
+        assert_eq!((10..13).map(|a| slave.get_holding_register(a)).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(slave.get_holding_register(20), 0xFFFF);
+        assert_eq!(slave.get_holding_register(21), 0xFFFE);
+        assert_eq!(slave.get_holding_register(30), 0x4142);
+        assert_eq!(slave.get_holding_register(31), 0x4300);
+        assert!(slave.get_coil(5) && slave.get_coil(6));
+
+        let ctx = RequestContext { unit_id: 1, peer_addr: None, transport: TransportKind::Tcp };
+        let write = Request::WriteSingleRegister { address: 31, value: 0 };
+        assert_eq!(slave.handle(&ctx, write).unwrap_err(), ExceptionCode::IllegalDataAddress);
+
+        assert!(config.single_slave().is_err());
+        let invalid = ServerConfig::from_json(r#"{ "slaves": [{ "unit_id": 1, "points": [
+            { "table": "holding_registers", "address": 0, "value": 70000 }
+        ]}]}"#).unwrap();
+        assert!(invalid.build_multi_slave().is_err());
+    }
+
+    #[test]
+    fn test_load_text_addresses() {
+        let config = ServerConfig::from_json(r#"{ "slaves": [{ "unit_id": 1, "points": [
+            { "address": "40011", "count": 3, "value": [1, 2, 3] },
+            { "address": "HR20", "type": "i32", "value": -2 },
+            { "address": "10006", "value": true },
+            { "table": "input_registers", "address": "30001", "value": 7 }
+        ]}]}"#).unwrap();
+        let point = &config.slaves[0].points[0];
+        assert_eq!(point.address, ModbusAddress::holding_register(10));
+
+        let slave = config.build_multi_slave().unwrap().slave(1).unwrap();
+        assert_eq!((10..13).map(|a| slave.get_holding_register(a)).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(slave.get_holding_register(21), 0xFFFE);
+        assert!(slave.get_discrete_input(5));
+        assert_eq!(slave.get_input_register(0), 7);
+
+        // 数据表与Modicon地址不一致
+        let conflict = ServerConfig::from_json(r#"{ "slaves": [{ "unit_id": 1, "points": [
+            { "table": "coils", "address": "40001", "value": true }
+        ]}]}"#);
+        assert!(conflict.is_err());
+    }
+
+    #[test]
+    fn test_load_wide_types() {
+        let config = ServerConfig::from_json(r#"{ "slaves": [{ "unit_id": 1, "points": [
+            { "address": "HR0", "type": "u64", "byte_order": "CDAB", "value": 81985529216486895 },
+            { "address": "HR4", "type": "i48", "value": -2 },
+            { "address": "HR7", "type": "u48", "value": 281474976710655 },
+            { "address": "HR10", "type": "f64", "value": -1.25 }
+        ]}]}"#).unwrap();
+        let slave = config.build_multi_slave().unwrap().slave(1).unwrap();
+        assert_eq!(slave.get_registers(Table::HoldingRegisters, 0, 4).unwrap(), vec![0xCDEF, 0x89AB, 0x4567, 0x0123]);
+        assert_eq!(slave.get_registers(Table::HoldingRegisters, 4, 3).unwrap(), vec![0xFFFF, 0xFFFF, 0xFFFE]);
+        assert_eq!(slave.get_registers(Table::HoldingRegisters, 7, 3).unwrap(), vec![0xFFFF; 3]);
+        assert_eq!(slave.get_value::<f64>(Table::HoldingRegisters, 10, ByteOrder::ABCD).unwrap(), -1.25);
+
+        // 超出48位
+        let overflow = ServerConfig::from_json(r#"{ "slaves": [{ "unit_id": 1, "points": [
+            { "address": "HR0", "type": "u48", "value": 281474976710656 }
+        ]}]}"#).unwrap();
+        assert!(overflow.build_multi_slave().is_err());
+    }
+}
@@ -0,0 +1,831 @@
+use crate::protocol::*;
+use super::access::AccessRule;
+use super::authorization::AuthorizationPolicy;
+use super::faults::{FaultInjector, FaultPlan};
+use super::framing::*;
+use super::hooks::DataChange;
+use super::persistence::*;
+use super::limits::{ConnectionLimits, LimitPolicy};
+use super::service::*;
+use super::simulator::{SimulationTarget, Simulator};
+use crate::utils::RegisterValue;
+use super::shutdown::ShutdownHandle;
+use bytes::{Buf, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_serial::SerialStream;
+use tokio_util::sync::CancellationToken;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 单元ID路由规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitRouting {
+    /// 只处理指定单元ID的请求，其他单元ID的请求静默丢弃
+    Single(u8),
+    /// 所有单元ID的请求都交给服务，由服务决定是否回复异常
+    All,
+}
+
+impl UnitRouting {
+    /// 是否处理该单元ID的请求
+    pub fn accepts(&self, unit_id: u8) -> bool {
+        match self {
+            UnitRouting::Single(id) => *id == unit_id,
+            UnitRouting::All => true,
+        }
+    }
+}
+
+impl From<u8> for UnitRouting {
+    fn from(unit_id: u8) -> Self {
+        UnitRouting::Single(unit_id)
+    }
+}
+
+/// TCP监听传输，帧格式由`F`决定（Modbus TCP或RTU over TCP）
+pub struct TcpTransport<F: Framing> {
+    /// `run`时取出，关闭后随之释放，不再接受新连接
+    listener: Mutex<Option<TcpListener>>,
+    local_addr: SocketAddr,
+    limits: ConnectionLimits,
+    _framing: PhantomData<F>,
+}
+
+/// 串口传输，使用RTU帧格式
+pub struct SerialTransport {
+    port: SerialStream,
+}
+
+/// 通用Modbus服务器
+///
+/// 传输方式`T`负责接收连接和切分数据帧，单元ID按`UnitRouting`过滤后，
+/// 请求交给服务`S`处理。所有协议共用同一套请求处理流程。
+pub struct ModbusServer<T, S: Service> {
+    transport: T,
+    routing: UnitRouting,
+    service: Arc<S>,
+    authorization: Arc<AuthorizationPolicy>,
+    faults: Option<Arc<FaultInjector>>,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
+}
+
+/// 一条连接上处理请求所需的配置
+#[derive(Clone)]
+pub(crate) struct ConnectionOptions {
+    pub(crate) routing: UnitRouting,
+    pub(crate) authorization: Arc<AuthorizationPolicy>,
+    pub(crate) limits: Arc<ConnectionLimits>,
+    pub(crate) faults: Option<Arc<FaultInjector>>,
+}
+
+/// 默认关闭超时：等待处理中的请求完成的最长时间
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl<T, S: Service> ModbusServer<T, S> {
+    /// 获取请求处理服务
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    /// 获取单元ID路由规则
+    pub fn routing(&self) -> UnitRouting {
+        self.routing
+    }
+
+    /// 获取关闭句柄
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// 设置授权策略，在`run`之前调用
+    pub fn set_authorization_policy(&mut self, policy: AuthorizationPolicy) {
+        self.authorization = Arc::new(policy);
+    }
+
+    /// 获取授权策略
+    pub fn authorization_policy(&self) -> &AuthorizationPolicy {
+        &self.authorization
+    }
+
+    /// 设置故障注入器，用于测试主站的容错能力，在`run`之前调用
+    pub fn set_fault_injector(&mut self, injector: FaultInjector) {
+        self.faults = Some(Arc::new(injector));
+    }
+
+    /// 关闭故障注入
+    pub fn clear_fault_injector(&mut self) {
+        self.faults = None;
+    }
+
+    fn connection_options(&self, limits: ConnectionLimits) -> ConnectionOptions {
+        ConnectionOptions {
+            routing: self.routing,
+            authorization: Arc::clone(&self.authorization),
+            limits: Arc::new(limits),
+            faults: self.faults.clone(),
+        }
+    }
+
+    /// 设置关闭超时，超时后仍未完成的连接被强制断开
+    pub fn set_shutdown_timeout(&mut self, timeout: Duration) {
+        self.shutdown_timeout = timeout;
+    }
+}
+
+impl<T, S: Service + Persistent> ModbusServer<T, S> {
+    /// 从文件恢复数据（文件不存在时跳过），并在后台按指定方式保存到该文件，
+    /// 服务器关闭时再保存一次
+    pub async fn enable_persistence(&self, path: impl Into<std::path::PathBuf>, mode: PersistMode) -> Result<JoinHandle<()>, ModbusError> {
+        spawn_persistence((*self.service).clone(), path, mode, self.shutdown.clone()).await
+    }
+}
+
+impl<T, S: Service + SimulationTarget> ModbusServer<T, S> {
+    /// 在后台运行仿真引擎，服务器关闭时停止
+    pub fn start_simulator(&self, simulator: Simulator) -> JoinHandle<()> {
+        simulator.spawn((*self.service).clone(), self.shutdown.clone())
+    }
+}
+
+impl<S: Service> ModbusServer<TcpTransport<TcpFraming>, S> {
+    /// 创建Modbus TCP服务器
+    pub async fn tcp(addr: &str, routing: impl Into<UnitRouting>, service: S) -> Result<Self, ModbusError> {
+        Self::bind(addr, routing.into(), service).await
+    }
+}
+
+impl<S: Service> ModbusServer<TcpTransport<RtuOverTcpFraming>, S> {
+    /// 创建Modbus RTU over TCP服务器
+    pub async fn rtu_over_tcp(addr: &str, routing: impl Into<UnitRouting>, service: S) -> Result<Self, ModbusError> {
+        Self::bind(addr, routing.into(), service).await
+    }
+}
+
+impl<F: Framing, S: Service> ModbusServer<TcpTransport<F>, S> {
+    async fn bind(addr: &str, routing: UnitRouting, service: S) -> Result<Self, ModbusError> {
+        let listener = TcpListener::bind(addr).await
+            .map_err(|e| ModbusError::NetworkError(e.to_string()))?;
+        let local_addr = listener.local_addr()
+            .map_err(|e| ModbusError::NetworkError(e.to_string()))?;
+
+        Ok(Self {
+            transport: TcpTransport {
+                listener: Mutex::new(Some(listener)),
+                local_addr,
+                limits: ConnectionLimits::default(),
+                _framing: PhantomData,
+            },
+            routing,
+            service: Arc::new(service),
+            authorization: Arc::new(AuthorizationPolicy::default()),
+            faults: None,
+            shutdown: ShutdownHandle::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        })
+    }
+
+    /// 获取实际监听的本地地址（绑定端口0时用于获取分配的端口）
+    pub fn local_addr(&self) -> Result<SocketAddr, ModbusError> {
+        Ok(self.transport.local_addr)
+    }
+
+    /// 设置连接限制（最大连接数、超时和允许的网段），在`run`之前调用
+    pub fn set_connection_limits(&mut self, limits: ConnectionLimits) {
+        self.transport.limits = limits;
+    }
+
+    /// 获取连接限制
+    pub fn connection_limits(&self) -> &ConnectionLimits {
+        &self.transport.limits
+    }
+
+    /// 运行服务器，直到通过关闭句柄请求关闭
+    ///
+    /// 返回时监听端口已经关闭，同一个服务器不能再次运行。
+    pub async fn run(&self) -> Result<(), ModbusError> {
+        let listener = self.transport.listener.lock().unwrap().take()
+            .ok_or_else(|| ModbusError::NetworkError(format!("{} server is already running or stopped", F::TRANSPORT)))?;
+        let token = self.shutdown.token().clone();
+        let options = self.connection_options(self.transport.limits.clone());
+        let limits = Arc::clone(&options.limits);
+        let mut connections = JoinSet::new();
+        // 按建立顺序记录活动连接，用于淘汰最早的连接
+        let mut active: VecDeque<(u64, CancellationToken)> = VecDeque::new();
+        let mut next_id: u64 = 0;
+
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        if !limits.is_allowed(addr.ip()) {
+                            log::warn!("Rejected {} connection from {}: address not allowed", F::TRANSPORT, addr);
+                            continue;
+                        }
+
+                        if let Some(max_connections) = limits.max_connections {
+                            if active.len() >= max_connections {
+                                match limits.policy {
+                                    LimitPolicy::Reject => {
+                                        log::warn!("Rejected {} connection from {}: connection limit {} reached", F::TRANSPORT, addr, max_connections);
+                                        continue;
+                                    },
+                                    LimitPolicy::EvictOldest => {
+                                        while active.len() >= max_connections.max(1) {
+                                            if let Some((_, oldest)) = active.pop_front() {
+                                                oldest.cancel();
+                                            }
+                                        }
+                                        log::info!("Evicted oldest {} connection to accept {}", F::TRANSPORT, addr);
+                                    },
+                                }
+                            }
+                        }
+
+                        log::info!("New {} connection from: {}", F::TRANSPORT, addr);
+
+                        let id = next_id;
+                        next_id += 1;
+                        let connection_token = token.child_token();
+                        active.push_back((id, connection_token.clone()));
+
+                        let service = Arc::clone(&self.service);
+                        let options = options.clone();
+
+                        connections.spawn(async move {
+                            let mut stream = stream;
+                            if let Err(e) = serve_connection::<F, _, _>(&mut stream, Some(addr), &*service, &options, &connection_token).await {
+                                log::error!("Error handling {} client: {}", F::TRANSPORT, e);
+                            }
+                            id
+                        });
+                    },
+                    Err(e) => {
+                        log::error!("Failed to accept {} connection: {}", F::TRANSPORT, e);
+                    }
+                },
+                // 回收已结束的连接任务
+                Some(finished) = connections.join_next(), if !connections.is_empty() => {
+                    if let Ok(id) = finished {
+                        active.retain(|(active_id, _)| *active_id != id);
+                    }
+                },
+            }
+        }
+
+        // 先关闭监听端口，排空连接期间新的连接请求直接被拒绝
+        drop(listener);
+        log::info!("{} server shutting down, {} connection(s) active", F::TRANSPORT, connections.len());
+        let drain = async { while connections.join_next().await.is_some() {} };
+        if tokio::time::timeout(self.shutdown_timeout, drain).await.is_err() {
+            log::warn!("{} server shutdown timed out, aborting {} connection(s)", F::TRANSPORT, connections.len());
+            connections.shutdown().await;
+        }
+
+        self.shutdown.mark_stopped();
+        Ok(())
+    }
+}
+
+impl<S: Service> ModbusServer<SerialTransport, S> {
+    /// 创建Modbus RTU串口服务器
+    pub async fn rtu(port_name: &str, baud_rate: u32, routing: impl Into<UnitRouting>, service: S) -> Result<Self, ModbusError> {
+        let port = tokio_serial::SerialStream::open(&tokio_serial::new(port_name, baud_rate))?;
+
+        Ok(Self {
+            transport: SerialTransport { port },
+            routing: routing.into(),
+            service: Arc::new(service),
+            authorization: Arc::new(AuthorizationPolicy::default()),
+            faults: None,
+            shutdown: ShutdownHandle::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        })
+    }
+
+    /// 运行服务器，直到通过关闭句柄请求关闭
+    pub async fn run(&mut self) -> Result<(), ModbusError> {
+        let token = self.shutdown.token().clone();
+        let shutdown_timeout = self.shutdown_timeout;
+        let options = self.connection_options(ConnectionLimits::default());
+
+        while !token.is_cancelled() {
+            let serve = serve_connection::<RtuFraming, _, _>(&mut self.transport.port, None, &*self.service, &options, &token);
+            // 请求关闭后，处理中的请求最多再等待关闭超时
+            let deadline = async {
+                token.cancelled().await;
+                tokio::time::sleep(shutdown_timeout).await;
+            };
+
+            tokio::select! {
+                result = serve => {
+                    if let Err(e) = result {
+                        log::error!("Serial port read error: {}", e);
+                    }
+                },
+                _ = deadline => {
+                    log::warn!("RTU server shutdown timed out");
+                    break;
+                },
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {},
+                _ = token.cancelled() => {},
+            }
+        }
+
+        self.shutdown.mark_stopped();
+        Ok(())
+    }
+}
+
+impl<T> ModbusServer<T, MemoryService> {
+    /// 设置线圈值
+    pub fn set_coil(&self, address: u16, value: bool) {
+        self.service.set_coil(address, value);
+    }
+
+    /// 设置离散输入值
+    pub fn set_discrete_input(&self, address: u16, value: bool) {
+        self.service.set_discrete_input(address, value);
+    }
+
+    /// 设置保持寄存器值
+    pub fn set_holding_register(&self, address: u16, value: u16) {
+        self.service.set_holding_register(address, value);
+    }
+
+    /// 设置输入寄存器值
+    pub fn set_input_register(&self, address: u16, value: u16) {
+        self.service.set_input_register(address, value);
+    }
+
+    /// 添加访问规则
+    pub fn add_access_rule(&self, rule: AccessRule) {
+        self.service.add_access_rule(rule);
+    }
+
+    /// 注册写入前钩子，返回`Err`时拒绝客户端的写入
+    pub fn on_before_write<F>(&self, hook: F)
+    where
+        F: Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync + 'static,
+    {
+        self.service.on_before_write(hook);
+    }
+
+    /// 注册写入后回调
+    pub fn on_after_write<F>(&self, callback: F)
+    where
+        F: Fn(&DataChange) + Send + Sync + 'static,
+    {
+        self.service.on_after_write(callback);
+    }
+
+    /// 订阅客户端写入产生的数据变更
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<DataChange> {
+        self.service.subscribe_changes()
+    }
+
+    /// 原子地写入一段连续的保持寄存器或输入寄存器
+    pub fn set_registers(&self, table: Table, address: u16, values: &[u16]) -> Result<(), ModbusError> {
+        self.service.set_registers(table, address, values)
+    }
+
+    /// 原子地读取一段连续的保持寄存器或输入寄存器
+    pub fn get_registers(&self, table: Table, address: u16, count: u16) -> Result<Vec<u16>, ModbusError> {
+        self.service.get_registers(table, address, count)
+    }
+
+    /// 按字节序写入一个数值，例如`set_value(Table::HoldingRegisters, 0, 1.5f32, ByteOrder::CDAB)`
+    pub fn set_value<V: RegisterValue>(&self, table: Table, address: u16, value: V, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.service.set_value(table, address, value, byte_order)
+    }
+
+    /// 按字节序读取一个数值，例如`get_value::<i32>(Table::InputRegisters, 0, ByteOrder::ABCD)`
+    pub fn get_value<V: RegisterValue>(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<V, ModbusError> {
+        self.service.get_value(table, address, byte_order)
+    }
+
+    /// 写入字符串，占用`register_count`个寄存器
+    pub fn set_string(&self, table: Table, address: u16, text: &str, register_count: u16, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.service.set_string(table, address, text, register_count, byte_order)
+    }
+
+    /// 读取字符串
+    pub fn get_string(&self, table: Table, address: u16, register_count: u16, byte_order: ByteOrder) -> Result<String, ModbusError> {
+        self.service.get_string(table, address, register_count, byte_order)
+    }
+
+    /// 获取数据快照
+    pub fn snapshot(&self) -> DataSnapshot {
+        self.service.snapshot()
+    }
+
+    /// 按快照恢复数据
+    pub fn restore(&self, snapshot: &DataSnapshot) {
+        self.service.restore(snapshot);
+    }
+}
+
+impl<T> ModbusServer<T, MultiSlaveMemoryService> {
+    /// 添加从机
+    pub fn add_slave(&self, slave_id: u8) {
+        self.service.add_slave(slave_id);
+    }
+
+    /// 移除从机
+    pub fn remove_slave(&self, slave_id: u8) {
+        self.service.remove_slave(slave_id);
+    }
+
+    /// 设置指定从机的线圈值
+    pub fn set_coil(&self, slave_id: u8, address: u16, value: bool) -> Result<(), ModbusError> {
+        self.service.set_coil(slave_id, address, value)
+    }
+
+    /// 设置指定从机的离散输入值
+    pub fn set_discrete_input(&self, slave_id: u8, address: u16, value: bool) -> Result<(), ModbusError> {
+        self.service.set_discrete_input(slave_id, address, value)
+    }
+
+    /// 设置指定从机的保持寄存器值
+    pub fn set_holding_register(&self, slave_id: u8, address: u16, value: u16) -> Result<(), ModbusError> {
+        self.service.set_holding_register(slave_id, address, value)
+    }
+
+    /// 设置指定从机的输入寄存器值
+    pub fn set_input_register(&self, slave_id: u8, address: u16, value: u16) -> Result<(), ModbusError> {
+        self.service.set_input_register(slave_id, address, value)
+    }
+
+    /// 获取所有已注册的从机 ID
+    pub fn get_slave_ids(&self) -> Vec<u8> {
+        self.service.get_slave_ids()
+    }
+
+    /// 获取指定从机的数据存储，可用于按类型读写寄存器
+    pub fn slave(&self, slave_id: u8) -> Option<MemoryService> {
+        self.service.slave(slave_id)
+    }
+
+    /// 为指定从机添加访问规则
+    pub fn add_access_rule(&self, slave_id: u8, rule: AccessRule) -> Result<(), ModbusError> {
+        self.service.add_access_rule(slave_id, rule)
+    }
+
+    /// 获取所有从机的数据快照
+    pub fn snapshot(&self) -> MultiSlaveSnapshot {
+        self.service.snapshot()
+    }
+
+    /// 获取指定从机的数据快照
+    pub fn snapshot_slave(&self, slave_id: u8) -> Result<DataSnapshot, ModbusError> {
+        self.service.snapshot_slave(slave_id)
+    }
+
+    /// 按快照恢复从机数据
+    pub fn restore(&self, snapshot: &MultiSlaveSnapshot) {
+        self.service.restore(snapshot);
+    }
+
+    /// 恢复指定从机的数据
+    pub fn restore_slave(&self, slave_id: u8, snapshot: &DataSnapshot) -> Result<(), ModbusError> {
+        self.service.restore_slave(slave_id, snapshot)
+    }
+
+    /// 注册写入前钩子，返回`Err`时拒绝客户端的写入
+    pub fn on_before_write<F>(&self, hook: F)
+    where
+        F: Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync + 'static,
+    {
+        self.service.on_before_write(hook);
+    }
+
+    /// 注册写入后回调
+    pub fn on_after_write<F>(&self, callback: F)
+    where
+        F: Fn(&DataChange) + Send + Sync + 'static,
+    {
+        self.service.on_after_write(callback);
+    }
+
+    /// 订阅客户端写入产生的数据变更
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<DataChange> {
+        self.service.subscribe_changes()
+    }
+}
+
+/// 处理一条连接（或一个串口）上的全部请求，直到对端关闭或请求关闭
+pub(crate) async fn serve_connection<F, IO, S>(
+    io: &mut IO,
+    peer_addr: Option<SocketAddr>,
+    service: &S,
+    options: &ConnectionOptions,
+    shutdown: &CancellationToken,
+) -> Result<(), ModbusError>
+where
+    F: Framing,
+    IO: AsyncRead + AsyncWrite + Unpin,
+    S: Service,
+{
+    let limits = &*options.limits;
+    let mut buffer = BytesMut::with_capacity(MAX_FRAME_LEN);
+    // 当前未完整请求帧收到第一个字节的时间
+    let mut frame_started: Option<Instant> = None;
+    // 超长帧中尚未收到、需要跳过的字节数
+    let mut discard: usize = 0;
+
+    loop {
+        let skipped = discard.min(buffer.len());
+        buffer.advance(skipped);
+        discard -= skipped;
+
+        // 处理缓冲区中所有完整的请求帧
+        while discard == 0 {
+            let Some(frame_len) = F::frame_len(&buffer) else {
+                break;
+            };
+            if frame_len > MAX_FRAME_LEN {
+                // 跳过整个超长帧，避免把它的剩余部分当作新的请求帧
+                log::warn!("Discarding oversized {} frame ({} bytes)", F::TRANSPORT, frame_len);
+                let skipped = frame_len.min(buffer.len());
+                buffer.advance(skipped);
+                discard = frame_len - skipped;
+                continue;
+            }
+            if buffer.len() < frame_len {
+                break;
+            }
+
+            let frame = buffer.split_to(frame_len);
+            frame_started = None;
+            let (header, request) = match F::decode_request(&frame) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    log::warn!("Failed to parse {} request: {}", F::TRANSPORT, e);
+                    // 没有长度头部的帧格式无法重新定位帧边界，丢弃剩余数据
+                    if F::TRANSPORT != TransportKind::Tcp {
+                        buffer.clear();
+                    }
+                    continue;
+                },
+            };
+
+            if !options.routing.accepts(request.slave_id) {
+                continue;
+            }
+
+            let ctx = RequestContext {
+                unit_id: request.slave_id,
+                peer_addr,
+                transport: F::TRANSPORT,
+            };
+
+            let plan = options.faults.as_ref().map(|faults| faults.plan(&request)).unwrap_or_default();
+
+            let mut response = match (options.authorization.check(&ctx, &request), plan.exception) {
+                (Err(code), _) | (Ok(()), Some(code)) => ModbusResponse::exception(request.slave_id, request.function_code, code),
+                (Ok(()), None) => match limits.request_timeout {
+                    Some(timeout) => match tokio::time::timeout(timeout, dispatch(service, ctx, &request)).await {
+                        Ok(response) => response,
+                        Err(_) => {
+                            log::warn!("{} request timed out after {:?}", F::TRANSPORT, timeout);
+                            ModbusResponse::exception(request.slave_id, request.function_code, ExceptionCode::SlaveDeviceFailure)
+                        },
+                    },
+                    None => dispatch(service, ctx, &request).await,
+                },
+            };
+
+            if plan != FaultPlan::default() {
+                log::debug!("Injecting faults into {} response: {:?}", F::TRANSPORT, plan);
+                if plan.wrong_unit_id {
+                    response.slave_id = response.slave_id.wrapping_add(1);
+                }
+                if !plan.delay.is_zero() {
+                    tokio::time::sleep(plan.delay).await;
+                }
+                if plan.close_connection && F::TRANSPORT != TransportKind::Rtu {
+                    return Ok(());
+                }
+                if plan.drop_response || plan.close_connection {
+                    continue;
+                }
+            }
+
+            let response_frame = plan.apply_to_frame(F::TRANSPORT, &F::encode_response(header, &response)?);
+            io.write_all(&response_frame).await?;
+            io.flush().await?;
+        }
+
+        // 缓冲区为空时按空闲超时等待，收到半个请求帧后按请求超时等待剩余部分
+        let deadline = if buffer.is_empty() {
+            frame_started = None;
+            limits.idle_timeout.map(|timeout| Instant::now() + timeout)
+        } else {
+            let started = *frame_started.get_or_insert_with(Instant::now);
+            limits.request_timeout.map(|timeout| started + timeout)
+        };
+        let expired = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        // 关闭时只打断等待中的读取，已收到的请求处理完毕后才退出
+        let bytes_read = tokio::select! {
+            read = io.read_buf(&mut buffer) => read?,
+            _ = shutdown.cancelled() => return Ok(()),
+            _ = expired => {
+                if buffer.is_empty() {
+                    log::info!("Closing idle {} connection", F::TRANSPORT);
+                } else {
+                    log::warn!("Closing {} connection: incomplete request timed out", F::TRANSPORT);
+                }
+                return Ok(());
+            },
+        };
+        if bytes_read == 0 {
+            log::info!("{} client disconnected", F::TRANSPORT);
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serve_connection_split_and_pipelined_frames() {
+        let service = MemoryService::new();
+        service.set_holding_register(0, 0x1234);
+
+        let request = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadHoldingRegisters,
+            address: 0,
+            count: 1,
+            data: None,
+        };
+        let other_unit = ModbusRequest { slave_id: 2, ..request.clone() };
+
+        let options = ConnectionOptions {
+            routing: UnitRouting::Single(1),
+            authorization: Arc::new(AuthorizationPolicy::default()),
+            limits: Arc::new(ConnectionLimits::default()),
+            faults: None,
+        };
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let task = tokio::spawn(async move {
+            serve_connection::<TcpFraming, _, _>(&mut server, None, &service, &options, &CancellationToken::new()).await
+        });
+
+        // 一个请求分两次到达，随后两个请求粘在一起（其中一个单元ID不匹配）
+        let first = ModbusTcp::build_request(&request, 1).unwrap();
+        client.write_all(&first[..4]).await.unwrap();
+        client.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.write_all(&first[4..]).await.unwrap();
+        let mut pipelined = ModbusTcp::build_request(&other_unit, 2).unwrap().to_vec();
+        pipelined.extend_from_slice(&ModbusTcp::build_request(&request, 3).unwrap());
+        client.write_all(&pipelined).await.unwrap();
+
+        for expected_transaction_id in [1, 3] {
+            let mut response = [0u8; 11];
+            client.read_exact(&mut response).await.unwrap();
+            let (transaction_id, response) = ModbusTcp::parse_response(&response).unwrap();
+            assert_eq!(transaction_id, expected_transaction_id);
+            assert_eq!(response.data, vec![0x12, 0x34]);
+        }
+
+        drop(client);
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_connection_malformed_frames() {
+        let service = MemoryService::new();
+        service.set_holding_register(0, 0x1234);
+        let options = ConnectionOptions {
+            routing: UnitRouting::All,
+            authorization: Arc::new(AuthorizationPolicy::default()),
+            limits: Arc::new(ConnectionLimits::default()),
+            faults: None,
+        };
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let task = tokio::spawn(async move {
+            serve_connection::<TcpFraming, _, _>(&mut server, None, &service, &options, &CancellationToken::new()).await
+        });
+
+        let request = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadHoldingRegisters,
+            address: 0,
+            count: 1,
+            data: None,
+        };
+        let valid = |transaction_id| ModbusTcp::build_request(&request, transaction_id).unwrap().to_vec();
+
+        // PDU被截断的帧
+        client.write_all(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x03, 0x00]).await.unwrap();
+        // 超长帧，数据中夹带一个完整的请求帧，分两次到达
+        let mut oversized = vec![0x00, 0x02, 0x00, 0x00, 0x01, 0x2C];
+        oversized.extend(std::iter::repeat_n(0u8, 100));
+        oversized.extend(valid(2));
+        oversized.resize(6 + 300, 0);
+        client.write_all(&oversized[..150]).await.unwrap();
+        client.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.write_all(&oversized[150..]).await.unwrap();
+        client.write_all(&valid(3)).await.unwrap();
+
+        // 只有最后一个请求得到响应
+        let mut response = [0u8; 11];
+        client.read_exact(&mut response).await.unwrap();
+        let (transaction_id, response) = ModbusTcp::parse_response(&response).unwrap();
+        assert_eq!(transaction_id, 3);
+        assert_eq!(response.data, vec![0x12, 0x34]);
+
+        drop(client);
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_server_graceful_shutdown() {
+        let server = crate::server::ModbusTcpServer::new("127.0.0.1:0", 1).await.unwrap();
+        server.set_holding_register(5, 500);
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let task = tokio::spawn(async move { server.run().await });
+
+        let mut client = crate::client::ModbusTcpClient::new("127.0.0.1", addr.port(), 1).await.unwrap();
+        assert_eq!(client.read_holding_registers(5, 1).await.unwrap(), vec![500]);
+
+        handle.shutdown();
+        tokio::time::timeout(Duration::from_secs(1), handle.stopped()).await.unwrap();
+        task.await.unwrap().unwrap();
+        assert!(handle.is_stopped());
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_server_releases_listener_after_run() {
+        let server = Arc::new(crate::server::ModbusTcpServer::new("127.0.0.1:0", 1).await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let task = tokio::spawn({
+            let server = Arc::clone(&server);
+            async move { server.run().await }
+        });
+
+        let mut client = crate::client::ModbusTcpClient::new("127.0.0.1", addr.port(), 1).await.unwrap();
+        server.set_holding_register(0, 7);
+        assert_eq!(client.read_holding_registers(0, 1).await.unwrap(), vec![7]);
+
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+
+        // 服务器仍然存在，但监听端口已经关闭
+        server.set_holding_register(0, 8);
+        assert_eq!(server.local_addr().unwrap(), addr);
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+        assert!(server.run().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_server_connection_limits() {
+        let mut server = crate::server::ModbusTcpServer::new("127.0.0.1:0", 1).await.unwrap();
+        server.set_connection_limits(ConnectionLimits::new()
+            .with_max_connections(1, LimitPolicy::EvictOldest)
+            .with_idle_timeout(Duration::from_millis(200)));
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let task = tokio::spawn(async move { server.run().await });
+
+        // 第二条连接使最早的连接被断开
+        let mut oldest = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let mut newest = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_millis(100), oldest.read(&mut buf)).await.unwrap();
+        assert_eq!(read.unwrap(), 0);
+
+        // 空闲超时后新连接也被断开
+        let read = tokio::time::timeout(Duration::from_secs(1), newest.read(&mut buf)).await.unwrap();
+        assert_eq!(read.unwrap(), 0);
+
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+}
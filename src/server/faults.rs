@@ -0,0 +1,296 @@
+use crate::protocol::*;
+use crate::utils::SimpleRng;
+use super::service::{Request, TransportKind};
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 故障触发条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    /// 每个匹配的请求都触发
+    Always,
+    /// 按概率触发（0.0～1.0），使用注入器的随机数种子
+    Probability(f64),
+    /// 每第n个匹配的请求触发一次
+    EveryNth(u64),
+}
+
+/// 可注入的故障
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// 延迟响应，实际延迟在`delay`基础上增加[0, jitter)的随机值
+    Delay { delay: Duration, jitter: Duration },
+    /// 不发送响应
+    DropResponse,
+    /// 破坏RTU帧的CRC（仅串口RTU，Modbus TCP和RTU over TCP没有CRC）
+    CorruptCrc,
+    /// 响应中使用错误的事务ID（仅Modbus TCP）
+    WrongTransactionId,
+    /// 响应中使用错误的单元ID
+    WrongUnitId,
+    /// 只发送响应帧的前一半
+    TruncateFrame,
+    /// 不处理请求，直接回复指定异常码；可限定数据表和地址范围
+    Exception {
+        code: ExceptionCode,
+        table: Option<Table>,
+        addresses: Option<RangeInclusive<u16>>,
+    },
+    /// 不发送响应并断开连接（串口上等同于丢弃响应）
+    CloseConnection,
+}
+
+impl Fault {
+    /// 对指定地址范围回复异常码
+    pub fn exception_at(code: ExceptionCode, table: Table, addresses: RangeInclusive<u16>) -> Self {
+        Fault::Exception {
+            code,
+            table: Some(table),
+            addresses: Some(addresses),
+        }
+    }
+
+    fn applies_to(&self, request: &ModbusRequest) -> bool {
+        let (table, addresses) = match self {
+            Fault::Exception { table, addresses, .. } => (table, addresses),
+            _ => return true,
+        };
+        if table.is_none() && addresses.is_none() {
+            return true;
+        }
+        let request = match Request::from_modbus_request(request) {
+            Ok(request) => request,
+            Err(_) => return false,
+        };
+        if table.is_some_and(|table| table != request.table()) {
+            return false;
+        }
+        match addresses {
+            Some(addresses) => {
+                let start = request.address();
+                let end = start.saturating_add(request.quantity().saturating_sub(1));
+                start <= *addresses.end() && end >= *addresses.start()
+            },
+            None => true,
+        }
+    }
+}
+
+/// 对单个请求生效的故障
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultPlan {
+    pub delay: Duration,
+    pub drop_response: bool,
+    pub corrupt_crc: bool,
+    pub wrong_transaction_id: bool,
+    pub wrong_unit_id: bool,
+    pub truncate: bool,
+    pub close_connection: bool,
+    pub exception: Option<ExceptionCode>,
+}
+
+impl FaultPlan {
+    /// 按计划修改编码后的响应帧
+    pub fn apply_to_frame(&self, transport: TransportKind, frame: &[u8]) -> Vec<u8> {
+        let mut frame = frame.to_vec();
+        if self.wrong_transaction_id && transport == TransportKind::Tcp && frame.len() >= 2 {
+            let transaction_id = u16::from_be_bytes([frame[0], frame[1]]).wrapping_add(1);
+            frame[..2].copy_from_slice(&transaction_id.to_be_bytes());
+        }
+        if self.corrupt_crc && transport == TransportKind::Rtu {
+            if let Some(last) = frame.last_mut() {
+                *last ^= 0xFF;
+            }
+        }
+        if self.truncate {
+            frame.truncate((frame.len() / 2).max(1));
+        }
+        frame
+    }
+}
+
+struct FaultState {
+    rng: SimpleRng,
+    counts: Vec<u64>,
+}
+
+/// 故障注入器，用于测试主站对异常从站的处理
+///
+/// 每条规则独立判断是否触发，同一请求可以同时触发多个故障。
+/// 注入器在服务器的所有连接间共享，计数和随机序列也是共享的。
+pub struct FaultInjector {
+    rules: Vec<(Fault, Trigger)>,
+    state: Mutex<FaultState>,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultInjector {
+    /// 创建故障注入器，随机数种子取当前时间
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            state: Mutex::new(FaultState {
+                rng: SimpleRng::from_time(),
+                counts: Vec::new(),
+            }),
+        }
+    }
+
+    /// 设置随机数种子，使故障序列可以复现
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.state.lock().unwrap().rng = SimpleRng::new(seed);
+        self
+    }
+
+    /// 添加故障规则
+    pub fn with_fault(mut self, fault: Fault, trigger: Trigger) -> Self {
+        self.rules.push((fault, trigger));
+        self.state.lock().unwrap().counts.push(0);
+        self
+    }
+
+    /// 决定对该请求注入哪些故障
+    pub fn plan(&self, request: &ModbusRequest) -> FaultPlan {
+        let mut plan = FaultPlan::default();
+        let mut state = self.state.lock().unwrap();
+        let FaultState { rng, counts } = &mut *state;
+
+        for (i, (fault, trigger)) in self.rules.iter().enumerate() {
+            if !fault.applies_to(request) {
+                continue;
+            }
+            counts[i] += 1;
+            let triggered = match trigger {
+                Trigger::Always => true,
+                Trigger::Probability(probability) => rng.chance(*probability),
+                Trigger::EveryNth(n) => *n > 0 && counts[i].is_multiple_of(*n),
+            };
+            if !triggered {
+                continue;
+            }
+
+            match fault {
+                Fault::Delay { delay, jitter } => {
+                    plan.delay += *delay + jitter.mul_f64(rng.next_f64());
+                },
+                Fault::DropResponse => plan.drop_response = true,
+                Fault::CorruptCrc => plan.corrupt_crc = true,
+                Fault::WrongTransactionId => plan.wrong_transaction_id = true,
+                Fault::WrongUnitId => plan.wrong_unit_id = true,
+                Fault::TruncateFrame => plan.truncate = true,
+                Fault::Exception { code, .. } => plan.exception = Some(*code),
+                Fault::CloseConnection => plan.close_connection = true,
+            }
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::engine::{serve_connection, ConnectionOptions, UnitRouting};
+    use crate::server::framing::*;
+    use crate::server::{AuthorizationPolicy, ConnectionLimits, MemoryService};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::sync::CancellationToken;
+
+    fn read(address: u16) -> ModbusRequest {
+        ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadHoldingRegisters,
+            address,
+            count: 2,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_fault_plan() {
+        let injector = FaultInjector::new()
+            .with_seed(1)
+            .with_fault(Fault::DropResponse, Trigger::EveryNth(3))
+            .with_fault(Fault::exception_at(ExceptionCode::SlaveDeviceBusy, Table::HoldingRegisters, 100..=109), Trigger::Always)
+            .with_fault(Fault::Delay { delay: Duration::from_millis(10), jitter: Duration::from_millis(5) }, Trigger::Probability(1.0));
+
+        let plans: Vec<FaultPlan> = (0..3).map(|_| injector.plan(&read(0))).collect();
+        assert_eq!(plans.iter().map(|plan| plan.drop_response).collect::<Vec<_>>(), vec![false, false, true]);
+        assert!(plans.iter().all(|plan| plan.exception.is_none()));
+        assert!(plans.iter().all(|plan| (10..15).contains(&plan.delay.as_millis())));
+
+        assert_eq!(injector.plan(&read(99)).exception, Some(ExceptionCode::SlaveDeviceBusy));
+        assert_eq!(injector.plan(&read(110)).exception, None);
+
+        // 相同种子产生相同的故障序列
+        let sequence = |seed| {
+            let injector = FaultInjector::new().with_seed(seed).with_fault(Fault::WrongUnitId, Trigger::Probability(0.5));
+            (0..32).map(|_| injector.plan(&read(0)).wrong_unit_id).collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+    }
+
+    #[test]
+    fn test_apply_to_frame() {
+        let plan = FaultPlan { wrong_transaction_id: true, ..FaultPlan::default() };
+        let frame = [0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x12, 0x34];
+        assert_eq!(plan.apply_to_frame(TransportKind::Tcp, &frame)[..2], [0x00, 0x02]);
+
+        let plan = FaultPlan { corrupt_crc: true, truncate: true, ..FaultPlan::default() };
+        let frame = [0x01, 0x03, 0x02, 0x12, 0x34, 0xB5, 0x33];
+        assert_eq!(plan.apply_to_frame(TransportKind::Rtu, &frame), vec![0x01, 0x03, 0x02]);
+        let plan = FaultPlan { corrupt_crc: true, ..FaultPlan::default() };
+        assert_eq!(plan.apply_to_frame(TransportKind::Rtu, &frame)[6], 0x33 ^ 0xFF);
+    }
+
+    /// 通过连接处理流程发送一个请求，返回收到的响应帧
+    async fn serve_with_faults<F: Framing>(injector: FaultInjector, request: &[u8], response_len: usize) -> Vec<u8> {
+        let service = MemoryService::new();
+        service.set_holding_register(0, 0x1234);
+        let options = ConnectionOptions {
+            routing: UnitRouting::Single(1),
+            authorization: Arc::new(AuthorizationPolicy::default()),
+            limits: Arc::new(ConnectionLimits::default()),
+            faults: Some(Arc::new(injector)),
+        };
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let task = tokio::spawn(async move {
+            serve_connection::<F, _, _>(&mut server, None, &service, &options, &CancellationToken::new()).await
+        });
+
+        client.write_all(request).await.unwrap();
+        let mut response = vec![0u8; response_len];
+        client.read_exact(&mut response).await.unwrap();
+        drop(client);
+        task.await.unwrap().unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_crc_per_transport() {
+        let request = ModbusRequest { count: 1, ..read(0) };
+        let injector = || FaultInjector::new().with_fault(Fault::CorruptCrc, Trigger::Always);
+
+        // 只有串口RTU帧的CRC被破坏
+        let frame = ModbusRtu::build_request(&request).unwrap();
+        let response = serve_with_faults::<RtuFraming>(injector(), &frame, 7).await;
+        assert!(matches!(ModbusRtu::parse_response(&response), Err(ModbusError::CrcCheckFailed)));
+
+        // 没有CRC的传输方式，响应保持不变
+        let frame = ModbusTcp::build_request(&request, 5).unwrap();
+        let response = serve_with_faults::<TcpFraming>(injector(), &frame, 11).await;
+        let (transaction_id, response) = ModbusTcp::parse_response(&response).unwrap();
+        assert_eq!(transaction_id, 5);
+        assert_eq!(response.data, vec![0x12, 0x34]);
+
+        let frame = ModbusRtuOverTcp::build_request(&request).unwrap();
+        let response = serve_with_faults::<RtuOverTcpFraming>(injector(), &frame, 5).await;
+        assert_eq!(ModbusRtuOverTcp::parse_response(&response).unwrap().data, vec![0x12, 0x34]);
+    }
+}
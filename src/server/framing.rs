@@ -0,0 +1,164 @@
+use crate::protocol::*;
+use super::service::TransportKind;
+use bytes::Bytes;
+
+/// 单个请求帧的最大长度（MBAP头部7字节 + PDU 253字节）
+pub const MAX_FRAME_LEN: usize = 260;
+
+/// 服务器端帧格式
+///
+/// 负责从字节流中切分出完整的请求帧，并完成请求解析和响应编码。
+pub trait Framing: Send + Sync + 'static {
+    /// 回复时需要从请求帧中带回的帧头信息（例如TCP事务ID）
+    type Header: Copy + Send + Sync + 'static;
+
+    /// 对应的传输方式
+    const TRANSPORT: TransportKind;
+
+    /// 缓冲区中第一个请求帧的总长度，数据不足以判断时返回None
+    fn frame_len(buf: &[u8]) -> Option<usize>;
+
+    /// 解析一个完整的请求帧
+    fn decode_request(frame: &[u8]) -> Result<(Self::Header, ModbusRequest), ModbusError>;
+
+    /// 编码响应帧
+    fn encode_response(header: Self::Header, response: &ModbusResponse) -> Result<Bytes, ModbusError>;
+}
+
+/// Modbus TCP帧格式（MBAP头部）
+pub struct TcpFraming;
+
+impl Framing for TcpFraming {
+    type Header = u16;
+
+    const TRANSPORT: TransportKind = TransportKind::Tcp;
+
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        if buf.len() < 6 {
+            return None;
+        }
+        let length = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+        Some(6 + length)
+    }
+
+    fn decode_request(frame: &[u8]) -> Result<(u16, ModbusRequest), ModbusError> {
+        ModbusTcp::parse_request(frame)
+    }
+
+    fn encode_response(transaction_id: u16, response: &ModbusResponse) -> Result<Bytes, ModbusError> {
+        ModbusTcp::build_response(response, transaction_id)
+    }
+}
+
+/// Modbus RTU帧格式（带CRC）
+pub struct RtuFraming;
+
+impl Framing for RtuFraming {
+    type Header = ();
+
+    const TRANSPORT: TransportKind = TransportKind::Rtu;
+
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        rtu_pdu_len(buf).map(|len| len + 2)
+    }
+
+    fn decode_request(frame: &[u8]) -> Result<((), ModbusRequest), ModbusError> {
+        Ok(((), ModbusRtu::parse_request(frame)?))
+    }
+
+    fn encode_response(_header: (), response: &ModbusResponse) -> Result<Bytes, ModbusError> {
+        ModbusRtu::build_response(response)
+    }
+}
+
+/// Modbus RTU over TCP帧格式（无CRC）
+pub struct RtuOverTcpFraming;
+
+impl Framing for RtuOverTcpFraming {
+    type Header = ();
+
+    const TRANSPORT: TransportKind = TransportKind::RtuOverTcp;
+
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        rtu_pdu_len(buf)
+    }
+
+    fn decode_request(frame: &[u8]) -> Result<((), ModbusRequest), ModbusError> {
+        Ok(((), ModbusRtuOverTcp::parse_request(frame)?))
+    }
+
+    fn encode_response(_header: (), response: &ModbusResponse) -> Result<Bytes, ModbusError> {
+        ModbusRtuOverTcp::build_response(response)
+    }
+}
+
+/// 从机地址 + PDU 的长度（不含CRC）
+///
+/// 无法识别的功能码无法确定帧长，返回整个缓冲区的长度，交给解析报错。
+fn rtu_pdu_len(buf: &[u8]) -> Option<usize> {
+    if buf.len() < 2 {
+        return None;
+    }
+    match FunctionCode::from_u8(buf[1]) {
+        Ok(FunctionCode::WriteMultipleCoils) | Ok(FunctionCode::WriteMultipleRegisters) => {
+            // 从机地址、功能码、地址、数量、字节数 + 数据
+            buf.get(6).map(|&byte_count| 7 + byte_count as usize)
+        },
+        Ok(_) => Some(6),
+        Err(_) => Some(buf.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_len() {
+        let read = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadHoldingRegisters,
+            address: 0,
+            count: 2,
+            data: None,
+        };
+        let write = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::WriteMultipleRegisters,
+            address: 0,
+            count: 2,
+            data: Some(vec![0, 1, 0, 2]),
+        };
+
+        for request in [&read, &write] {
+            let frame = ModbusTcp::build_request(request, 7).unwrap();
+            assert_eq!(TcpFraming::frame_len(&frame), Some(frame.len()));
+            assert_eq!(TcpFraming::frame_len(&frame[..5]), None);
+
+            let frame = ModbusRtu::build_request(request).unwrap();
+            assert_eq!(RtuFraming::frame_len(&frame), Some(frame.len()));
+            let (_, parsed) = RtuFraming::decode_request(&frame).unwrap();
+            assert_eq!(parsed.count, 2);
+
+            let frame = ModbusRtuOverTcp::build_request(request).unwrap();
+            assert_eq!(RtuOverTcpFraming::frame_len(&frame), Some(frame.len()));
+        }
+
+        assert_eq!(RtuFraming::frame_len(&[1, 0x10, 0, 0, 0, 2]), None);
+    }
+
+    #[test]
+    fn test_rtu_crc_rejected() {
+        let request = ModbusRequest {
+            slave_id: 1,
+            function_code: FunctionCode::ReadCoils,
+            address: 0,
+            count: 8,
+            data: None,
+        };
+        let mut frame = ModbusRtu::build_request(&request).unwrap().to_vec();
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+        assert!(matches!(RtuFraming::decode_request(&frame), Err(ModbusError::CrcCheckFailed)));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::protocol::*;
+use crate::client::ModbusMaster;
+use super::service::*;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// 网关默认的单次请求超时
+pub const DEFAULT_GATEWAY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 网关服务：把上游请求转发给下游主站，并把回复转换回来
+///
+/// 同一下游总线上一次只进行一个事务，多个上游连接的请求按到达顺序排队。
+/// 下游超时或回复无法解析时回复`GatewayTargetDeviceFailedToRespond`，
+/// 超时后通过`ModbusMaster::reset`重置下游连接，丢弃迟到的回复；
+/// 总线不可用时回复`GatewayPathUnavailable`，从机自身的异常码原样转发。
+/// 广播地址0没有回复，网关不转发，回复`GatewayPathUnavailable`。
+pub struct GatewayService<M: ModbusMaster> {
+    master: Mutex<M>,
+    timeout: Duration,
+}
+
+impl<M: ModbusMaster> GatewayService<M> {
+    /// 使用下游主站创建网关服务
+    pub fn new(master: M) -> Self {
+        Self {
+            master: Mutex::new(master),
+            timeout: DEFAULT_GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// 设置单次请求超时，从占用总线开始计时，不包括排队等待的时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 获取单次请求超时
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// 向指定从机转发请求
+    pub async fn forward(&self, unit_id: u8, request: &Request) -> Result<Response, ExceptionCode> {
+        if unit_id == 0 {
+            return Err(ExceptionCode::GatewayPathUnavailable);
+        }
+
+        let modbus_request = request.to_modbus_request(unit_id);
+        let mut master = self.master.lock().await;
+        let result = tokio::time::timeout(self.timeout, master.send_request(&modbus_request)).await;
+        if result.is_err() {
+            // 迟到的回复会被下一个请求读到，释放总线前先重置下游连接
+            if let Err(e) = master.reset().await {
+                log::warn!("Failed to reset gateway downstream after timeout: {}", e);
+            }
+        }
+        drop(master);
+
+        let response = match result {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                log::warn!("Gateway request to unit {} failed: {}", unit_id, e);
+                return Err(gateway_exception(&e));
+            },
+            Err(_) => {
+                log::warn!("Gateway request to unit {} timed out after {:?}", unit_id, self.timeout);
+                return Err(ExceptionCode::GatewayTargetDeviceFailedToRespond);
+            },
+        };
+
+        if response.is_exception {
+            return Err(response.exception_code.unwrap_or(ExceptionCode::SlaveDeviceFailure));
+        }
+        Response::from_modbus_response(request, &response).map_err(|e| {
+            log::warn!("Invalid gateway response from unit {}: {}", unit_id, e);
+            ExceptionCode::GatewayTargetDeviceFailedToRespond
+        })
+    }
+}
+
+impl<M: ModbusMaster> Service for GatewayService<M> {
+    async fn call(&self, ctx: RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        self.forward(ctx.unit_id, &request).await
+    }
+}
+
+/// 下游错误对应的网关异常码：传输层故障表示路径不可用，其余表示目标设备没有正确回复
+fn gateway_exception(error: &ModbusError) -> ExceptionCode {
+    match error {
+        ModbusError::IoError(_) | ModbusError::SerialError(_) | ModbusError::NetworkError(_) => {
+            ExceptionCode::GatewayPathUnavailable
+        },
+        _ => ExceptionCode::GatewayTargetDeviceFailedToRespond,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// 由内存表模拟的下游RTU总线，记录同时进行的事务数
+    struct MemoryBus {
+        memory: MemoryService,
+        delay: Duration,
+        active: Arc<AtomicUsize>,
+        max_active: Arc<AtomicUsize>,
+    }
+
+    impl ModbusMaster for MemoryBus {
+        async fn send_request(&mut self, request: &ModbusRequest) -> Result<ModbusResponse, ModbusError> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            if request.slave_id == 9 {
+                return Err(ModbusError::TimeoutError);
+            }
+            let ctx = RequestContext { unit_id: request.slave_id, peer_addr: None, transport: TransportKind::Rtu };
+            let response = dispatch(&self.memory, ctx, request).await;
+            ModbusRtu::parse_response(&ModbusRtu::build_response(&response)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_forwarding() {
+        let memory = MemoryService::new();
+        memory.set_holding_register(10, 0x1234);
+        let max_active = Arc::new(AtomicUsize::new(0));
+        let bus = MemoryBus {
+            memory: memory.clone(),
+            delay: Duration::from_millis(5),
+            active: Arc::new(AtomicUsize::new(0)),
+            max_active: Arc::clone(&max_active),
+        };
+        let gateway = Arc::new(GatewayService::new(bus).with_timeout(Duration::from_millis(100)));
+
+        // 并发请求在总线上串行执行
+        let mut tasks = Vec::new();
+        for i in 0..4u16 {
+            let gateway = Arc::clone(&gateway);
+            tasks.push(tokio::spawn(async move {
+                gateway.forward(1, &Request::WriteSingleRegister { address: i, value: i + 100 }).await
+            }));
+        }
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+        assert_eq!(max_active.load(Ordering::SeqCst), 1);
+        assert_eq!(memory.get_holding_register(3), 103);
+
+        let response = gateway.forward(1, &Request::ReadHoldingRegisters { address: 10, count: 1 }).await;
+        assert_eq!(response, Ok(Response::ReadHoldingRegisters(vec![0x1234])));
+
+        // 从机异常原样转发，下游无回复和广播地址映射为网关异常
+        let response = gateway.forward(1, &Request::ReadHoldingRegisters { address: 0xFFFF, count: 2 }).await;
+        assert_eq!(response, Err(ExceptionCode::IllegalDataAddress));
+        let response = gateway.forward(9, &Request::ReadCoils { address: 0, count: 1 }).await;
+        assert_eq!(response, Err(ExceptionCode::GatewayTargetDeviceFailedToRespond));
+        let response = gateway.forward(0, &Request::ReadCoils { address: 0, count: 1 }).await;
+        assert_eq!(response, Err(ExceptionCode::GatewayPathUnavailable));
+
+        let slow = GatewayService::new(MemoryBus {
+            memory,
+            delay: Duration::from_millis(200),
+            active: Arc::new(AtomicUsize::new(0)),
+            max_active,
+        }).with_timeout(Duration::from_millis(20));
+        let response = slow.forward(1, &Request::ReadCoils { address: 0, count: 1 }).await;
+        assert_eq!(response, Err(ExceptionCode::GatewayTargetDeviceFailedToRespond));
+    }
+
+    #[tokio::test]
+    async fn test_gateway_discards_late_reply() {
+        use crate::client::ModbusTcpClient;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        // 下游设备读取地址0时延迟回复，其他地址立即回复地址本身
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut frame = [0u8; 12];
+                    while stream.read_exact(&mut frame).await.is_ok() {
+                        let (transaction_id, request) = ModbusTcp::parse_request(&frame).unwrap();
+                        let value = if request.address == 0 {
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                            0xAAAA
+                        } else {
+                            request.address
+                        };
+                        let mut data = vec![2];
+                        data.extend_from_slice(&value.to_be_bytes());
+                        let response = ModbusResponse {
+                            slave_id: request.slave_id,
+                            function_code: request.function_code,
+                            data,
+                            is_exception: false,
+                            exception_code: None,
+                        };
+                        let reply = ModbusTcp::build_response(&response, transaction_id).unwrap();
+                        if stream.write_all(&reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        let client = ModbusTcpClient::new("127.0.0.1", port, 1).await.unwrap();
+        let gateway = GatewayService::new(client).with_timeout(Duration::from_millis(50));
+        let response = gateway.forward(1, &Request::ReadHoldingRegisters { address: 0, count: 1 }).await;
+        assert_eq!(response, Err(ExceptionCode::GatewayTargetDeviceFailedToRespond));
+
+        // 等到迟到的回复已经发出，下一个请求仍收到自己的回复
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let response = gateway.forward(1, &Request::ReadHoldingRegisters { address: 5, count: 1 }).await;
+        assert_eq!(response, Ok(Response::ReadHoldingRegisters(vec![5])));
+    }
+}
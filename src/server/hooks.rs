@@ -0,0 +1,124 @@
+use crate::protocol::*;
+use super::service::RequestContext;
+use std::sync::RwLock;
+use tokio::sync::{broadcast, watch};
+
+/// 变更通知通道的默认容量
+const CHANGE_CHANNEL_CAPACITY: usize = 1024;
+
+/// 写入的数据值
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableValues {
+    /// 线圈值
+    Bits(Vec<bool>),
+    /// 寄存器值
+    Registers(Vec<u16>),
+}
+
+impl TableValues {
+    /// 值的个数
+    pub fn len(&self) -> usize {
+        match self {
+            TableValues::Bits(values) => values.len(),
+            TableValues::Registers(values) => values.len(),
+        }
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 一次写入涉及的数据表、地址范围和值
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataChange {
+    /// 单元标识符（从机地址）
+    pub unit_id: u8,
+    /// 数据表
+    pub table: Table,
+    /// 起始地址
+    pub address: u16,
+    /// 写入的值，长度即地址范围
+    pub values: TableValues,
+}
+
+/// 写入前钩子，返回`Err`时拒绝写入并向客户端回复该异常码
+pub type BeforeWriteHook = Box<dyn Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync>;
+
+/// 写入后回调
+pub type AfterWriteHook = Box<dyn Fn(&DataChange) + Send + Sync>;
+
+/// 客户端写入请求的钩子和变更通知
+pub struct WriteHooks {
+    before: RwLock<Vec<BeforeWriteHook>>,
+    after: RwLock<Vec<AfterWriteHook>>,
+    changes: broadcast::Sender<DataChange>,
+    /// 任何写入（包括本地`set_*`和仿真）后递增的修改计数
+    modified: watch::Sender<u64>,
+}
+
+impl Default for WriteHooks {
+    fn default() -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            before: RwLock::new(Vec::new()),
+            after: RwLock::new(Vec::new()),
+            changes,
+            modified: watch::Sender::new(0),
+        }
+    }
+}
+
+impl WriteHooks {
+    /// 注册写入前钩子
+    pub fn on_before_write<F>(&self, hook: F)
+    where
+        F: Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync + 'static,
+    {
+        self.before.write().unwrap().push(Box::new(hook));
+    }
+
+    /// 注册写入后回调
+    pub fn on_after_write<F>(&self, callback: F)
+    where
+        F: Fn(&DataChange) + Send + Sync + 'static,
+    {
+        self.after.write().unwrap().push(Box::new(callback));
+    }
+
+    /// 订阅数据变更通知
+    ///
+    /// 接收端处理过慢时会收到`RecvError::Lagged`，较早的通知被丢弃。
+    pub fn subscribe(&self) -> broadcast::Receiver<DataChange> {
+        self.changes.subscribe()
+    }
+
+    /// 依次执行写入前钩子，任一钩子拒绝即停止
+    pub fn before_write(&self, ctx: &RequestContext, change: &DataChange) -> Result<(), ExceptionCode> {
+        for hook in self.before.read().unwrap().iter() {
+            hook(ctx, change)?;
+        }
+        Ok(())
+    }
+
+    /// 执行写入后回调并广播变更
+    pub fn after_write(&self, change: DataChange) {
+        for callback in self.after.read().unwrap().iter() {
+            callback(&change);
+        }
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.changes.send(change);
+        self.mark_modified();
+    }
+
+    /// 记录一次数据修改，不区分来源
+    pub fn mark_modified(&self) {
+        self.modified.send_modify(|count| *count = count.wrapping_add(1));
+    }
+
+    /// 订阅修改计数，多次修改只唤醒一次
+    pub fn watch_modified(&self) -> watch::Receiver<u64> {
+        self.modified.subscribe()
+    }
+}
@@ -0,0 +1,163 @@
+use crate::protocol::ModbusError;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// IP网段（CIDR）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// 创建网段，前缀长度超过地址位数时返回错误
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, ModbusError> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_len {
+            return Err(ModbusError::ConfigError(format!("Invalid prefix length /{} for {}", prefix_len, addr)));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// 网段是否包含该地址，IPv4映射的IPv6地址按IPv4处理
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            },
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            },
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = ModbusError;
+
+    /// 解析"192.168.1.0/24"或单个地址"10.0.0.5"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ModbusError::ConfigError(format!("Invalid IP network: {}", s));
+        match s.split_once('/') {
+            Some((addr, prefix_len)) => {
+                let addr = addr.trim().parse().map_err(|_| invalid())?;
+                let prefix_len = prefix_len.trim().parse().map_err(|_| invalid())?;
+                Self::new(addr, prefix_len)
+            },
+            None => {
+                let addr: IpAddr = s.trim().parse().map_err(|_| invalid())?;
+                let prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+                Self::new(addr, prefix_len)
+            },
+        }
+    }
+}
+
+/// 连接数达到上限时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitPolicy {
+    /// 拒绝新连接
+    #[default]
+    Reject,
+    /// 断开最早建立的连接，接受新连接
+    EvictOldest,
+}
+
+/// TCP服务器的连接限制
+///
+/// 默认不限制连接数、不设超时、允许所有地址。
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimits {
+    /// 最大连接数
+    pub max_connections: Option<usize>,
+    /// 达到最大连接数时的策略
+    pub policy: LimitPolicy,
+    /// 空闲超时：连接在该时间内没有收到任何数据则断开
+    pub idle_timeout: Option<Duration>,
+    /// 请求超时：请求帧的剩余部分须在该时间内到齐，否则断开；
+    /// 服务处理超过该时间时回复SlaveDeviceFailure
+    pub request_timeout: Option<Duration>,
+    /// 允许连接的网段，为空时允许所有地址
+    pub allowed_peers: Vec<IpNetwork>,
+}
+
+impl ConnectionLimits {
+    /// 创建不做任何限制的配置
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置最大连接数及达到上限时的策略
+    pub fn with_max_connections(mut self, max_connections: usize, policy: LimitPolicy) -> Self {
+        self.max_connections = Some(max_connections);
+        self.policy = policy;
+        self
+    }
+
+    /// 设置空闲超时
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置请求超时
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// 添加允许连接的网段
+    pub fn allow(mut self, network: IpNetwork) -> Self {
+        self.allowed_peers.push(network);
+        self
+    }
+
+    /// 是否允许该地址连接
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        self.allowed_peers.is_empty() || self.allowed_peers.iter().any(|network| network.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_network() {
+        let network: IpNetwork = "192.168.1.0/24".parse().unwrap();
+        assert!(network.contains("192.168.1.77".parse().unwrap()));
+        assert!(!network.contains("192.168.2.1".parse().unwrap()));
+        assert!(network.contains("::ffff:192.168.1.5".parse().unwrap()));
+
+        let host: IpNetwork = "10.0.0.5".parse().unwrap();
+        assert!(host.contains("10.0.0.5".parse().unwrap()));
+        assert!(!host.contains("10.0.0.6".parse().unwrap()));
+
+        let any: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains("8.8.8.8".parse().unwrap()));
+
+        let v6: IpNetwork = "fd00::/8".parse().unwrap();
+        assert!(v6.contains("fd12::1".parse().unwrap()));
+        assert!(!v6.contains("fe80::1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("not-an-ip".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let limits = ConnectionLimits::new();
+        assert!(limits.is_allowed("1.2.3.4".parse().unwrap()));
+
+        let limits = limits.allow("127.0.0.0/8".parse().unwrap());
+        assert!(limits.is_allowed("127.0.0.1".parse().unwrap()));
+        assert!(!limits.is_allowed("1.2.3.4".parse().unwrap()));
+    }
+}
@@ -1,13 +1,46 @@
+pub mod service;
+pub mod hooks;
+pub mod access;
+pub mod framing;
+pub mod engine;
+pub mod shutdown;
+pub mod limits;
+pub mod authorization;
+pub mod persistence;
+pub mod config;
+pub mod typed;
+pub mod simulator;
+pub mod faults;
+pub mod gateway;
+pub mod router;
+pub mod cache;
 pub mod modbus_rtu_server;
 pub mod modbus_tcp_server;
 pub mod modbus_rtu_over_tcp_server;
 pub mod modbus_multi_slave_tcp_server;
 pub mod modbus_multi_slave_rtu_server;
 pub mod modbus_multi_slave_rtu_over_tcp_server;
+pub mod modbus_tcp_rtu_gateway;
 
+pub use service::*;
+pub use hooks::*;
+pub use access::*;
+pub use framing::*;
+pub use engine::*;
+pub use shutdown::*;
+pub use limits::*;
+pub use authorization::*;
+pub use persistence::*;
+pub use config::*;
+pub use simulator::*;
+pub use faults::*;
+pub use gateway::*;
+pub use router::*;
+pub use cache::*;
 pub use modbus_rtu_server::*;
 pub use modbus_tcp_server::*;
 pub use modbus_rtu_over_tcp_server::*;
 pub use modbus_multi_slave_tcp_server::*;
 pub use modbus_multi_slave_rtu_server::*;
 pub use modbus_multi_slave_rtu_over_tcp_server::*;
+pub use modbus_tcp_rtu_gateway::*;
@@ -0,0 +1,21 @@
+use crate::protocol::*;
+use crate::client::ModbusRtuClient;
+use super::engine::*;
+use super::framing::TcpFraming;
+use super::gateway::GatewayService;
+use std::time::Duration;
+
+/// Modbus TCP转RTU网关
+///
+/// 在TCP端口上接收MBAP请求，按单元ID转发给串口总线上的从机。
+pub type ModbusTcpRtuGateway = ModbusServer<TcpTransport<TcpFraming>, GatewayService<ModbusRtuClient>>;
+
+impl ModbusTcpRtuGateway {
+    /// 创建TCP转RTU网关，`timeout`为每个请求在串口总线上的超时
+    pub async fn new(addr: &str, port_name: &str, baud_rate: u32, timeout: Duration) -> Result<Self, ModbusError> {
+        let mut client = ModbusRtuClient::new(port_name, 0, baud_rate).await?;
+        client.set_timeout(timeout);
+        let service = GatewayService::new(client).with_timeout(timeout);
+        Self::tcp(addr, UnitRouting::All, service).await
+    }
+}
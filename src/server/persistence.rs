@@ -0,0 +1,244 @@
+use crate::protocol::{ModbusAddress, ModbusError};
+use super::service::{MemoryService, MultiSlaveMemoryService};
+use super::shutdown::ShutdownHandle;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 一个从机四张数据表的快照，只包含设置过的地址
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataSnapshot {
+    pub coils: BTreeMap<u16, bool>,
+    pub discrete_inputs: BTreeMap<u16, bool>,
+    pub holding_registers: BTreeMap<u16, u16>,
+    pub input_registers: BTreeMap<u16, u16>,
+    /// 一次写入规则覆盖、已经被客户端写入过的地址
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub written_once: BTreeSet<ModbusAddress>,
+}
+
+/// 多从机服务的快照，按从机ID保存
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MultiSlaveSnapshot {
+    pub slaves: BTreeMap<u8, DataSnapshot>,
+}
+
+/// 持久化方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistMode {
+    /// 按固定周期保存
+    Periodic(Duration),
+    /// 每次数据修改后保存，包括客户端写入、本地`set_*`和仿真（短时间内的多次修改合并为一次）
+    OnWrite,
+}
+
+/// 可以保存和恢复数据的服务
+pub trait Persistent: Clone + Send + Sync + 'static {
+    /// 快照类型
+    type Snapshot: Serialize + DeserializeOwned + Send + Sync + 'static;
+
+    /// 获取当前数据的快照
+    fn take_snapshot(&self) -> Self::Snapshot;
+
+    /// 按快照恢复数据
+    fn restore_snapshot(&self, snapshot: &Self::Snapshot);
+
+    /// 订阅修改计数
+    fn modified(&self) -> watch::Receiver<u64>;
+}
+
+impl Persistent for MemoryService {
+    type Snapshot = DataSnapshot;
+
+    fn take_snapshot(&self) -> DataSnapshot {
+        self.snapshot()
+    }
+
+    fn restore_snapshot(&self, snapshot: &DataSnapshot) {
+        self.restore(snapshot);
+    }
+
+    fn modified(&self) -> watch::Receiver<u64> {
+        self.watch_modified()
+    }
+}
+
+impl Persistent for MultiSlaveMemoryService {
+    type Snapshot = MultiSlaveSnapshot;
+
+    fn take_snapshot(&self) -> MultiSlaveSnapshot {
+        self.snapshot()
+    }
+
+    fn restore_snapshot(&self, snapshot: &MultiSlaveSnapshot) {
+        self.restore(snapshot);
+    }
+
+    fn modified(&self) -> watch::Receiver<u64> {
+        self.watch_modified()
+    }
+}
+
+/// 将快照以JSON格式写入文件，先写临时文件再重命名，避免写到一半的文件
+pub async fn save_snapshot<T: Serialize>(path: impl AsRef<Path>, snapshot: &T) -> Result<(), ModbusError> {
+    let path = path.as_ref();
+    let json = serde_json::to_vec_pretty(snapshot)
+        .map_err(|e| ModbusError::SerializationError(e.to_string()))?;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    tokio::fs::write(&temp_path, json).await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// 从JSON文件读取快照，文件不存在时返回`None`
+pub async fn load_snapshot<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<Option<T>, ModbusError> {
+    let json = match tokio::fs::read(path.as_ref()).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| ModbusError::SerializationError(e.to_string()))
+}
+
+/// 从文件恢复服务数据（文件不存在时跳过），然后在后台按指定方式保存，
+/// 请求关闭后再保存一次并退出
+pub async fn spawn_persistence<P: Persistent>(
+    service: P,
+    path: impl Into<PathBuf>,
+    mode: PersistMode,
+    shutdown: ShutdownHandle,
+) -> Result<JoinHandle<()>, ModbusError> {
+    let path = path.into();
+    if let Some(snapshot) = load_snapshot::<P::Snapshot>(&path).await? {
+        service.restore_snapshot(&snapshot);
+        log::info!("Restored server data from {}", path.display());
+    }
+
+    let mut modified = service.modified();
+    modified.mark_unchanged();
+    Ok(tokio::spawn(async move {
+        let mut interval = match mode {
+            PersistMode::Periodic(period) => Some(tokio::time::interval_at(tokio::time::Instant::now() + period, period)),
+            PersistMode::OnWrite => None,
+        };
+
+        loop {
+            let tick = async {
+                match interval.as_mut() {
+                    Some(interval) => {
+                        interval.tick().await;
+                    },
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = shutdown.requested() => break,
+                _ = tick => {},
+                // 修改计数只保留最新值，排队的多次修改只保存一次
+                changed = modified.changed(), if mode == PersistMode::OnWrite => {
+                    if changed.is_err() {
+                        break;
+                    }
+                },
+            }
+
+            if let Err(e) = save_snapshot(&path, &service.take_snapshot()).await {
+                log::error!("Failed to persist server data to {}: {}", path.display(), e);
+            }
+        }
+
+        if let Err(e) = save_snapshot(&path, &service.take_snapshot()).await {
+            log::error!("Failed to persist server data to {}: {}", path.display(), e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ExceptionCode, Table};
+    use crate::server::{AccessRule, Request, RequestContext, TransportKind};
+
+    #[tokio::test]
+    async fn test_persist_on_write_and_restore() {
+        let path = std::env::temp_dir().join(format!("modbus-rs-persistence-{}.json", std::process::id()));
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let service = MultiSlaveMemoryService::new();
+        service.add_slave(1);
+        service.set_coil(1, 3, true).unwrap();
+        let shutdown = ShutdownHandle::new();
+        let task = spawn_persistence(service.clone(), &path, PersistMode::OnWrite, shutdown.clone()).await.unwrap();
+
+        let ctx = RequestContext { unit_id: 1, peer_addr: None, transport: TransportKind::Tcp };
+        let slave = service.slave(1).unwrap();
+        slave.handle(&ctx, Request::WriteMultipleRegisters { address: 10, values: vec![7, 8] }).unwrap();
+        shutdown.shutdown();
+        task.await.unwrap();
+
+        // 新的服务从文件恢复
+        let restored = MultiSlaveMemoryService::new();
+        let snapshot: MultiSlaveSnapshot = load_snapshot(&path).await.unwrap().unwrap();
+        restored.restore(&snapshot);
+        assert_eq!(restored.get_slave_ids(), vec![1]);
+        let slave = restored.slave(1).unwrap();
+        assert!(slave.get_coil(3));
+        assert_eq!(slave.get_holding_register(11), 8);
+        assert_eq!(restored.snapshot(), service.snapshot());
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn test_persist_local_changes_and_write_once() {
+        let path = std::env::temp_dir().join(format!("modbus-rs-persistence-local-{}.json", std::process::id()));
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let service = MemoryService::new();
+        service.add_access_rule(AccessRule::write_once(Table::HoldingRegisters, 0..=0));
+        let shutdown = ShutdownHandle::new();
+        let task = spawn_persistence(service.clone(), &path, PersistMode::OnWrite, shutdown.clone()).await.unwrap();
+
+        // 本地修改同样触发保存
+        service.set_input_register(3, 42);
+        let saved = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let Ok(Some(snapshot)) = load_snapshot::<DataSnapshot>(&path).await {
+                    break snapshot;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }).await.unwrap();
+        assert_eq!(saved.input_registers.get(&3), Some(&42));
+
+        let ctx = RequestContext { unit_id: 1, peer_addr: None, transport: TransportKind::Tcp };
+        service.handle(&ctx, Request::WriteSingleRegister { address: 0, value: 5 }).unwrap();
+        shutdown.shutdown();
+        task.await.unwrap();
+
+        // 恢复后一次写入的寄存器仍然不能再写
+        let snapshot: DataSnapshot = load_snapshot(&path).await.unwrap().unwrap();
+        assert_eq!(snapshot.written_once, BTreeSet::from([ModbusAddress::holding_register(0)]));
+        let restored = MemoryService::new();
+        restored.add_access_rule(AccessRule::write_once(Table::HoldingRegisters, 0..=0));
+        restored.restore(&snapshot);
+        assert_eq!(restored.get_holding_register(0), 5);
+        assert_eq!(
+            restored.handle(&ctx, Request::WriteSingleRegister { address: 0, value: 6 }).unwrap_err(),
+            ExceptionCode::IllegalDataAddress,
+        );
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
@@ -0,0 +1,201 @@
+use crate::protocol::*;
+use crate::client::ModbusMaster;
+use super::gateway::GatewayService;
+use super::service::*;
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// 网关的下游目标
+///
+/// `GatewayService`对任意传输方式的客户端都实现了该接口，路由器通过它把不同下游放在同一张路由表中。
+pub trait GatewayTarget: Send + Sync + 'static {
+    /// 向下游的指定单元转发请求
+    fn forward<'a>(&'a self, unit_id: u8, request: &'a Request) -> Pin<Box<dyn Future<Output = Result<Response, ExceptionCode>> + Send + 'a>>;
+}
+
+impl<M: ModbusMaster> GatewayTarget for GatewayService<M> {
+    fn forward<'a>(&'a self, unit_id: u8, request: &'a Request) -> Pin<Box<dyn Future<Output = Result<Response, ExceptionCode>> + Send + 'a>> {
+        Box::pin(GatewayService::forward(self, unit_id, request))
+    }
+}
+
+/// 路由条目：匹配单元ID，可选地限定功能码和地址范围，并可重映射单元ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    units: RangeInclusive<u8>,
+    function_codes: Vec<FunctionCode>,
+    table: Option<Table>,
+    addresses: Option<RangeInclusive<u16>>,
+    mapped_start: Option<u8>,
+}
+
+impl Route {
+    /// 匹配单个单元ID
+    pub fn unit(unit_id: u8) -> Self {
+        Self::units(unit_id..=unit_id)
+    }
+
+    /// 匹配一段单元ID
+    pub fn units(units: RangeInclusive<u8>) -> Self {
+        Self {
+            units,
+            function_codes: Vec::new(),
+            table: None,
+            addresses: None,
+            mapped_start: None,
+        }
+    }
+
+    /// 转发时把单元ID重映射，范围内第一个单元映射为`unit_id`，其余依次顺延
+    pub fn map_to(mut self, unit_id: u8) -> Self {
+        self.mapped_start = Some(unit_id);
+        self
+    }
+
+    /// 只匹配这些功能码
+    pub fn for_functions(mut self, function_codes: &[FunctionCode]) -> Self {
+        self.function_codes = function_codes.to_vec();
+        self
+    }
+
+    /// 只匹配完全落在该数据表地址范围内的请求
+    pub fn on(mut self, table: Table, addresses: RangeInclusive<u16>) -> Self {
+        self.table = Some(table);
+        self.addresses = Some(addresses);
+        self
+    }
+
+    /// 判断请求是否匹配
+    pub fn matches(&self, unit_id: u8, request: &Request) -> bool {
+        if !self.units.contains(&unit_id) {
+            return false;
+        }
+        if !self.function_codes.is_empty() && !self.function_codes.contains(&request.function_code()) {
+            return false;
+        }
+        if self.table.is_some_and(|table| table != request.table()) {
+            return false;
+        }
+        match &self.addresses {
+            Some(addresses) => {
+                let start = request.address() as u32;
+                let end = start + request.quantity().saturating_sub(1) as u32;
+                *addresses.start() as u32 <= start && end <= *addresses.end() as u32
+            },
+            None => true,
+        }
+    }
+
+    /// 计算下游使用的单元ID，映射后超出0～255时返回None
+    pub fn map_unit(&self, unit_id: u8) -> Option<u8> {
+        match self.mapped_start {
+            Some(start) => {
+                let offset = unit_id.checked_sub(*self.units.start())?;
+                start.checked_add(offset)
+            },
+            None => Some(unit_id),
+        }
+    }
+}
+
+/// 按单元ID把请求分发给多个下游的网关服务
+///
+/// 路由按添加顺序匹配，第一条匹配的路由生效；没有匹配的路由时回复`GatewayPathUnavailable`。
+/// 多条路由可以共享同一个下游目标，对同一总线的访问仍然是串行的。
+#[derive(Clone, Default)]
+pub struct GatewayRouter {
+    routes: Vec<(Route, Arc<dyn GatewayTarget>)>,
+}
+
+impl GatewayRouter {
+    /// 创建空路由表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加路由
+    pub fn with_route<T: GatewayTarget>(mut self, route: Route, target: Arc<T>) -> Self {
+        self.routes.push((route, target));
+        self
+    }
+
+    /// 获取所有路由条目
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.routes.iter().map(|(route, _)| route)
+    }
+
+    /// 查找请求对应的下游目标和下游单元ID
+    pub fn resolve(&self, unit_id: u8, request: &Request) -> Option<(Arc<dyn GatewayTarget>, u8)> {
+        self.routes
+            .iter()
+            .find(|(route, _)| route.matches(unit_id, request))
+            .and_then(|(route, target)| Some((Arc::clone(target), route.map_unit(unit_id)?)))
+    }
+}
+
+impl Service for GatewayRouter {
+    async fn call(&self, ctx: RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        match self.resolve(ctx.unit_id, &request) {
+            Some((target, unit_id)) => target.forward(unit_id, &request).await,
+            None => {
+                log::debug!("No gateway route for unit {} {:?}", ctx.unit_id, request.function_code());
+                Err(ExceptionCode::GatewayPathUnavailable)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// 记录收到的单元ID，读寄存器时返回单元ID
+    #[derive(Default)]
+    struct Recorder {
+        units: Mutex<Vec<u8>>,
+    }
+
+    impl GatewayTarget for Recorder {
+        fn forward<'a>(&'a self, unit_id: u8, request: &'a Request) -> Pin<Box<dyn Future<Output = Result<Response, ExceptionCode>> + Send + 'a>> {
+            self.units.lock().unwrap().push(unit_id);
+            let count = request.quantity() as usize;
+            Box::pin(async move { Ok(Response::ReadHoldingRegisters(vec![unit_id as u16; count])) })
+        }
+    }
+
+    fn ctx(unit_id: u8) -> RequestContext {
+        RequestContext { unit_id, peer_addr: None, transport: TransportKind::Tcp }
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let serial = Arc::new(Recorder::default());
+        let converter = Arc::new(Recorder::default());
+        let plc = Arc::new(Recorder::default());
+        let router = GatewayRouter::new()
+            .with_route(Route::units(1..=10), Arc::clone(&serial))
+            .with_route(Route::units(11..=20).map_to(1), Arc::clone(&converter))
+            .with_route(
+                Route::unit(100).map_to(1).for_functions(&[FunctionCode::ReadHoldingRegisters]).on(Table::HoldingRegisters, 0..=99),
+                Arc::clone(&plc),
+            );
+
+        let read = |address, count| Request::ReadHoldingRegisters { address, count };
+        assert_eq!(router.call(ctx(5), read(0, 1)).await, Ok(Response::ReadHoldingRegisters(vec![5])));
+        assert_eq!(router.call(ctx(13), read(0, 1)).await, Ok(Response::ReadHoldingRegisters(vec![3])));
+        assert_eq!(router.call(ctx(100), read(90, 10)).await, Ok(Response::ReadHoldingRegisters(vec![1; 10])));
+
+        // 超出地址范围、功能码不匹配或单元ID没有路由
+        let unroutable = Err(ExceptionCode::GatewayPathUnavailable);
+        assert_eq!(router.call(ctx(100), read(95, 10)).await, unroutable);
+        assert_eq!(router.call(ctx(100), Request::WriteSingleRegister { address: 0, value: 1 }).await, unroutable);
+        assert_eq!(router.call(ctx(50), read(0, 1)).await, unroutable);
+
+        assert_eq!(*serial.units.lock().unwrap(), vec![5]);
+        assert_eq!(*converter.units.lock().unwrap(), vec![3]);
+        assert_eq!(*plc.units.lock().unwrap(), vec![1]);
+    }
+}
@@ -0,0 +1,901 @@
+use crate::protocol::*;
+use crate::utils::DataConverter;
+use super::access::*;
+use super::hooks::*;
+use super::persistence::{DataSnapshot, MultiSlaveSnapshot};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, watch};
+
+/// 单次读取线圈/离散输入的最大数量
+pub const MAX_READ_BITS: u16 = 2000;
+/// 单次读取寄存器的最大数量
+pub const MAX_READ_REGISTERS: u16 = 125;
+/// 单次写入多个线圈的最大数量
+pub const MAX_WRITE_BITS: u16 = 1968;
+/// 单次写入多个寄存器的最大数量
+pub const MAX_WRITE_REGISTERS: u16 = 123;
+
+/// 请求到达服务器所经过的传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    Tcp,
+    Rtu,
+    RtuOverTcp,
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportKind::Tcp => write!(f, "TCP"),
+            TransportKind::Rtu => write!(f, "RTU"),
+            TransportKind::RtuOverTcp => write!(f, "RTU over TCP"),
+        }
+    }
+}
+
+/// 请求上下文
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// 单元标识符（从机地址）
+    pub unit_id: u8,
+    /// 对端地址，串口请求为None
+    pub peer_addr: Option<SocketAddr>,
+    /// 传输方式
+    pub transport: TransportKind,
+}
+
+/// 类型化的Modbus请求
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    ReadCoils { address: u16, count: u16 },
+    ReadDiscreteInputs { address: u16, count: u16 },
+    ReadHoldingRegisters { address: u16, count: u16 },
+    ReadInputRegisters { address: u16, count: u16 },
+    WriteSingleCoil { address: u16, value: bool },
+    WriteSingleRegister { address: u16, value: u16 },
+    WriteMultipleCoils { address: u16, values: Vec<bool> },
+    WriteMultipleRegisters { address: u16, values: Vec<u16> },
+}
+
+impl Request {
+    /// 从原始请求解析，并按Modbus规范校验数量和地址范围
+    pub fn from_modbus_request(request: &ModbusRequest) -> Result<Self, ExceptionCode> {
+        let address = request.address;
+        let count = request.count;
+
+        match request.function_code {
+            FunctionCode::ReadCoils => {
+                check_range(address, count, MAX_READ_BITS)?;
+                Ok(Request::ReadCoils { address, count })
+            },
+            FunctionCode::ReadDiscreteInputs => {
+                check_range(address, count, MAX_READ_BITS)?;
+                Ok(Request::ReadDiscreteInputs { address, count })
+            },
+            FunctionCode::ReadHoldingRegisters => {
+                check_range(address, count, MAX_READ_REGISTERS)?;
+                Ok(Request::ReadHoldingRegisters { address, count })
+            },
+            FunctionCode::ReadInputRegisters => {
+                check_range(address, count, MAX_READ_REGISTERS)?;
+                Ok(Request::ReadInputRegisters { address, count })
+            },
+            FunctionCode::WriteSingleCoil => {
+                // 线圈值只能是0xFF00（ON）或0x0000（OFF）
+                let value = match count {
+                    0xFF00 => true,
+                    0x0000 => false,
+                    _ => return Err(ExceptionCode::IllegalDataValue),
+                };
+                Ok(Request::WriteSingleCoil { address, value })
+            },
+            FunctionCode::WriteSingleRegister => {
+                let data = request.data.as_deref().unwrap_or_default();
+                if data.len() < 2 {
+                    return Err(ExceptionCode::IllegalDataValue);
+                }
+                let value = u16::from_be_bytes([data[0], data[1]]);
+                Ok(Request::WriteSingleRegister { address, value })
+            },
+            FunctionCode::WriteMultipleCoils => {
+                check_range(address, count, MAX_WRITE_BITS)?;
+                let data = request.data.as_deref().unwrap_or_default();
+                if data.len() < count.div_ceil(8) as usize {
+                    return Err(ExceptionCode::IllegalDataValue);
+                }
+                let values = DataConverter::bytes_to_bool_array(data, count as usize);
+                Ok(Request::WriteMultipleCoils { address, values })
+            },
+            FunctionCode::WriteMultipleRegisters => {
+                check_range(address, count, MAX_WRITE_REGISTERS)?;
+                let data = request.data.as_deref().unwrap_or_default();
+                if data.len() != count as usize * 2 {
+                    return Err(ExceptionCode::IllegalDataValue);
+                }
+                let values = DataConverter::bytes_to_u16_array(data, ByteOrder::ABCD)
+                    .map_err(|_| ExceptionCode::IllegalDataValue)?;
+                Ok(Request::WriteMultipleRegisters { address, values })
+            },
+        }
+    }
+
+    /// 编码为发往指定从机的原始请求，用于网关转发
+    pub fn to_modbus_request(&self, slave_id: u8) -> ModbusRequest {
+        let (count, data) = match self {
+            Request::ReadCoils { count, .. }
+            | Request::ReadDiscreteInputs { count, .. }
+            | Request::ReadHoldingRegisters { count, .. }
+            | Request::ReadInputRegisters { count, .. } => (*count, None),
+            Request::WriteSingleCoil { value, .. } => (if *value { 0xFF00 } else { 0x0000 }, None),
+            Request::WriteSingleRegister { value, .. } => (1, Some(value.to_be_bytes().to_vec())),
+            Request::WriteMultipleCoils { values, .. } => {
+                (values.len() as u16, Some(DataConverter::bool_array_to_bytes(values)))
+            },
+            Request::WriteMultipleRegisters { values, .. } => {
+                (values.len() as u16, Some(DataConverter::u16_array_to_bytes(values, ByteOrder::ABCD)))
+            },
+        };
+
+        ModbusRequest {
+            slave_id,
+            function_code: self.function_code(),
+            address: self.address(),
+            count,
+            data,
+        }
+    }
+
+    /// 获取请求访问的数据表
+    pub fn table(&self) -> Table {
+        match self {
+            Request::ReadCoils { .. } | Request::WriteSingleCoil { .. } | Request::WriteMultipleCoils { .. } => Table::Coils,
+            Request::ReadDiscreteInputs { .. } => Table::DiscreteInputs,
+            Request::ReadHoldingRegisters { .. } | Request::WriteSingleRegister { .. } | Request::WriteMultipleRegisters { .. } => Table::HoldingRegisters,
+            Request::ReadInputRegisters { .. } => Table::InputRegisters,
+        }
+    }
+
+    /// 获取请求的起始地址
+    pub fn address(&self) -> u16 {
+        match self {
+            Request::ReadCoils { address, .. }
+            | Request::ReadDiscreteInputs { address, .. }
+            | Request::ReadHoldingRegisters { address, .. }
+            | Request::ReadInputRegisters { address, .. }
+            | Request::WriteSingleCoil { address, .. }
+            | Request::WriteSingleRegister { address, .. }
+            | Request::WriteMultipleCoils { address, .. }
+            | Request::WriteMultipleRegisters { address, .. } => *address,
+        }
+    }
+
+    /// 获取请求涉及的线圈/寄存器数量
+    pub fn quantity(&self) -> u16 {
+        match self {
+            Request::ReadCoils { count, .. }
+            | Request::ReadDiscreteInputs { count, .. }
+            | Request::ReadHoldingRegisters { count, .. }
+            | Request::ReadInputRegisters { count, .. } => *count,
+            Request::WriteSingleCoil { .. } | Request::WriteSingleRegister { .. } => 1,
+            Request::WriteMultipleCoils { values, .. } => values.len() as u16,
+            Request::WriteMultipleRegisters { values, .. } => values.len() as u16,
+        }
+    }
+
+    /// 是否为写请求
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Request::WriteSingleCoil { .. }
+                | Request::WriteSingleRegister { .. }
+                | Request::WriteMultipleCoils { .. }
+                | Request::WriteMultipleRegisters { .. }
+        )
+    }
+
+    /// 获取请求对应的功能码
+    pub fn function_code(&self) -> FunctionCode {
+        match self {
+            Request::ReadCoils { .. } => FunctionCode::ReadCoils,
+            Request::ReadDiscreteInputs { .. } => FunctionCode::ReadDiscreteInputs,
+            Request::ReadHoldingRegisters { .. } => FunctionCode::ReadHoldingRegisters,
+            Request::ReadInputRegisters { .. } => FunctionCode::ReadInputRegisters,
+            Request::WriteSingleCoil { .. } => FunctionCode::WriteSingleCoil,
+            Request::WriteSingleRegister { .. } => FunctionCode::WriteSingleRegister,
+            Request::WriteMultipleCoils { .. } => FunctionCode::WriteMultipleCoils,
+            Request::WriteMultipleRegisters { .. } => FunctionCode::WriteMultipleRegisters,
+        }
+    }
+}
+
+/// 校验数量在1..=max之间，且地址范围不越过0xFFFF
+fn check_range(address: u16, count: u16, max: u16) -> Result<(), ExceptionCode> {
+    if count == 0 || count > max {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    if address as u32 + count as u32 > 0x10000 {
+        return Err(ExceptionCode::IllegalDataAddress);
+    }
+    Ok(())
+}
+
+/// 类型化的Modbus响应
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    ReadCoils(Vec<bool>),
+    ReadDiscreteInputs(Vec<bool>),
+    ReadHoldingRegisters(Vec<u16>),
+    ReadInputRegisters(Vec<u16>),
+    WriteSingleCoil { address: u16, value: bool },
+    WriteSingleRegister { address: u16, value: u16 },
+    WriteMultipleCoils { address: u16, count: u16 },
+    WriteMultipleRegisters { address: u16, count: u16 },
+}
+
+impl Response {
+    /// 获取响应对应的功能码
+    pub fn function_code(&self) -> FunctionCode {
+        match self {
+            Response::ReadCoils(_) => FunctionCode::ReadCoils,
+            Response::ReadDiscreteInputs(_) => FunctionCode::ReadDiscreteInputs,
+            Response::ReadHoldingRegisters(_) => FunctionCode::ReadHoldingRegisters,
+            Response::ReadInputRegisters(_) => FunctionCode::ReadInputRegisters,
+            Response::WriteSingleCoil { .. } => FunctionCode::WriteSingleCoil,
+            Response::WriteSingleRegister { .. } => FunctionCode::WriteSingleRegister,
+            Response::WriteMultipleCoils { .. } => FunctionCode::WriteMultipleCoils,
+            Response::WriteMultipleRegisters { .. } => FunctionCode::WriteMultipleRegisters,
+        }
+    }
+
+    /// 从下游从机的原始响应解析，须先检查`is_exception`
+    ///
+    /// 写操作的回显只校验长度，结果按请求内容构造。
+    pub fn from_modbus_response(request: &Request, response: &ModbusResponse) -> Result<Self, ModbusError> {
+        if response.function_code != request.function_code() {
+            return Err(ModbusError::ProtocolError(format!(
+                "Expected {:?} response, got {:?}",
+                request.function_code(),
+                response.function_code
+            )));
+        }
+
+        let data = &response.data;
+        let count = request.quantity() as usize;
+        match request {
+            Request::ReadCoils { .. } | Request::ReadDiscreteInputs { .. } => {
+                if data.len() < count.div_ceil(8) {
+                    return Err(ModbusError::InvalidDataLength);
+                }
+                let values = DataConverter::bytes_to_bool_array(data, count);
+                Ok(match request {
+                    Request::ReadCoils { .. } => Response::ReadCoils(values),
+                    _ => Response::ReadDiscreteInputs(values),
+                })
+            },
+            Request::ReadHoldingRegisters { .. } | Request::ReadInputRegisters { .. } => {
+                if data.len() != count * 2 {
+                    return Err(ModbusError::InvalidDataLength);
+                }
+                let values = DataConverter::bytes_to_u16_array(data, ByteOrder::ABCD)?;
+                Ok(match request {
+                    Request::ReadHoldingRegisters { .. } => Response::ReadHoldingRegisters(values),
+                    _ => Response::ReadInputRegisters(values),
+                })
+            },
+            _ if data.len() != 4 => Err(ModbusError::InvalidDataLength),
+            Request::WriteSingleCoil { address, value } => {
+                Ok(Response::WriteSingleCoil { address: *address, value: *value })
+            },
+            Request::WriteSingleRegister { address, value } => {
+                Ok(Response::WriteSingleRegister { address: *address, value: *value })
+            },
+            Request::WriteMultipleCoils { address, values } => {
+                Ok(Response::WriteMultipleCoils { address: *address, count: values.len() as u16 })
+            },
+            Request::WriteMultipleRegisters { address, values } => {
+                Ok(Response::WriteMultipleRegisters { address: *address, count: values.len() as u16 })
+            },
+        }
+    }
+
+    /// 编码为原始响应
+    pub fn into_modbus_response(self, slave_id: u8) -> ModbusResponse {
+        let function_code = self.function_code();
+        let data = match self {
+            Response::ReadCoils(values) | Response::ReadDiscreteInputs(values) => {
+                let bytes = DataConverter::bool_array_to_bytes(&values);
+                let mut data = vec![bytes.len() as u8];
+                data.extend_from_slice(&bytes);
+                data
+            },
+            Response::ReadHoldingRegisters(values) | Response::ReadInputRegisters(values) => {
+                let bytes = DataConverter::u16_array_to_bytes(&values, ByteOrder::ABCD);
+                let mut data = vec![bytes.len() as u8];
+                data.extend_from_slice(&bytes);
+                data
+            },
+            Response::WriteSingleCoil { address, value } => {
+                let value: u16 = if value { 0xFF00 } else { 0x0000 };
+                [address.to_be_bytes(), value.to_be_bytes()].concat()
+            },
+            Response::WriteSingleRegister { address, value } => {
+                [address.to_be_bytes(), value.to_be_bytes()].concat()
+            },
+            Response::WriteMultipleCoils { address, count } |
+            Response::WriteMultipleRegisters { address, count } => {
+                [address.to_be_bytes(), count.to_be_bytes()].concat()
+            },
+        };
+
+        ModbusResponse {
+            slave_id,
+            function_code,
+            data,
+            is_exception: false,
+            exception_code: None,
+        }
+    }
+}
+
+/// 服务器请求处理接口
+///
+/// 服务器负责收发和解析数据帧，具体的读写由实现该trait的服务完成，
+/// 可以对接内存表、数据库或硬件驱动。返回`Err`时服务器向客户端回复对应的异常码。
+pub trait Service: Send + Sync + 'static {
+    /// 处理一个请求
+    fn call(&self, ctx: RequestContext, request: Request) -> impl Future<Output = Result<Response, ExceptionCode>> + Send;
+}
+
+impl<S: Service> Service for Arc<S> {
+    fn call(&self, ctx: RequestContext, request: Request) -> impl Future<Output = Result<Response, ExceptionCode>> + Send {
+        (**self).call(ctx, request)
+    }
+}
+
+/// 解析原始请求，交给服务处理，并将结果编码为原始响应
+pub async fn dispatch<S: Service>(service: &S, ctx: RequestContext, request: &ModbusRequest) -> ModbusResponse {
+    let unit_id = ctx.unit_id;
+
+    let typed_request = match Request::from_modbus_request(request) {
+        Ok(typed_request) => typed_request,
+        Err(code) => return ModbusResponse::exception(unit_id, request.function_code, code),
+    };
+
+    match service.call(ctx, typed_request).await {
+        Ok(response) if response.function_code() == request.function_code => {
+            response.into_modbus_response(unit_id)
+        },
+        Ok(response) => {
+            log::error!(
+                "Service returned {:?} response for {:?} request",
+                response.function_code(),
+                request.function_code
+            );
+            ModbusResponse::exception(unit_id, request.function_code, ExceptionCode::SlaveDeviceFailure)
+        },
+        Err(code) => ModbusResponse::exception(unit_id, request.function_code, code),
+    }
+}
+
+/// 基于内存表的默认服务
+///
+/// 四张数据表各自由`Arc<Mutex<HashMap>>`保存，克隆后共享同一份数据。
+/// 未设置的地址读取为0/false。客户端的写入依次经过访问规则检查和写入钩子，
+/// 成功后产生变更通知；本地调用`set_*`不受这些限制。
+#[derive(Clone, Default)]
+pub struct MemoryService {
+    coils: Arc<Mutex<HashMap<u16, bool>>>,
+    discrete_inputs: Arc<Mutex<HashMap<u16, bool>>>,
+    holding_registers: Arc<Mutex<HashMap<u16, u16>>>,
+    input_registers: Arc<Mutex<HashMap<u16, u16>>>,
+    hooks: Arc<WriteHooks>,
+    access: Arc<Mutex<AccessMap>>,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl MemoryService {
+    /// 创建空的内存服务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置线圈值
+    pub fn set_coil(&self, address: u16, value: bool) {
+        self.coils.lock().unwrap().insert(address, value);
+        self.hooks.mark_modified();
+    }
+
+    /// 设置离散输入值
+    pub fn set_discrete_input(&self, address: u16, value: bool) {
+        self.discrete_inputs.lock().unwrap().insert(address, value);
+        self.hooks.mark_modified();
+    }
+
+    /// 设置保持寄存器值
+    pub fn set_holding_register(&self, address: u16, value: u16) {
+        self.holding_registers.lock().unwrap().insert(address, value);
+        self.hooks.mark_modified();
+    }
+
+    /// 设置输入寄存器值
+    pub fn set_input_register(&self, address: u16, value: u16) {
+        self.input_registers.lock().unwrap().insert(address, value);
+        self.hooks.mark_modified();
+    }
+
+    /// 获取线圈值
+    pub fn get_coil(&self, address: u16) -> bool {
+        self.coils.lock().unwrap().get(&address).copied().unwrap_or(false)
+    }
+
+    /// 获取离散输入值
+    pub fn get_discrete_input(&self, address: u16) -> bool {
+        self.discrete_inputs.lock().unwrap().get(&address).copied().unwrap_or(false)
+    }
+
+    /// 获取保持寄存器值
+    pub fn get_holding_register(&self, address: u16) -> u16 {
+        self.holding_registers.lock().unwrap().get(&address).copied().unwrap_or(0)
+    }
+
+    /// 获取输入寄存器值
+    pub fn get_input_register(&self, address: u16) -> u16 {
+        self.input_registers.lock().unwrap().get(&address).copied().unwrap_or(0)
+    }
+
+    /// 原子地写入一段连续的保持寄存器或输入寄存器
+    pub fn set_registers(&self, table: Table, address: u16, values: &[u16]) -> Result<(), ModbusError> {
+        check_register_span(table, address, values.len())?;
+        match table {
+            Table::HoldingRegisters => write_table(&self.holding_registers, address, values),
+            _ => write_table(&self.input_registers, address, values),
+        }
+        self.hooks.mark_modified();
+        Ok(())
+    }
+
+    /// 原子地读取一段连续的保持寄存器或输入寄存器
+    pub fn get_registers(&self, table: Table, address: u16, count: u16) -> Result<Vec<u16>, ModbusError> {
+        check_register_span(table, address, count as usize)?;
+        Ok(match table {
+            Table::HoldingRegisters => read_table(&self.holding_registers, address, count, 0),
+            _ => read_table(&self.input_registers, address, count, 0),
+        })
+    }
+
+    /// 获取四张数据表的快照
+    pub fn snapshot(&self) -> DataSnapshot {
+        let _write = self.write_lock.lock().unwrap();
+        DataSnapshot {
+            coils: self.coils.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect(),
+            discrete_inputs: self.discrete_inputs.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect(),
+            holding_registers: self.holding_registers.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect(),
+            input_registers: self.input_registers.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect(),
+            written_once: self.access.lock().unwrap().written_once().collect(),
+        }
+    }
+
+    /// 用快照替换四张数据表的内容和一次写入记录，访问规则和钩子保持不变
+    pub fn restore(&self, snapshot: &DataSnapshot) {
+        let _write = self.write_lock.lock().unwrap();
+        self.access.lock().unwrap().set_written_once(snapshot.written_once.iter().copied());
+        *self.coils.lock().unwrap() = snapshot.coils.iter().map(|(k, v)| (*k, *v)).collect();
+        *self.discrete_inputs.lock().unwrap() = snapshot.discrete_inputs.iter().map(|(k, v)| (*k, *v)).collect();
+        *self.holding_registers.lock().unwrap() = snapshot.holding_registers.iter().map(|(k, v)| (*k, *v)).collect();
+        *self.input_registers.lock().unwrap() = snapshot.input_registers.iter().map(|(k, v)| (*k, *v)).collect();
+    }
+
+    /// 添加访问规则，只约束客户端写入，本地`set_*`不受限制
+    pub fn add_access_rule(&self, rule: AccessRule) {
+        self.access.lock().unwrap().add_rule(rule);
+    }
+
+    /// 清除所有访问规则
+    pub fn clear_access_rules(&self) {
+        self.access.lock().unwrap().clear();
+    }
+
+    /// 注册写入前钩子，客户端写入前调用，返回`Err`时拒绝写入
+    pub fn on_before_write<F>(&self, hook: F)
+    where
+        F: Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync + 'static,
+    {
+        self.hooks.on_before_write(hook);
+    }
+
+    /// 注册写入后回调，客户端写入成功后调用
+    pub fn on_after_write<F>(&self, callback: F)
+    where
+        F: Fn(&DataChange) + Send + Sync + 'static,
+    {
+        self.hooks.on_after_write(callback);
+    }
+
+    /// 订阅客户端写入产生的数据变更
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<DataChange> {
+        self.hooks.subscribe()
+    }
+
+    /// 订阅修改计数，本地`set_*`和客户端写入都会使计数递增
+    pub fn watch_modified(&self) -> watch::Receiver<u64> {
+        self.hooks.watch_modified()
+    }
+
+    /// 同步处理请求
+    pub fn handle(&self, ctx: &RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        let change = Self::change_of(ctx.unit_id, &request);
+
+        // 客户端写入串行执行，保证权限检查、钩子和写入之间不被其他写入打断
+        let write_guard = match &change {
+            Some(change) => {
+                let guard = self.write_lock.lock().unwrap();
+                self.access.lock().unwrap().check_write(change)?;
+                self.hooks.before_write(ctx, change)?;
+                Some(guard)
+            },
+            None => None,
+        };
+
+        let response = match request {
+            Request::ReadCoils { address, count } => {
+                Response::ReadCoils(read_table(&self.coils, address, count, false))
+            },
+            Request::ReadDiscreteInputs { address, count } => {
+                Response::ReadDiscreteInputs(read_table(&self.discrete_inputs, address, count, false))
+            },
+            Request::ReadHoldingRegisters { address, count } => {
+                Response::ReadHoldingRegisters(read_table(&self.holding_registers, address, count, 0))
+            },
+            Request::ReadInputRegisters { address, count } => {
+                Response::ReadInputRegisters(read_table(&self.input_registers, address, count, 0))
+            },
+            Request::WriteSingleCoil { address, value } => {
+                self.set_coil(address, value);
+                Response::WriteSingleCoil { address, value }
+            },
+            Request::WriteSingleRegister { address, value } => {
+                self.set_holding_register(address, value);
+                Response::WriteSingleRegister { address, value }
+            },
+            Request::WriteMultipleCoils { address, values } => {
+                write_table(&self.coils, address, &values);
+                Response::WriteMultipleCoils { address, count: values.len() as u16 }
+            },
+            Request::WriteMultipleRegisters { address, values } => {
+                write_table(&self.holding_registers, address, &values);
+                Response::WriteMultipleRegisters { address, count: values.len() as u16 }
+            },
+        };
+
+        if let Some(change) = change {
+            self.access.lock().unwrap().record_write(&change);
+            drop(write_guard);
+            self.hooks.after_write(change);
+        }
+
+        Ok(response)
+    }
+
+    /// 写请求对应的数据变更，读请求返回None
+    fn change_of(unit_id: u8, request: &Request) -> Option<DataChange> {
+        let (table, address, values) = match request {
+            Request::WriteSingleCoil { address, value } => {
+                (Table::Coils, *address, TableValues::Bits(vec![*value]))
+            },
+            Request::WriteSingleRegister { address, value } => {
+                (Table::HoldingRegisters, *address, TableValues::Registers(vec![*value]))
+            },
+            Request::WriteMultipleCoils { address, values } => {
+                (Table::Coils, *address, TableValues::Bits(values.clone()))
+            },
+            Request::WriteMultipleRegisters { address, values } => {
+                (Table::HoldingRegisters, *address, TableValues::Registers(values.clone()))
+            },
+            _ => return None,
+        };
+
+        Some(DataChange {
+            unit_id,
+            table,
+            address,
+            values,
+        })
+    }
+}
+
+impl Service for MemoryService {
+    async fn call(&self, ctx: RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        self.handle(&ctx, request)
+    }
+}
+
+fn check_register_span(table: Table, address: u16, len: usize) -> Result<(), ModbusError> {
+    if table.is_bit() {
+        return Err(ModbusError::ProtocolError(format!("{:?} is not a register table", table)));
+    }
+    if address as usize + len > 0x10000 {
+        return Err(ModbusError::ProtocolError(format!(
+            "Register range {}+{} exceeds address space",
+            address, len
+        )));
+    }
+    Ok(())
+}
+
+fn read_table<T: Copy>(table: &Mutex<HashMap<u16, T>>, address: u16, count: u16, default: T) -> Vec<T> {
+    let table = table.lock().unwrap();
+    (0..count)
+        .map(|i| table.get(&address.wrapping_add(i)).copied().unwrap_or(default))
+        .collect()
+}
+
+fn write_table<T: Copy>(table: &Mutex<HashMap<u16, T>>, address: u16, values: &[T]) {
+    let mut table = table.lock().unwrap();
+    for (i, value) in values.iter().enumerate() {
+        table.insert(address.wrapping_add(i as u16), *value);
+    }
+}
+
+/// 多从机内存服务
+///
+/// 每个从机ID对应一个独立的`MemoryService`，未注册的从机返回IllegalDataAddress。
+/// 所有从机共享同一组写入钩子和变更通知，通过`DataChange::unit_id`区分。
+#[derive(Clone, Default)]
+pub struct MultiSlaveMemoryService {
+    slaves: Arc<Mutex<HashMap<u8, MemoryService>>>,
+    hooks: Arc<WriteHooks>,
+}
+
+impl MultiSlaveMemoryService {
+    /// 创建空的多从机内存服务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加从机
+    pub fn add_slave(&self, slave_id: u8) {
+        let slave = MemoryService {
+            hooks: Arc::clone(&self.hooks),
+            ..MemoryService::default()
+        };
+        self.slaves.lock().unwrap().insert(slave_id, slave);
+        self.hooks.mark_modified();
+    }
+
+    /// 移除从机
+    pub fn remove_slave(&self, slave_id: u8) {
+        self.slaves.lock().unwrap().remove(&slave_id);
+        self.hooks.mark_modified();
+    }
+
+    /// 获取指定从机的数据存储
+    pub fn slave(&self, slave_id: u8) -> Option<MemoryService> {
+        self.slaves.lock().unwrap().get(&slave_id).cloned()
+    }
+
+    /// 为指定从机添加访问规则
+    pub fn add_access_rule(&self, slave_id: u8, rule: AccessRule) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.add_access_rule(rule);
+        Ok(())
+    }
+
+    /// 注册写入前钩子，对所有从机生效
+    pub fn on_before_write<F>(&self, hook: F)
+    where
+        F: Fn(&RequestContext, &DataChange) -> Result<(), ExceptionCode> + Send + Sync + 'static,
+    {
+        self.hooks.on_before_write(hook);
+    }
+
+    /// 注册写入后回调，对所有从机生效
+    pub fn on_after_write<F>(&self, callback: F)
+    where
+        F: Fn(&DataChange) + Send + Sync + 'static,
+    {
+        self.hooks.on_after_write(callback);
+    }
+
+    /// 订阅所有从机的数据变更
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<DataChange> {
+        self.hooks.subscribe()
+    }
+
+    /// 订阅所有从机的修改计数
+    pub fn watch_modified(&self) -> watch::Receiver<u64> {
+        self.hooks.watch_modified()
+    }
+
+    /// 获取所有已注册的从机 ID
+    pub fn get_slave_ids(&self) -> Vec<u8> {
+        self.slaves.lock().unwrap().keys().copied().collect()
+    }
+
+    /// 获取所有从机的数据快照
+    pub fn snapshot(&self) -> MultiSlaveSnapshot {
+        let slaves: Vec<(u8, MemoryService)> = self.slaves.lock().unwrap()
+            .iter()
+            .map(|(id, slave)| (*id, slave.clone()))
+            .collect();
+        MultiSlaveSnapshot {
+            slaves: slaves.into_iter().map(|(id, slave)| (id, slave.snapshot())).collect(),
+        }
+    }
+
+    /// 获取指定从机的数据快照
+    pub fn snapshot_slave(&self, slave_id: u8) -> Result<DataSnapshot, ModbusError> {
+        Ok(self.slave_or_err(slave_id)?.snapshot())
+    }
+
+    /// 按快照恢复从机数据，快照中不存在的从机会被添加，快照未包含的从机保持不变
+    pub fn restore(&self, snapshot: &MultiSlaveSnapshot) {
+        for (slave_id, data) in &snapshot.slaves {
+            if self.slave(*slave_id).is_none() {
+                self.add_slave(*slave_id);
+            }
+            if let Some(slave) = self.slave(*slave_id) {
+                slave.restore(data);
+            }
+        }
+    }
+
+    /// 恢复指定从机的数据
+    pub fn restore_slave(&self, slave_id: u8, snapshot: &DataSnapshot) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.restore(snapshot);
+        Ok(())
+    }
+
+    fn slave_or_err(&self, slave_id: u8) -> Result<MemoryService, ModbusError> {
+        self.slave(slave_id)
+            .ok_or_else(|| ModbusError::ProtocolError(format!("Slave {} not found", slave_id)))
+    }
+
+    /// 设置指定从机的线圈值
+    pub fn set_coil(&self, slave_id: u8, address: u16, value: bool) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.set_coil(address, value);
+        Ok(())
+    }
+
+    /// 设置指定从机的离散输入值
+    pub fn set_discrete_input(&self, slave_id: u8, address: u16, value: bool) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.set_discrete_input(address, value);
+        Ok(())
+    }
+
+    /// 设置指定从机的保持寄存器值
+    pub fn set_holding_register(&self, slave_id: u8, address: u16, value: u16) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.set_holding_register(address, value);
+        Ok(())
+    }
+
+    /// 设置指定从机的输入寄存器值
+    pub fn set_input_register(&self, slave_id: u8, address: u16, value: u16) -> Result<(), ModbusError> {
+        self.slave_or_err(slave_id)?.set_input_register(address, value);
+        Ok(())
+    }
+}
+
+impl Service for MultiSlaveMemoryService {
+    async fn call(&self, ctx: RequestContext, request: Request) -> Result<Response, ExceptionCode> {
+        match self.slave(ctx.unit_id) {
+            Some(slave) => slave.handle(&ctx, request),
+            None => Err(ExceptionCode::IllegalDataAddress),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(unit_id: u8) -> RequestContext {
+        RequestContext {
+            unit_id,
+            peer_addr: None,
+            transport: TransportKind::Tcp,
+        }
+    }
+
+    fn raw(function_code: FunctionCode, address: u16, count: u16, data: Option<Vec<u8>>) -> ModbusRequest {
+        ModbusRequest {
+            slave_id: 1,
+            function_code,
+            address,
+            count,
+            data,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_write_then_read() {
+        let service = MemoryService::new();
+
+        let request = raw(FunctionCode::WriteMultipleRegisters, 10, 2, Some(vec![0x12, 0x34, 0x56, 0x78]));
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert!(!response.is_exception);
+        assert_eq!(response.data, vec![0x00, 0x0A, 0x00, 0x02]);
+
+        let request = raw(FunctionCode::ReadHoldingRegisters, 10, 3, None);
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.data, vec![6, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00]);
+
+        let request = raw(FunctionCode::WriteSingleCoil, 3, 0xFF00, None);
+        dispatch(&service, ctx(1), &request).await;
+        let request = raw(FunctionCode::ReadCoils, 0, 9, None);
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.data, vec![2, 0b0000_1000, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_rejects_invalid_requests() {
+        let service = MemoryService::new();
+
+        let request = raw(FunctionCode::ReadHoldingRegisters, 0, 126, None);
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.exception_code, Some(ExceptionCode::IllegalDataValue));
+
+        let request = raw(FunctionCode::ReadCoils, 0xFFFF, 2, None);
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.exception_code, Some(ExceptionCode::IllegalDataAddress));
+
+        let request = raw(FunctionCode::WriteSingleCoil, 0, 0x1234, None);
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.exception_code, Some(ExceptionCode::IllegalDataValue));
+    }
+
+    #[tokio::test]
+    async fn test_multi_slave_unknown_unit() {
+        let service = MultiSlaveMemoryService::new();
+        service.add_slave(2);
+        service.set_holding_register(2, 0, 42).unwrap();
+        assert!(service.set_holding_register(3, 0, 42).is_err());
+
+        let request = raw(FunctionCode::ReadHoldingRegisters, 0, 1, None);
+        let response = dispatch(&service, ctx(2), &request).await;
+        assert_eq!(response.data, vec![2, 0x00, 0x2A]);
+
+        let response = dispatch(&service, ctx(3), &request).await;
+        assert_eq!(response.exception_code, Some(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[tokio::test]
+    async fn test_write_hooks_veto_and_notify() {
+        let service = MultiSlaveMemoryService::new();
+        service.add_slave(1);
+        service.add_slave(2);
+
+        let mut changes = service.subscribe_changes();
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&notified);
+        service.on_after_write(move |change| sink.lock().unwrap().push(change.clone()));
+        service.on_before_write(|_ctx, change| {
+            if change.table == Table::HoldingRegisters && change.address == 100 {
+                Err(ExceptionCode::IllegalDataValue)
+            } else {
+                Ok(())
+            }
+        });
+
+        let request = raw(FunctionCode::WriteMultipleRegisters, 10, 2, Some(vec![0, 1, 0, 2]));
+        let response = dispatch(&service, ctx(2), &request).await;
+        assert!(!response.is_exception);
+
+        let request = raw(FunctionCode::WriteSingleRegister, 100, 0, Some(vec![0, 9]));
+        let response = dispatch(&service, ctx(1), &request).await;
+        assert_eq!(response.exception_code, Some(ExceptionCode::IllegalDataValue));
+        assert_eq!(service.slave(1).unwrap().get_holding_register(100), 0);
+
+        let expected = DataChange {
+            unit_id: 2,
+            table: Table::HoldingRegisters,
+            address: 10,
+            values: TableValues::Registers(vec![1, 2]),
+        };
+        assert_eq!(changes.recv().await.unwrap(), expected);
+        assert!(changes.try_recv().is_err());
+        assert_eq!(*notified.lock().unwrap(), vec![expected]);
+    }
+}
@@ -0,0 +1,53 @@
+use tokio_util::sync::CancellationToken;
+
+/// 服务器关闭句柄
+///
+/// 可以克隆后交给其他任务。调用`shutdown`后服务器停止接受新连接，
+/// 已在处理中的请求在关闭超时内完成，随后`run`返回。
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    shutdown: CancellationToken,
+    stopped: CancellationToken,
+}
+
+impl ShutdownHandle {
+    /// 创建新的关闭句柄
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求关闭服务器
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// 是否已经请求关闭
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// 等待关闭请求
+    pub async fn requested(&self) {
+        self.shutdown.cancelled().await
+    }
+
+    /// 等待服务器的`run`返回
+    pub async fn stopped(&self) {
+        self.stopped.cancelled().await
+    }
+
+    /// 服务器是否已经停止
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.is_cancelled()
+    }
+
+    /// 关闭请求对应的取消令牌
+    pub fn token(&self) -> &CancellationToken {
+        &self.shutdown
+    }
+
+    /// 标记服务器已停止
+    pub(crate) fn mark_stopped(&self) {
+        self.stopped.cancel();
+    }
+}
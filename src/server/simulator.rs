@@ -0,0 +1,369 @@
+use crate::protocol::*;
+use crate::utils::{i48_from_registers, i48_to_registers, u48_from_registers, u48_to_registers, DataType, SimpleRng, I48_MAX, I48_MIN, U48_MAX};
+use super::config::ServerConfig;
+use super::service::{MemoryService, MultiSlaveMemoryService};
+use super::shutdown::ShutdownHandle;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 默认仿真更新周期
+pub const DEFAULT_SIMULATION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 信号发生器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Signal {
+    /// 正弦波：offset + amplitude * sin(2π·t/period + phase)
+    Sine {
+        amplitude: f64,
+        period_secs: f64,
+        #[serde(default)]
+        offset: f64,
+        #[serde(default)]
+        phase: f64,
+    },
+    /// 锯齿波：每个周期从min线性上升到max
+    Ramp { min: f64, max: f64, period_secs: f64 },
+    /// 方波：每个周期前duty部分为high，其余为low
+    Square {
+        low: f64,
+        high: f64,
+        period_secs: f64,
+        #[serde(default = "default_duty")]
+        duty: f64,
+    },
+    /// 随机游走：每次更新在±step内随机变化，限制在[min, max]内
+    RandomWalk { start: f64, step: f64, min: f64, max: f64 },
+    /// 计数器：每次更新加step，超过max后回到start
+    Counter {
+        #[serde(default)]
+        start: f64,
+        #[serde(default = "default_step")]
+        step: f64,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+    /// 复制同一从机另一个地址的值（按目标点的数据类型和字节序读取），地址写法与仿真点相同
+    Copy {
+        #[serde(flatten)]
+        source: ModbusAddress,
+    },
+}
+
+fn default_duty() -> f64 {
+    0.5
+}
+
+fn default_step() -> f64 {
+    1.0
+}
+
+/// 一个仿真点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedPoint {
+    /// 地址：`table`加数字地址，或不带`table`的Modicon/显式写法
+    #[serde(flatten)]
+    pub address: ModbusAddress,
+    /// 数据类型，省略时位表为`bool`（值大于0为true），寄存器表为`u16`
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<DataType>,
+    /// 字节序，省略时为ABCD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_order: Option<ByteOrder>,
+    /// 信号
+    pub signal: Signal,
+}
+
+impl SimulatedPoint {
+    /// 创建仿真点
+    pub fn new(table: Table, address: u16, signal: Signal) -> Self {
+        Self {
+            address: ModbusAddress::new(table, address),
+            data_type: None,
+            byte_order: None,
+            signal,
+        }
+    }
+
+    /// 设置数据类型和字节序
+    pub fn with_type(mut self, data_type: DataType, byte_order: ByteOrder) -> Self {
+        self.data_type = Some(data_type);
+        self.byte_order = Some(byte_order);
+        self
+    }
+
+    fn data_type(&self) -> DataType {
+        self.data_type.unwrap_or(if self.address.table.is_bit() { DataType::Bool } else { DataType::U16 })
+    }
+
+    fn byte_order(&self) -> ByteOrder {
+        self.byte_order.unwrap_or(ByteOrder::ABCD)
+    }
+}
+
+/// 仿真写入的目标数据
+pub trait SimulationTarget: Clone + Send + Sync + 'static {
+    /// 获取单元ID对应的数据存储
+    fn memory(&self, unit_id: u8) -> Option<MemoryService>;
+}
+
+impl SimulationTarget for MemoryService {
+    /// 单从机数据忽略单元ID
+    fn memory(&self, _unit_id: u8) -> Option<MemoryService> {
+        Some(self.clone())
+    }
+}
+
+impl SimulationTarget for MultiSlaveMemoryService {
+    fn memory(&self, unit_id: u8) -> Option<MemoryService> {
+        self.slave(unit_id)
+    }
+}
+
+struct PointState {
+    unit_id: u8,
+    point: SimulatedPoint,
+    /// 随机游走和计数器的当前值
+    value: Option<f64>,
+}
+
+/// 仿真引擎，按固定周期更新配置的地址
+pub struct Simulator {
+    interval: Duration,
+    points: Vec<PointState>,
+    rng: SimpleRng,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIMULATION_INTERVAL)
+    }
+}
+
+impl Simulator {
+    /// 创建仿真引擎
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            points: Vec::new(),
+            rng: SimpleRng::from_time(),
+        }
+    }
+
+    /// 按配置文件中各从机的`simulation`创建
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let interval = config.simulation_interval_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_SIMULATION_INTERVAL);
+        config.slaves.iter().fold(Self::new(interval), |simulator, slave| {
+            slave.simulation.iter().fold(simulator, |simulator, point| simulator.with_point(slave.unit_id, point.clone()))
+        })
+    }
+
+    /// 设置随机数种子，使随机游走可以复现
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SimpleRng::new(seed);
+        self
+    }
+
+    /// 添加仿真点，单从机服务器忽略`unit_id`
+    pub fn with_point(mut self, unit_id: u8, point: SimulatedPoint) -> Self {
+        self.points.push(PointState { unit_id, point, value: None });
+        self
+    }
+
+    /// 更新周期
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// 按启动后经过的时间更新一次所有点
+    pub fn update<T: SimulationTarget>(&mut self, target: &T, elapsed: Duration) {
+        let t = elapsed.as_secs_f64();
+        for state in &mut self.points {
+            let memory = match target.memory(state.unit_id) {
+                Some(memory) => memory,
+                None => continue,
+            };
+
+            let value = match state.point.signal {
+                Signal::Sine { amplitude, period_secs, offset, phase } => {
+                    offset + amplitude * (TAU * cycle(t, period_secs) + phase).sin()
+                },
+                Signal::Ramp { min, max, period_secs } => min + (max - min) * cycle(t, period_secs).fract(),
+                Signal::Square { low, high, period_secs, duty } => {
+                    if cycle(t, period_secs).fract() < duty { high } else { low }
+                },
+                Signal::RandomWalk { start, step, min, max } => {
+                    let value = match state.value {
+                        Some(value) => (value + self.rng.range_f64(-step, step)).clamp(min, max),
+                        None => start,
+                    };
+                    state.value = Some(value);
+                    value
+                },
+                Signal::Counter { start, step, max } => {
+                    let mut value = state.value.map_or(start, |value| value + step);
+                    if max.is_some_and(|max| value > max) {
+                        value = start;
+                    }
+                    state.value = Some(value);
+                    value
+                },
+                Signal::Copy { source } => match read_value(&memory, source, &state.point) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        log::warn!("Simulation copy from {:?} {} failed: {}", source.table, source.offset, e);
+                        continue;
+                    },
+                },
+            };
+
+            if let Err(e) = write_value(&memory, &state.point, value) {
+                log::warn!("Simulation update of {:?} {} failed: {}", state.point.address.table, state.point.address.offset, e);
+            }
+        }
+    }
+
+    /// 在后台按周期更新，请求关闭后退出
+    pub fn spawn<T: SimulationTarget>(mut self, target: T, shutdown: ShutdownHandle) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let start = tokio::time::Instant::now();
+            let mut interval = tokio::time::interval(self.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    _ = shutdown.requested() => break,
+                    _ = interval.tick() => self.update(&target, start.elapsed()),
+                }
+            }
+        })
+    }
+}
+
+/// 经过的周期数
+fn cycle(t: f64, period_secs: f64) -> f64 {
+    if period_secs > 0.0 { t / period_secs } else { 0.0 }
+}
+
+fn read_value(memory: &MemoryService, source: ModbusAddress, point: &SimulatedPoint) -> Result<f64, ModbusError> {
+    let (table, address, byte_order) = (source.table, source.offset, point.byte_order());
+    match table {
+        Table::Coils => return Ok(memory.get_coil(address) as u8 as f64),
+        Table::DiscreteInputs => return Ok(memory.get_discrete_input(address) as u8 as f64),
+        _ => {},
+    }
+    match point.data_type() {
+        DataType::I16 => memory.get_value::<i16>(table, address, byte_order).map(f64::from),
+        DataType::U32 => memory.get_value::<u32>(table, address, byte_order).map(f64::from),
+        DataType::I32 => memory.get_value::<i32>(table, address, byte_order).map(f64::from),
+        DataType::F32 => memory.get_value::<f32>(table, address, byte_order).map(f64::from),
+        DataType::U48 => u48_from_registers(&memory.get_registers(table, address, 3)?, byte_order).map(|v| v as f64),
+        DataType::I48 => i48_from_registers(&memory.get_registers(table, address, 3)?, byte_order).map(|v| v as f64),
+        DataType::U64 => memory.get_value::<u64>(table, address, byte_order).map(|v| v as f64),
+        DataType::I64 => memory.get_value::<i64>(table, address, byte_order).map(|v| v as f64),
+        DataType::F64 => memory.get_value::<f64>(table, address, byte_order),
+        _ => memory.get_value::<u16>(table, address, byte_order).map(f64::from),
+    }
+}
+
+fn write_value(memory: &MemoryService, point: &SimulatedPoint, value: f64) -> Result<(), ModbusError> {
+    let (table, address, byte_order) = (point.address.table, point.address.offset, point.byte_order());
+    // 整数类型四舍五入后饱和转换
+    match (table, point.data_type()) {
+        (Table::Coils, _) => memory.set_coil(address, value > 0.0),
+        (Table::DiscreteInputs, _) => memory.set_discrete_input(address, value > 0.0),
+        (_, DataType::I16) => memory.set_value(table, address, value.round() as i16, byte_order)?,
+        (_, DataType::U32) => memory.set_value(table, address, value.round() as u32, byte_order)?,
+        (_, DataType::I32) => memory.set_value(table, address, value.round() as i32, byte_order)?,
+        (_, DataType::F32) => memory.set_value(table, address, value as f32, byte_order)?,
+        (_, DataType::U16) => memory.set_value(table, address, value.round() as u16, byte_order)?,
+        (_, DataType::U48) => {
+            let value = value.round().clamp(0.0, U48_MAX as f64) as u64;
+            memory.set_registers(table, address, &u48_to_registers(value, byte_order)?)?
+        },
+        (_, DataType::I48) => {
+            let value = value.round().clamp(I48_MIN as f64, I48_MAX as f64) as i64;
+            memory.set_registers(table, address, &i48_to_registers(value, byte_order)?)?
+        },
+        (_, DataType::U64) => memory.set_value(table, address, value.round() as u64, byte_order)?,
+        (_, DataType::I64) => memory.set_value(table, address, value.round() as i64, byte_order)?,
+        (_, DataType::F64) => memory.set_value(table, address, value, byte_order)?,
+        (_, data_type) => {
+            return Err(ModbusError::ConfigError(format!("{:?} cannot be simulated", data_type)));
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals() {
+        let service = MemoryService::new();
+        let mut simulator = Simulator::new(Duration::from_millis(100))
+            .with_seed(7)
+            .with_point(1, SimulatedPoint::new(Table::HoldingRegisters, 0, Signal::Sine {
+                amplitude: 100.0,
+                period_secs: 4.0,
+                offset: 100.0,
+                phase: 0.0,
+            }))
+            .with_point(1, SimulatedPoint::new(Table::InputRegisters, 0, Signal::Ramp { min: 0.0, max: 10.0, period_secs: 10.0 })
+                .with_type(DataType::F32, ByteOrder::ABCD))
+            .with_point(1, SimulatedPoint::new(Table::Coils, 0, Signal::Square { low: 0.0, high: 1.0, period_secs: 4.0, duty: 0.5 }))
+            .with_point(1, SimulatedPoint::new(Table::HoldingRegisters, 1, Signal::Counter { start: 0.0, step: 1.0, max: Some(2.0) }))
+            .with_point(1, SimulatedPoint::new(Table::HoldingRegisters, 2, Signal::RandomWalk { start: 50.0, step: 5.0, min: 0.0, max: 100.0 }))
+            .with_point(1, SimulatedPoint::new(Table::HoldingRegisters, 3, Signal::Copy { source: ModbusAddress::holding_register(1) }));
+
+        simulator.update(&service, Duration::from_secs(1));
+        assert_eq!(service.get_holding_register(0), 200);
+        assert_eq!(service.get_f32(Table::InputRegisters, 0, ByteOrder::ABCD).unwrap(), 1.0);
+        assert!(service.get_coil(0));
+        assert_eq!(service.get_holding_register(1), 0);
+        assert_eq!(service.get_holding_register(2), 50);
+        assert_eq!(service.get_holding_register(3), 0);
+
+        simulator.update(&service, Duration::from_secs(3));
+        assert_eq!(service.get_holding_register(0), 0);
+        assert!(!service.get_coil(0));
+        assert_eq!(service.get_holding_register(1), 1);
+        assert!((45..=55).contains(&service.get_holding_register(2)));
+        assert_eq!(service.get_holding_register(3), 1);
+
+        simulator.update(&service, Duration::from_secs(4));
+        simulator.update(&service, Duration::from_secs(5));
+        // 计数器超过max后回到start
+        assert_eq!(service.get_holding_register(1), 0);
+    }
+
+    #[test]
+    fn test_simulation_from_config() {
+        let config = ServerConfig::from_json(r#"{
+            "simulation_interval_ms": 250,
+            "slaves": [{
+                "unit_id": 3,
+                "simulation": [
+                    { "table": "holding_registers", "address": 0, "type": "i16", "signal": { "kind": "counter", "start": -1, "step": -1 } },
+                    { "address": "40002", "type": "i16", "signal": { "kind": "copy", "address": "40001" } },
+                    { "table": "holding_registers", "address": 2, "signal": { "kind": "copy", "table": "holding_registers", "address": 1 } }
+                ]
+            }]
+        }"#).unwrap();
+        let service = config.build_multi_slave().unwrap();
+        let mut simulator = Simulator::from_config(&config);
+        assert_eq!(simulator.interval(), Duration::from_millis(250));
+
+        simulator.update(&service, Duration::ZERO);
+        simulator.update(&service, Duration::ZERO);
+        let slave = service.slave(3).unwrap();
+        assert_eq!(slave.get_i16(Table::HoldingRegisters, 0, ByteOrder::ABCD).unwrap(), -2);
+        // 复制源可以用Modicon写法或table加数字地址
+        assert_eq!(slave.get_i16(Table::HoldingRegisters, 1, ByteOrder::ABCD).unwrap(), -2);
+        assert_eq!(slave.get_holding_register(2), (-2i16) as u16);
+    }
+}
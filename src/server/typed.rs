@@ -0,0 +1,127 @@
+use crate::protocol::*;
+use crate::utils::{registers_to_string, string_to_registers, RegisterValue};
+use super::service::MemoryService;
+
+/// 按类型读写寄存器
+///
+/// `table`须为`Table::HoldingRegisters`或`Table::InputRegisters`，
+/// 多寄存器的值一次性写入，读取方不会看到只写了一半的值。
+impl MemoryService {
+    /// 按字节序写入一个数值
+    pub fn set_value<V: RegisterValue>(&self, table: Table, address: u16, value: V, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_registers(table, address, &value.to_registers(byte_order))
+    }
+
+    /// 按字节序读取一个数值
+    pub fn get_value<V: RegisterValue>(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<V, ModbusError> {
+        let registers = self.get_registers(table, address, V::REGISTER_COUNT as u16)?;
+        V::from_registers(&registers, byte_order)
+    }
+
+    /// 写入字符串，占用`register_count`个寄存器，不足部分补0
+    pub fn set_string(&self, table: Table, address: u16, text: &str, register_count: u16, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        let registers = string_to_registers(text, register_count as usize, byte_order)?;
+        self.set_registers(table, address, &registers)
+    }
+
+    /// 读取`register_count`个寄存器中的字符串，去掉末尾的0
+    pub fn get_string(&self, table: Table, address: u16, register_count: u16, byte_order: ByteOrder) -> Result<String, ModbusError> {
+        let registers = self.get_registers(table, address, register_count)?;
+        Ok(registers_to_string(&registers, byte_order))
+    }
+
+    /// 写入i16
+    pub fn set_i16(&self, table: Table, address: u16, value: i16, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取i16
+    pub fn get_i16(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<i16, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入u32（2个寄存器）
+    pub fn set_u32(&self, table: Table, address: u16, value: u32, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取u32（2个寄存器）
+    pub fn get_u32(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<u32, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入i32（2个寄存器）
+    pub fn set_i32(&self, table: Table, address: u16, value: i32, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取i32（2个寄存器）
+    pub fn get_i32(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<i32, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入f32（2个寄存器）
+    pub fn set_f32(&self, table: Table, address: u16, value: f32, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取f32（2个寄存器）
+    pub fn get_f32(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<f32, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入u64（4个寄存器）
+    pub fn set_u64(&self, table: Table, address: u16, value: u64, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取u64（4个寄存器）
+    pub fn get_u64(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<u64, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入i64（4个寄存器）
+    pub fn set_i64(&self, table: Table, address: u16, value: i64, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取i64（4个寄存器）
+    pub fn get_i64(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<i64, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+
+    /// 写入f64（4个寄存器）
+    pub fn set_f64(&self, table: Table, address: u16, value: f64, byte_order: ByteOrder) -> Result<(), ModbusError> {
+        self.set_value(table, address, value, byte_order)
+    }
+
+    /// 读取f64（4个寄存器）
+    pub fn get_f64(&self, table: Table, address: u16, byte_order: ByteOrder) -> Result<f64, ModbusError> {
+        self.get_value(table, address, byte_order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_accessors() {
+        let service = MemoryService::new();
+        service.set_f32(Table::HoldingRegisters, 0, 1.5, ByteOrder::CDAB).unwrap();
+        assert_eq!(service.get_holding_register(0), 0x0000);
+        assert_eq!(service.get_holding_register(1), 0x3FC0);
+        assert_eq!(service.get_f32(Table::HoldingRegisters, 0, ByteOrder::CDAB).unwrap(), 1.5);
+
+        service.set_i64(Table::InputRegisters, 10, -5, ByteOrder::ABCD).unwrap();
+        assert_eq!(service.get_input_register(13), 0xFFFB);
+        assert_eq!(service.get_i64(Table::InputRegisters, 10, ByteOrder::ABCD).unwrap(), -5);
+
+        service.set_string(Table::HoldingRegisters, 20, "PUMP-1", 5, ByteOrder::ABCD).unwrap();
+        assert_eq!(service.get_holding_register(20), 0x5055);
+        assert_eq!(service.get_string(Table::HoldingRegisters, 20, 5, ByteOrder::ABCD).unwrap(), "PUMP-1");
+
+        assert!(service.set_u32(Table::Coils, 0, 1, ByteOrder::ABCD).is_err());
+        assert!(service.set_u32(Table::HoldingRegisters, 0xFFFF, 1, ByteOrder::ABCD).is_err());
+    }
+}
@@ -1,4 +1,30 @@
-use crate::protocol::{ByteOrder, ModbusError};
+use crate::protocol::{ByteOrder, Endianness, ModbusError};
+
+/// 48位整数的最大值
+pub const U48_MAX: u64 = (1 << 48) - 1;
+/// 48位有符号整数的最小值
+pub const I48_MIN: i64 = -(1 << 47);
+/// 48位有符号整数的最大值
+pub const I48_MAX: i64 = (1 << 47) - 1;
+
+/// 字符串末尾的填充字符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringPadding {
+    /// 补0（NUL）
+    #[default]
+    Nul,
+    /// 补空格
+    Space,
+}
+
+impl StringPadding {
+    fn byte(&self) -> u8 {
+        match self {
+            StringPadding::Nul => 0,
+            StringPadding::Space => b' ',
+        }
+    }
+}
 
 /// 数据类型转换工具
 pub struct DataConverter;
@@ -6,7 +32,7 @@ pub struct DataConverter;
 impl DataConverter {
     /// 将字节数组转换为u16数组
     pub fn bytes_to_u16_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<u16>, ModbusError> {
-        if bytes.len() % 2 != 0 {
+        if !bytes.len().is_multiple_of(2) {
             return Err(ModbusError::InvalidDataLength);
         }
         
@@ -30,7 +56,7 @@ impl DataConverter {
     
     /// 将字节数组转换为u32数组
     pub fn bytes_to_u32_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<u32>, ModbusError> {
-        if bytes.len() % 4 != 0 {
+        if !bytes.len().is_multiple_of(4) {
             return Err(ModbusError::InvalidDataLength);
         }
         
@@ -54,7 +80,7 @@ impl DataConverter {
     
     /// 将字节数组转换为f32数组（IEEE 754）
     pub fn bytes_to_f32_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<f32>, ModbusError> {
-        if bytes.len() % 4 != 0 {
+        if !bytes.len().is_multiple_of(4) {
             return Err(ModbusError::InvalidDataLength);
         }
         
@@ -78,66 +104,249 @@ impl DataConverter {
         result
     }
     
-    /// 将字节数组转换为f64数组（IEEE 754）
-    pub fn bytes_to_f64_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<f64>, ModbusError> {
-        if bytes.len() % 8 != 0 {
+    /// 将字节数组转换为u64数组
+    pub fn bytes_to_u64_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<u64>, ModbusError> {
+        if !bytes.len().is_multiple_of(8) {
             return Err(ModbusError::InvalidDataLength);
         }
         
         let mut result = Vec::new();
         for chunk in bytes.chunks(8) {
-            let u64_value = match byte_order {
-                ByteOrder::ABCD => u64::from_be_bytes([
-                    chunk[0], chunk[1], chunk[2], chunk[3],
-                    chunk[4], chunk[5], chunk[6], chunk[7]
-                ]),
-                ByteOrder::DCBA => u64::from_le_bytes([
-                    chunk[0], chunk[1], chunk[2], chunk[3],
-                    chunk[4], chunk[5], chunk[6], chunk[7]
-                ]),
-                ByteOrder::BADC => u64::from_be_bytes([
-                    chunk[1], chunk[0], chunk[3], chunk[2],
-                    chunk[5], chunk[4], chunk[7], chunk[6]
-                ]),
-                ByteOrder::CDAB => u64::from_le_bytes([
-                    chunk[1], chunk[0], chunk[3], chunk[2],
-                    chunk[5], chunk[4], chunk[7], chunk[6]
-                ]),
-            };
-            result.push(f64::from_bits(u64_value));
+            result.push(byte_order.bytes_to_u64(chunk)?);
         }
         
         Ok(result)
     }
     
-    /// 将f64数组转换为字节数组（IEEE 754）
-    pub fn f64_array_to_bytes(values: &[f64], byte_order: ByteOrder) -> Vec<u8> {
+    /// 将u64数组转换为字节数组
+    pub fn u64_array_to_bytes(values: &[u64], byte_order: ByteOrder) -> Vec<u8> {
         let mut result = Vec::new();
         for &value in values {
-            let u64_value = value.to_bits();
-            let bytes = match byte_order {
-                ByteOrder::ABCD => u64_value.to_be_bytes(),
-                ByteOrder::DCBA => u64_value.to_le_bytes(),
-                ByteOrder::BADC => {
-                    let bytes = u64_value.to_be_bytes();
-                    [
-                        bytes[1], bytes[0], bytes[3], bytes[2],
-                        bytes[5], bytes[4], bytes[7], bytes[6]
-                    ]
-                },
-                ByteOrder::CDAB => {
-                    let bytes = u64_value.to_le_bytes();
-                    [
-                        bytes[1], bytes[0], bytes[3], bytes[2],
-                        bytes[5], bytes[4], bytes[7], bytes[6]
-                    ]
-                },
-            };
+            let bytes = byte_order.u64_to_bytes(value);
             result.extend_from_slice(&bytes);
         }
         result
     }
     
+    /// 将字节数组转换为i16数组
+    pub fn bytes_to_i16_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<i16>, ModbusError> {
+        Ok(Self::bytes_to_u16_array(bytes, byte_order)?.into_iter().map(|value| value as i16).collect())
+    }
+    
+    /// 将i16数组转换为字节数组
+    pub fn i16_array_to_bytes(values: &[i16], byte_order: ByteOrder) -> Vec<u8> {
+        let values: Vec<u16> = values.iter().map(|&value| value as u16).collect();
+        Self::u16_array_to_bytes(&values, byte_order)
+    }
+    
+    /// 将字节数组转换为i32数组
+    pub fn bytes_to_i32_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<i32>, ModbusError> {
+        Ok(Self::bytes_to_u32_array(bytes, byte_order)?.into_iter().map(|value| value as i32).collect())
+    }
+    
+    /// 将i32数组转换为字节数组
+    pub fn i32_array_to_bytes(values: &[i32], byte_order: ByteOrder) -> Vec<u8> {
+        let values: Vec<u32> = values.iter().map(|&value| value as u32).collect();
+        Self::u32_array_to_bytes(&values, byte_order)
+    }
+    
+    /// 将字节数组转换为i64数组
+    pub fn bytes_to_i64_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<i64>, ModbusError> {
+        Ok(Self::bytes_to_u64_array(bytes, byte_order)?.into_iter().map(|value| value as i64).collect())
+    }
+    
+    /// 将i64数组转换为字节数组
+    pub fn i64_array_to_bytes(values: &[i64], byte_order: ByteOrder) -> Vec<u8> {
+        let values: Vec<u64> = values.iter().map(|&value| value as u64).collect();
+        Self::u64_array_to_bytes(&values, byte_order)
+    }
+    
+    /// 将字节数组转换为48位无符号整数数组（每个值3个寄存器），常见于电能累计值
+    pub fn bytes_to_u48_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<u64>, ModbusError> {
+        if !bytes.len().is_multiple_of(6) {
+            return Err(ModbusError::InvalidDataLength);
+        }
+        
+        let mut result = Vec::new();
+        for chunk in bytes.chunks(6) {
+            result.push(byte_order.bytes_to_u48(chunk)?);
+        }
+        
+        Ok(result)
+    }
+    
+    /// 将48位无符号整数数组转换为字节数组，超过48位的值返回错误
+    pub fn u48_array_to_bytes(values: &[u64], byte_order: ByteOrder) -> Result<Vec<u8>, ModbusError> {
+        let mut result = Vec::new();
+        for &value in values {
+            if value > U48_MAX {
+                return Err(ModbusError::InvalidValue(format!("{} does not fit in 48 bits", value)));
+            }
+            result.extend_from_slice(&byte_order.u48_to_bytes(value));
+        }
+        Ok(result)
+    }
+    
+    /// 将字节数组转换为48位有符号整数数组（补码）
+    pub fn bytes_to_i48_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<i64>, ModbusError> {
+        Ok(Self::bytes_to_u48_array(bytes, byte_order)?
+            .into_iter()
+            .map(|value| ((value << 16) as i64) >> 16)
+            .collect())
+    }
+    
+    /// 将48位有符号整数数组转换为字节数组，超出范围的值返回错误
+    pub fn i48_array_to_bytes(values: &[i64], byte_order: ByteOrder) -> Result<Vec<u8>, ModbusError> {
+        let mut unsigned = Vec::with_capacity(values.len());
+        for &value in values {
+            if !(I48_MIN..=I48_MAX).contains(&value) {
+                return Err(ModbusError::InvalidValue(format!("{} does not fit in 48 bits", value)));
+            }
+            unsigned.push(value as u64 & U48_MAX);
+        }
+        Self::u48_array_to_bytes(&unsigned, byte_order)
+    }
+    
+    /// 解码压缩BCD（每4位一个十进制数字），非法数字返回错误
+    pub fn bcd_to_u32(bcd: u32) -> Result<u32, ModbusError> {
+        let mut value = 0;
+        for shift in (0..32).step_by(4).rev() {
+            let digit = (bcd >> shift) & 0xF;
+            if digit > 9 {
+                return Err(ModbusError::InvalidValue(format!("0x{:X} is not a valid BCD value", bcd)));
+            }
+            value = value * 10 + digit;
+        }
+        Ok(value)
+    }
+    
+    /// 编码为压缩BCD，最大99999999
+    pub fn u32_to_bcd(value: u32) -> Result<u32, ModbusError> {
+        if value > 99_999_999 {
+            return Err(ModbusError::InvalidValue(format!("{} has more than 8 BCD digits", value)));
+        }
+        let mut bcd = 0;
+        let mut rest = value;
+        for shift in (0..32).step_by(4) {
+            bcd |= (rest % 10) << shift;
+            rest /= 10;
+        }
+        Ok(bcd)
+    }
+    
+    /// 解码一个寄存器中的4位压缩BCD
+    pub fn bcd_to_u16(bcd: u16) -> Result<u16, ModbusError> {
+        Self::bcd_to_u32(bcd as u32).map(|value| value as u16)
+    }
+    
+    /// 编码为一个寄存器中的4位压缩BCD，最大9999
+    pub fn u16_to_bcd(value: u16) -> Result<u16, ModbusError> {
+        if value > 9999 {
+            return Err(ModbusError::InvalidValue(format!("{} has more than 4 BCD digits", value)));
+        }
+        Self::u32_to_bcd(value as u32).map(|bcd| bcd as u16)
+    }
+    
+    /// 读取寄存器中从第`offset`位开始的`width`位（第0位为最低位）
+    pub fn get_bits(word: u16, offset: u8, width: u8) -> u16 {
+        if offset >= 16 || width == 0 {
+            return 0;
+        }
+        let mask = bit_mask(width.min(16 - offset));
+        (word >> offset) & mask
+    }
+    
+    /// 设置寄存器中从第`offset`位开始的`width`位，其他位不变；值超出位宽时返回错误
+    pub fn set_bits(word: u16, offset: u8, width: u8, value: u16) -> Result<u16, ModbusError> {
+        if width == 0 || offset as u32 + width as u32 > 16 {
+            return Err(ModbusError::InvalidValue(format!("Bit field {}+{} exceeds 16 bits", offset, width)));
+        }
+        let mask = bit_mask(width);
+        if value & !mask != 0 {
+            return Err(ModbusError::InvalidValue(format!("{} does not fit in {} bits", value, width)));
+        }
+        Ok((word & !(mask << offset)) | (value << offset))
+    }
+    
+    /// 读取寄存器中的一位
+    pub fn get_bit(word: u16, bit: u8) -> bool {
+        Self::get_bits(word, bit, 1) != 0
+    }
+    
+    /// 设置寄存器中的一位
+    pub fn set_bit(word: u16, bit: u8, value: bool) -> u16 {
+        if bit >= 16 {
+            return word;
+        }
+        if value {
+            word | (1 << bit)
+        } else {
+            word & !(1 << bit)
+        }
+    }
+    
+    /// 将字节数组转换为f64数组（IEEE 754）
+    pub fn bytes_to_f64_array(bytes: &[u8], byte_order: ByteOrder) -> Result<Vec<f64>, ModbusError> {
+        Ok(Self::bytes_to_u64_array(bytes, byte_order)?
+            .into_iter()
+            .map(f64::from_bits)
+            .collect())
+    }
+    
+    /// 将f64数组转换为字节数组（IEEE 754）
+    pub fn f64_array_to_bytes(values: &[f64], byte_order: ByteOrder) -> Vec<u8> {
+        let bits: Vec<u64> = values.iter().map(|value| value.to_bits()).collect();
+        Self::u64_array_to_bytes(&bits, byte_order)
+    }
+    
+    /// 将字符串转换为寄存器字节，按`byte_len`补0；寄存器内字节小端（BADC/DCBA）时交换每个寄存器内的两个字节
+    pub fn string_to_bytes(text: &str, byte_len: usize, byte_order: ByteOrder) -> Result<Vec<u8>, ModbusError> {
+        Self::string_to_bytes_with_padding(text, byte_len, byte_order, StringPadding::Nul)
+    }
+    
+    /// 将字符串转换为寄存器字节，按`byte_len`用指定字符填充
+    pub fn string_to_bytes_with_padding(text: &str, byte_len: usize, byte_order: ByteOrder, padding: StringPadding) -> Result<Vec<u8>, ModbusError> {
+        if text.len() > byte_len {
+            return Err(ModbusError::InvalidDataLength);
+        }
+        
+        let mut result = text.as_bytes().to_vec();
+        result.resize(byte_len, padding.byte());
+        if byte_order.bytes_in_word() == Endianness::Little {
+            result.chunks_mut(2).for_each(|pair| pair.reverse());
+        }
+        Ok(result)
+    }
+    
+    /// 将寄存器字节转换为字符串，去掉末尾的0
+    pub fn bytes_to_string(bytes: &[u8], byte_order: ByteOrder) -> String {
+        let mut bytes = bytes.to_vec();
+        if byte_order.bytes_in_word() == Endianness::Little {
+            bytes.chunks_mut(2).for_each(|pair| pair.reverse());
+        }
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+    
+    /// 将寄存器字节转换为字符串，在第一个0处截断，空格填充时再去掉末尾空格
+    pub fn bytes_to_string_with_padding(bytes: &[u8], byte_order: ByteOrder, padding: StringPadding) -> String {
+        let mut bytes = bytes.to_vec();
+        if byte_order.bytes_in_word() == Endianness::Little {
+            bytes.chunks_mut(2).for_each(|pair| pair.reverse());
+        }
+        if let Some(end) = bytes.iter().position(|&byte| byte == 0) {
+            bytes.truncate(end);
+        }
+        if padding == StringPadding::Space {
+            while bytes.last() == Some(&b' ') {
+                bytes.pop();
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+    
     /// 将线圈字节转换为布尔数组
     pub fn bytes_to_bool_array(bytes: &[u8], bit_count: usize) -> Vec<bool> {
         let mut result = Vec::new();
@@ -174,6 +383,11 @@ impl DataConverter {
     }
 }
 
+/// 低`width`位全为1的掩码
+fn bit_mask(width: u8) -> u16 {
+    if width >= 16 { u16::MAX } else { (1 << width) - 1 }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,6 +420,7 @@ mod tests {
     }
     
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_bytes_to_f32_array() {
         let bytes = [0x40, 0x49, 0x0F, 0xDB]; // 3.14159 in IEEE 754
         
@@ -223,4 +438,144 @@ mod tests {
         let result = DataConverter::bool_array_to_bytes(&bools);
         assert_eq!(result, vec![0b01010101, 0b00000001]);
     }
+
+    #[test]
+    fn test_signed_round_trip() {
+        for byte_order in [ByteOrder::ABCD, ByteOrder::DCBA, ByteOrder::BADC, ByteOrder::CDAB] {
+            let values = [i16::MIN, -1, 0, 1234, i16::MAX];
+            let bytes = DataConverter::i16_array_to_bytes(&values, byte_order);
+            assert_eq!(DataConverter::bytes_to_i16_array(&bytes, byte_order).unwrap(), values);
+            
+            let values = [i32::MIN, -123456, 0, 987654321, i32::MAX];
+            let bytes = DataConverter::i32_array_to_bytes(&values, byte_order);
+            assert_eq!(DataConverter::bytes_to_i32_array(&bytes, byte_order).unwrap(), values);
+            
+            let values = [i64::MIN, -1, 0x0102_0304_0506_0708, i64::MAX];
+            let bytes = DataConverter::i64_array_to_bytes(&values, byte_order);
+            assert_eq!(DataConverter::bytes_to_i64_array(&bytes, byte_order).unwrap(), values);
+            
+            let values = [0, 1, 0x1234_5678_9ABC, U48_MAX];
+            let bytes = DataConverter::u48_array_to_bytes(&values, byte_order).unwrap();
+            assert_eq!(DataConverter::bytes_to_u48_array(&bytes, byte_order).unwrap(), values);
+            
+            let values = [-(1 << 47), -1, 0, (1 << 47) - 1];
+            let bytes = DataConverter::i48_array_to_bytes(&values, byte_order).unwrap();
+            assert_eq!(DataConverter::bytes_to_i48_array(&bytes, byte_order).unwrap(), values);
+        }
+        
+        // 48位：CDAB为低位字在前
+        let bytes = DataConverter::u48_array_to_bytes(&[0x1122_3344_5566], ByteOrder::CDAB).unwrap();
+        assert_eq!(bytes, vec![0x55, 0x66, 0x33, 0x44, 0x11, 0x22]);
+        assert!(DataConverter::u48_array_to_bytes(&[U48_MAX + 1], ByteOrder::ABCD).is_err());
+        assert!(DataConverter::i48_array_to_bytes(&[1 << 47], ByteOrder::ABCD).is_err());
+    }
+    
+    #[test]
+    fn test_bcd() {
+        assert_eq!(DataConverter::bcd_to_u16(0x1234).unwrap(), 1234);
+        assert_eq!(DataConverter::u16_to_bcd(9876).unwrap(), 0x9876);
+        assert_eq!(DataConverter::bcd_to_u32(0x1234_5678).unwrap(), 12345678);
+        assert_eq!(DataConverter::u32_to_bcd(90_210).unwrap(), 0x0009_0210);
+        for value in [0, 7, 99, 4321, 9999] {
+            assert_eq!(DataConverter::bcd_to_u16(DataConverter::u16_to_bcd(value).unwrap()).unwrap(), value);
+        }
+        assert!(DataConverter::bcd_to_u16(0x12A4).is_err());
+        assert!(DataConverter::u16_to_bcd(10000).is_err());
+    }
+    
+    #[test]
+    fn test_string_padding() {
+        let bytes = DataConverter::string_to_bytes_with_padding("AB1", 6, ByteOrder::ABCD, StringPadding::Space).unwrap();
+        assert_eq!(bytes, b"AB1   ".to_vec());
+        assert_eq!(DataConverter::bytes_to_string_with_padding(&bytes, ByteOrder::ABCD, StringPadding::Space), "AB1");
+        
+        // 交换寄存器内字节
+        let bytes = DataConverter::string_to_bytes_with_padding("AB1", 4, ByteOrder::BADC, StringPadding::Nul).unwrap();
+        assert_eq!(bytes, vec![b'B', b'A', 0, b'1']);
+        assert_eq!(DataConverter::bytes_to_string_with_padding(&bytes, ByteOrder::BADC, StringPadding::Nul), "AB1");
+        
+        // 在第一个NUL处截断，UTF-8按字节计算长度
+        assert_eq!(DataConverter::bytes_to_string_with_padding(b"OK\0junk", ByteOrder::ABCD, StringPadding::Nul), "OK");
+        let bytes = DataConverter::string_to_bytes("温度", 6, ByteOrder::ABCD).unwrap();
+        assert_eq!(DataConverter::bytes_to_string(&bytes, ByteOrder::ABCD), "温度");
+        assert!(DataConverter::string_to_bytes("温度", 5, ByteOrder::ABCD).is_err());
+    }
+    
+    #[test]
+    fn test_bitfields() {
+        let word = 0b1011_0110_0000_0001;
+        assert!(DataConverter::get_bit(word, 0));
+        assert!(!DataConverter::get_bit(word, 1));
+        assert_eq!(DataConverter::get_bits(word, 9, 3), 0b011);
+        assert_eq!(DataConverter::get_bits(word, 12, 4), 0b1011);
+        
+        let word = DataConverter::set_bits(word, 9, 3, 0b101).unwrap();
+        assert_eq!(DataConverter::get_bits(word, 9, 3), 0b101);
+        assert_eq!(DataConverter::get_bits(word, 12, 4), 0b1011);
+        assert_eq!(DataConverter::set_bit(word, 0, false) & 1, 0);
+        assert!(DataConverter::set_bits(word, 9, 3, 8).is_err());
+        assert!(DataConverter::set_bits(word, 14, 3, 0).is_err());
+    }
+    
+    #[test]
+    fn test_byte_order_parts() {
+        for byte_order in [ByteOrder::ABCD, ByteOrder::DCBA, ByteOrder::BADC, ByteOrder::CDAB] {
+            assert_eq!(ByteOrder::from_parts(byte_order.bytes_in_word(), byte_order.word_order()), byte_order);
+        }
+        assert_eq!(ByteOrder::from_parts(Endianness::Big, Endianness::Little), ByteOrder::CDAB);
+        assert_eq!(ByteOrder::from_parts(Endianness::Little, Endianness::Big), ByteOrder::BADC);
+        
+        // 单个寄存器没有字序之分
+        assert_eq!(ByteOrder::CDAB.u16_to_bytes(0x1234), [0x12, 0x34]);
+        assert_eq!(ByteOrder::BADC.u16_to_bytes(0x1234), [0x34, 0x12]);
+    }
+    
+    /// 四种字节序的对照表
+    ///
+    /// 表中的寄存器值是按IEEE 754编码自行推导的，并非摘自厂商文档；编码本身由`to_bits`核对。
+    /// 名称对应关系：Big-endian = ABCD，Little-endian = DCBA，Big-endian byte swap = BADC，
+    /// Little-endian byte swap = CDAB。
+    #[test]
+    fn test_byte_order_tables() {
+        // 123456.0f32 = 0x47F12000
+        assert_eq!(123456.0f32.to_bits(), 0x47F1_2000);
+        let f32_table: [(ByteOrder, [u16; 2]); 4] = [
+            (ByteOrder::ABCD, [0x47F1, 0x2000]),
+            (ByteOrder::DCBA, [0x0020, 0xF147]),
+            (ByteOrder::BADC, [0xF147, 0x0020]),
+            (ByteOrder::CDAB, [0x2000, 0x47F1]),
+        ];
+        for (byte_order, registers) in f32_table {
+            let bytes = DataConverter::u16_array_to_bytes(&registers, ByteOrder::ABCD);
+            assert_eq!(DataConverter::bytes_to_f32_array(&bytes, byte_order).unwrap(), vec![123456.0], "{:?}", byte_order);
+            assert_eq!(DataConverter::f32_array_to_bytes(&[123456.0], byte_order), bytes, "{:?}", byte_order);
+        }
+        
+        // 0x0102_0304_0506（3个寄存器）
+        let u48_table: [(ByteOrder, [u16; 3]); 4] = [
+            (ByteOrder::ABCD, [0x0102, 0x0304, 0x0506]),
+            (ByteOrder::DCBA, [0x0605, 0x0403, 0x0201]),
+            (ByteOrder::BADC, [0x0201, 0x0403, 0x0605]),
+            (ByteOrder::CDAB, [0x0506, 0x0304, 0x0102]),
+        ];
+        for (byte_order, registers) in u48_table {
+            let bytes = DataConverter::u16_array_to_bytes(&registers, ByteOrder::ABCD);
+            assert_eq!(DataConverter::bytes_to_u48_array(&bytes, byte_order).unwrap(), vec![0x0102_0304_0506], "{:?}", byte_order);
+            assert_eq!(DataConverter::u48_array_to_bytes(&[0x0102_0304_0506], byte_order).unwrap(), bytes, "{:?}", byte_order);
+        }
+        
+        // 123456789.0f64 = 0x419D6F3454000000
+        assert_eq!(123456789.0f64.to_bits(), 0x419D_6F34_5400_0000);
+        let f64_table: [(ByteOrder, [u16; 4]); 4] = [
+            (ByteOrder::ABCD, [0x419D, 0x6F34, 0x5400, 0x0000]),
+            (ByteOrder::DCBA, [0x0000, 0x0054, 0x346F, 0x9D41]),
+            (ByteOrder::BADC, [0x9D41, 0x346F, 0x0054, 0x0000]),
+            (ByteOrder::CDAB, [0x0000, 0x5400, 0x6F34, 0x419D]),
+        ];
+        for (byte_order, registers) in f64_table {
+            let bytes = DataConverter::u16_array_to_bytes(&registers, ByteOrder::ABCD);
+            assert_eq!(DataConverter::bytes_to_f64_array(&bytes, byte_order).unwrap(), vec![123456789.0], "{:?}", byte_order);
+            assert_eq!(DataConverter::f64_array_to_bytes(&[123456789.0], byte_order), bytes, "{:?}", byte_order);
+        }
+    }
 }
@@ -1,3 +1,11 @@
 pub mod data;
+pub mod register_value;
+pub mod register_block;
+pub mod random;
+pub mod value;
 
 pub use data::*;
+pub use register_value::*;
+pub use register_block::*;
+pub use random::*;
+pub use value::*;
@@ -0,0 +1,49 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 轻量伪随机数生成器（xorshift64*），用于仿真和故障注入
+///
+/// 相同的种子产生相同的序列，便于复现测试场景；不适用于加密用途。
+#[derive(Debug, Clone)]
+pub struct SimpleRng {
+    state: u64,
+}
+
+impl SimpleRng {
+    /// 使用指定种子创建
+    pub fn new(seed: u64) -> Self {
+        // 状态不能为0
+        Self { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    /// 使用当前时间作为种子创建
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    /// 下一个u64随机数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// [0, 1)之间的随机数
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// [min, max)之间的随机数
+    pub fn range_f64(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// 以概率`probability`返回true
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
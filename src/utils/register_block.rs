@@ -0,0 +1,92 @@
+use crate::protocol::ModbusError;
+
+pub use modbus_derive::ModbusRegisters;
+
+/// 与一段连续寄存器相互转换的结构体，通常由`#[derive(ModbusRegisters)]`生成
+///
+/// 字段按各自的偏移、类型、字节序和缩放编码；写入时字段之间的空隙补0。
+pub trait ModbusRegisters: Sized {
+    /// 占用的寄存器数
+    const REGISTER_COUNT: u16;
+
+    /// 从寄存器解码，寄存器数不能少于`REGISTER_COUNT`
+    fn from_registers(registers: &[u16]) -> Result<Self, ModbusError>;
+
+    /// 编码为`REGISTER_COUNT`个寄存器，缩放后超出原始类型范围时返回`InvalidValue`
+    fn to_registers(&self) -> Result<Vec<u16>, ModbusError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, ModbusRegisters)]
+    #[modbus(byte_order = "CDAB")]
+    struct InverterStatus {
+        #[modbus(offset = 0)]
+        state: u16,
+        #[modbus(offset = 1, bit = 0)]
+        running: bool,
+        #[modbus(offset = 1, bit = 3)]
+        fault: bool,
+        #[modbus(offset = 2, ty = "i16", scale = 0.1)]
+        temperature: f64,
+        #[modbus(offset = 3)]
+        energy: u32,
+        #[modbus(byte_order = "ABCD")]
+        power: f32,
+        #[modbus(offset = 10, registers = 4)]
+        serial: String,
+    }
+
+    #[test]
+    fn test_derive_registers() {
+        assert_eq!(InverterStatus::REGISTER_COUNT, 14);
+
+        let mut registers = vec![0u16; 14];
+        registers[0] = 4;
+        registers[1] = 0b1001;
+        registers[2] = (-125i16) as u16;
+        registers[3..5].copy_from_slice(&[0x5678, 0x1234]);
+        registers[5..7].copy_from_slice(&[0x47F1, 0x2000]);
+        registers[10..14].copy_from_slice(&[0x4142, 0x3132, 0, 0]);
+
+        let status = InverterStatus::from_registers(&registers).unwrap();
+        assert_eq!(status, InverterStatus {
+            state: 4,
+            running: true,
+            fault: true,
+            temperature: -12.5,
+            energy: 0x1234_5678,
+            power: 123456.0,
+            serial: "AB12".to_string(),
+        });
+        assert_eq!(status.to_registers().unwrap(), registers);
+
+        // 缩放后超出原始类型范围
+        let overflow = InverterStatus { temperature: 5000.0, ..status };
+        assert!(matches!(overflow.to_registers(), Err(ModbusError::InvalidValue(_))));
+        assert!(matches!(InverterStatus::from_registers(&registers[..10]), Err(ModbusError::InvalidDataLength)));
+    }
+
+    #[test]
+    fn test_derive_scaled_decode() {
+        #[derive(Debug, PartialEq, ModbusRegisters)]
+        struct Scaled {
+            #[modbus(ty = "u16", scale = 0.1)]
+            rounded: u16,
+            #[modbus(ty = "i32", scale = 100)]
+            narrowed: i16,
+            #[modbus(ty = "u16")]
+            signed: i16,
+        }
+
+        // 0.1的换算结果不是精确值，按四舍五入而不是截断
+        let scaled = Scaled::from_registers(&[29, 0, 300, 0x7FFF]).unwrap();
+        assert_eq!(scaled, Scaled { rounded: 3, narrowed: 30000, signed: i16::MAX });
+
+        // 超出字段类型范围时报错，而不是饱和
+        assert!(matches!(Scaled::from_registers(&[29, 0, 400, 0]), Err(ModbusError::InvalidValue(_))));
+        assert!(matches!(Scaled::from_registers(&[29, 0, 0, 0x8000]), Err(ModbusError::InvalidValue(_))));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::protocol::{ByteOrder, ModbusError};
+use super::data::DataConverter;
+
+/// 可以存放在连续寄存器中的数值类型
+pub trait RegisterValue: Sized + Copy {
+    /// 占用的寄存器数
+    const REGISTER_COUNT: usize;
+
+    /// 按字节序编码为寄存器
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16>;
+
+    /// 按字节序从寄存器解码，寄存器数量必须等于`REGISTER_COUNT`
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError>;
+}
+
+/// 寄存器按协议顺序（高字节在前）展开为字节
+fn registers_to_bytes(registers: &[u16], expected: usize) -> Result<Vec<u8>, ModbusError> {
+    if registers.len() != expected {
+        return Err(ModbusError::InvalidDataLength);
+    }
+    Ok(DataConverter::u16_array_to_bytes(registers, ByteOrder::ABCD))
+}
+
+/// 字节按协议顺序（高字节在前）组合为寄存器
+fn bytes_to_registers(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()
+}
+
+impl RegisterValue for u16 {
+    const REGISTER_COUNT: usize = 1;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        bytes_to_registers(&byte_order.u16_to_bytes(self))
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        byte_order.bytes_to_u16(&registers_to_bytes(registers, Self::REGISTER_COUNT)?)
+    }
+}
+
+impl RegisterValue for i16 {
+    const REGISTER_COUNT: usize = 1;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        (self as u16).to_registers(byte_order)
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        u16::from_registers(registers, byte_order).map(|value| value as i16)
+    }
+}
+
+impl RegisterValue for u32 {
+    const REGISTER_COUNT: usize = 2;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        bytes_to_registers(&byte_order.u32_to_bytes(self))
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        byte_order.bytes_to_u32(&registers_to_bytes(registers, Self::REGISTER_COUNT)?)
+    }
+}
+
+impl RegisterValue for i32 {
+    const REGISTER_COUNT: usize = 2;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        (self as u32).to_registers(byte_order)
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        u32::from_registers(registers, byte_order).map(|value| value as i32)
+    }
+}
+
+impl RegisterValue for f32 {
+    const REGISTER_COUNT: usize = 2;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        self.to_bits().to_registers(byte_order)
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        u32::from_registers(registers, byte_order).map(f32::from_bits)
+    }
+}
+
+impl RegisterValue for u64 {
+    const REGISTER_COUNT: usize = 4;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        bytes_to_registers(&DataConverter::u64_array_to_bytes(&[self], byte_order))
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        let bytes = registers_to_bytes(registers, Self::REGISTER_COUNT)?;
+        Ok(DataConverter::bytes_to_u64_array(&bytes, byte_order)?[0])
+    }
+}
+
+impl RegisterValue for i64 {
+    const REGISTER_COUNT: usize = 4;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        (self as u64).to_registers(byte_order)
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        u64::from_registers(registers, byte_order).map(|value| value as i64)
+    }
+}
+
+impl RegisterValue for f64 {
+    const REGISTER_COUNT: usize = 4;
+
+    fn to_registers(self, byte_order: ByteOrder) -> Vec<u16> {
+        self.to_bits().to_registers(byte_order)
+    }
+
+    fn from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<Self, ModbusError> {
+        u64::from_registers(registers, byte_order).map(f64::from_bits)
+    }
+}
+
+/// 可以与f64相互换算的数值类型，用于缩放后的字段
+pub trait RegisterNumber: RegisterValue {
+    /// 转换为f64
+    fn to_f64(self) -> f64;
+
+    /// 从f64转换，整数类型四舍五入，超出范围时返回None
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+macro_rules! impl_register_number_int {
+    ($($ty:ty),*) => {
+        $(
+            impl RegisterNumber for $ty {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Option<Self> {
+                    let value = value.round();
+                    // u64/i64的MAX转换为f64时向上舍入为2^64/2^63，须用严格小于比较
+                    (value.is_finite() && value >= <$ty>::MIN as f64 && value < <$ty>::MAX as f64 + 1.0).then_some(value as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_register_number_int!(u16, i16, u32, i32, u64, i64);
+
+impl RegisterNumber for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        (value.is_finite() && value.abs() <= f32::MAX as f64).then_some(value as f32)
+    }
+}
+
+impl RegisterNumber for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        value.is_finite().then_some(value)
+    }
+}
+
+/// 将48位无符号整数编码为3个寄存器，超出48位时返回错误
+pub fn u48_to_registers(value: u64, byte_order: ByteOrder) -> Result<Vec<u16>, ModbusError> {
+    Ok(bytes_to_registers(&DataConverter::u48_array_to_bytes(&[value], byte_order)?))
+}
+
+/// 从3个寄存器解码48位无符号整数
+pub fn u48_from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<u64, ModbusError> {
+    Ok(DataConverter::bytes_to_u48_array(&registers_to_bytes(registers, 3)?, byte_order)?[0])
+}
+
+/// 将48位有符号整数编码为3个寄存器（补码），超出范围时返回错误
+pub fn i48_to_registers(value: i64, byte_order: ByteOrder) -> Result<Vec<u16>, ModbusError> {
+    Ok(bytes_to_registers(&DataConverter::i48_array_to_bytes(&[value], byte_order)?))
+}
+
+/// 从3个寄存器解码48位有符号整数
+pub fn i48_from_registers(registers: &[u16], byte_order: ByteOrder) -> Result<i64, ModbusError> {
+    Ok(DataConverter::bytes_to_i48_array(&registers_to_bytes(registers, 3)?, byte_order)?[0])
+}
+
+/// 将字符串编码为`register_count`个寄存器，不足部分补0
+pub fn string_to_registers(text: &str, register_count: usize, byte_order: ByteOrder) -> Result<Vec<u16>, ModbusError> {
+    Ok(bytes_to_registers(&DataConverter::string_to_bytes(text, register_count * 2, byte_order)?))
+}
+
+/// 从寄存器解码字符串，去掉末尾的0
+pub fn registers_to_string(registers: &[u16], byte_order: ByteOrder) -> String {
+    DataConverter::bytes_to_string(&DataConverter::u16_array_to_bytes(registers, ByteOrder::ABCD), byte_order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::data::{I48_MAX, I48_MIN, U48_MAX};
+
+    #[test]
+    fn test_register_value_round_trip() {
+        assert_eq!(1.5f32.to_registers(ByteOrder::ABCD), vec![0x3FC0, 0x0000]);
+        assert_eq!(1.5f32.to_registers(ByteOrder::CDAB), vec![0x0000, 0x3FC0]);
+        assert_eq!(0x12345678u32.to_registers(ByteOrder::BADC), vec![0x3412, 0x7856]);
+        assert_eq!(0x0102030405060708u64.to_registers(ByteOrder::CDAB), vec![0x0708, 0x0506, 0x0304, 0x0102]);
+
+        for order in [ByteOrder::ABCD, ByteOrder::DCBA, ByteOrder::BADC, ByteOrder::CDAB] {
+            assert_eq!(i32::from_registers(&(-123456i32).to_registers(order), order).unwrap(), -123456);
+            assert_eq!(f64::from_registers(&(-2.25f64).to_registers(order), order).unwrap(), -2.25);
+            assert_eq!(i64::from_registers(&i64::MIN.to_registers(order), order).unwrap(), i64::MIN);
+            assert_eq!(i16::from_registers(&(-2i16).to_registers(order), order).unwrap(), -2);
+        }
+        assert!(u32::from_registers(&[1], ByteOrder::ABCD).is_err());
+
+        let registers = string_to_registers("ABC", 3, ByteOrder::BADC).unwrap();
+        assert_eq!(registers, vec![0x4241, 0x0043, 0x0000]);
+        assert_eq!(registers_to_string(&registers, ByteOrder::BADC), "ABC");
+        assert!(string_to_registers("ABCDEFG", 3, ByteOrder::ABCD).is_err());
+    }
+
+    #[test]
+    fn test_48_bit_registers() {
+        assert_eq!(u48_to_registers(0x0102_0304_0506, ByteOrder::ABCD).unwrap(), vec![0x0102, 0x0304, 0x0506]);
+        assert_eq!(u48_to_registers(0x0102_0304_0506, ByteOrder::CDAB).unwrap(), vec![0x0506, 0x0304, 0x0102]);
+        assert_eq!(u48_to_registers(0x0102_0304_0506, ByteOrder::BADC).unwrap(), vec![0x0201, 0x0403, 0x0605]);
+        assert_eq!(u48_to_registers(0x0102_0304_0506, ByteOrder::DCBA).unwrap(), vec![0x0605, 0x0403, 0x0201]);
+        assert!(u48_to_registers(U48_MAX + 1, ByteOrder::ABCD).is_err());
+        assert_eq!(i48_to_registers(-2, ByteOrder::ABCD).unwrap(), vec![0xFFFF, 0xFFFF, 0xFFFE]);
+        assert!(i48_to_registers(I48_MAX + 1, ByteOrder::ABCD).is_err());
+
+        for order in [ByteOrder::ABCD, ByteOrder::DCBA, ByteOrder::BADC, ByteOrder::CDAB] {
+            assert_eq!(u48_from_registers(&u48_to_registers(U48_MAX, order).unwrap(), order).unwrap(), U48_MAX);
+            assert_eq!(i48_from_registers(&i48_to_registers(I48_MIN, order).unwrap(), order).unwrap(), I48_MIN);
+            assert_eq!(i48_from_registers(&i48_to_registers(-123456789, order).unwrap(), order).unwrap(), -123456789);
+        }
+        assert!(u48_from_registers(&[0, 0], ByteOrder::ABCD).is_err());
+    }
+
+    #[test]
+    fn test_from_f64_bounds() {
+        assert_eq!(u16::from_f64(65534.6), Some(u16::MAX));
+        assert_eq!(u16::from_f64(65535.5), None);
+        assert_eq!(i16::from_f64(-32768.4), Some(i16::MIN));
+        assert_eq!(u32::from_f64(-0.4), Some(0));
+        assert_eq!(u32::from_f64(-0.6), None);
+        // 2^64和2^63刚好超出范围
+        assert_eq!(u64::from_f64(18446744073709551616.0), None);
+        assert_eq!(u64::from_f64(18446744073709549568.0), Some(18446744073709549568));
+        assert_eq!(i64::from_f64(9223372036854775808.0), None);
+        assert_eq!(i64::from_f64(-9223372036854775808.0), Some(i64::MIN));
+        assert_eq!(i32::from_f64(f64::NAN), None);
+        assert_eq!(f32::from_f64(1e39), None);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::protocol::*;
+use super::register_value::{i48_from_registers, registers_to_string, u48_from_registers, RegisterValue};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 点的数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataType {
+    /// 位，用于线圈和离散输入
+    Bool,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    /// 48位无符号整数，占3个寄存器，常见于电能表累计量
+    U48,
+    /// 48位有符号整数，占3个寄存器
+    I48,
+    U64,
+    I64,
+    F64,
+    /// 字符串，每个寄存器两个字符，长度由`length`（寄存器数）指定
+    String,
+}
+
+impl DataType {
+    /// 单个值占用的地址数，字符串为`length`个寄存器
+    pub fn width(&self, length: Option<u16>) -> u16 {
+        match self {
+            DataType::Bool | DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            DataType::U48 | DataType::I48 => 3,
+            DataType::U64 | DataType::I64 | DataType::F64 => 4,
+            DataType::String => length.unwrap_or(1),
+        }
+    }
+
+    /// 按数据类型解码，位的值以寄存器0/1表示；48位整数解码为`U64`/`I64`；字符串使用全部寄存器
+    pub fn decode(&self, registers: &[u16], byte_order: ByteOrder) -> Result<Value, ModbusError> {
+        match self {
+            DataType::Bool => registers.first().map(|&value| Value::Bool(value != 0)).ok_or(ModbusError::InvalidDataLength),
+            DataType::U16 => u16::from_registers(registers, byte_order).map(Value::U16),
+            DataType::I16 => i16::from_registers(registers, byte_order).map(Value::I16),
+            DataType::U32 => u32::from_registers(registers, byte_order).map(Value::U32),
+            DataType::I32 => i32::from_registers(registers, byte_order).map(Value::I32),
+            DataType::F32 => f32::from_registers(registers, byte_order).map(Value::F32),
+            DataType::U48 => u48_from_registers(registers, byte_order).map(Value::U64),
+            DataType::I48 => i48_from_registers(registers, byte_order).map(Value::I64),
+            DataType::U64 => u64::from_registers(registers, byte_order).map(Value::U64),
+            DataType::I64 => i64::from_registers(registers, byte_order).map(Value::I64),
+            DataType::F64 => f64::from_registers(registers, byte_order).map(Value::F64),
+            DataType::String => Ok(Value::String(registers_to_string(registers, byte_order))),
+        }
+    }
+}
+
+/// 解码后的点值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    String(String),
+}
+
+impl Value {
+    /// 数值类型转换为f64，位为0/1，字符串返回None；超过2^53的64位整数会损失精度
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            Value::U16(value) => Some(f64::from(*value)),
+            Value::I16(value) => Some(f64::from(*value)),
+            Value::U32(value) => Some(f64::from(*value)),
+            Value::I32(value) => Some(f64::from(*value)),
+            Value::F32(value) => Some(f64::from(*value)),
+            Value::U64(value) => Some(*value as f64),
+            Value::I64(value) => Some(*value as f64),
+            Value::F64(value) => Some(*value),
+            Value::String(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::U16(value) => write!(f, "{}", value),
+            Value::I16(value) => write!(f, "{}", value),
+            Value::U32(value) => write!(f, "{}", value),
+            Value::I32(value) => write!(f, "{}", value),
+            Value::F32(value) => write!(f, "{}", value),
+            Value::U64(value) => write!(f, "{}", value),
+            Value::I64(value) => write!(f, "{}", value),
+            Value::F64(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! `#[derive(ModbusRegisters)]`的编译错误检查
+
+#[test]
+fn test_derive_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use modbus_rs::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct BitOnNumber {
+    #[modbus(bit = 3)]
+    state: u16,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use modbus_rs::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct Overlap {
+    #[modbus(offset = 0)]
+    energy: u32,
+    #[modbus(offset = 1)]
+    state: u16,
+}
+
+fn main() {}
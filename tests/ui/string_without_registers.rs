@@ -0,0 +1,8 @@
+use modbus_rs::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct MissingRegisters {
+    serial: String,
+}
+
+fn main() {}
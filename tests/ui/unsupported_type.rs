@@ -0,0 +1,8 @@
+use modbus_rs::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct Unsupported {
+    state: u8,
+}
+
+fn main() {}